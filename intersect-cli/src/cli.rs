@@ -31,6 +31,62 @@ pub enum Commands {
     },
     /// Open a document by trace
     Open { trace: String },
+    /// Crawl everything reachable from a trace and write it to a directory for offline use
+    Mirror {
+        trace: String,
+        dir: PathBuf,
+        /// how many links records deep to follow
+        #[arg(long, default_value_t = 3)]
+        depth: u32,
+        /// don't fetch author accounts
+        #[arg(long)]
+        no_accounts: bool,
+    },
+    /// Export everything reachable from a trace to a portable archive file
+    Export {
+        trace: String,
+        output: PathBuf,
+        /// how many links records deep to follow
+        #[arg(long, default_value_t = 3)]
+        depth: u32,
+    },
+    /// Re-create the documents in an archive file under the current identity
+    Import { path: PathBuf },
+    /// Publish a directory: files become fragments, directories become indexes.
+    /// re-running only uploads what changed and updates the existing indexes.
+    Publish { dir: PathBuf },
+    /// Delete a document you wrote by trace. other nodes' cached copies can't be taken back
+    Delete { trace: String },
+    /// Keep a document alive on the network by refreshing it periodically
+    Pin { trace: String },
+    /// Stop refreshing a document (including one pinned automatically)
+    Unpin { trace: String },
+    /// List pinned documents and how their last refresh went
+    Pins,
+    /// Manage local contacts (petnames for account fingerprints)
+    Contact {
+        #[command(subcommand)]
+        what: ContactCommands,
+    },
+    /// Search documents you've fetched or opened on this device
+    Search {
+        query: Vec<String>,
+        /// maximum number of results
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Follow an account by trace (requires a logged in account)
+    Follow { trace: String },
+    /// Stop following an account by trace
+    Unfollow { trace: String },
+    /// List followed accounts
+    Following,
+    /// Open a live feed of changes from followed accounts
+    Feed,
+    /// List documents written from this device that haven't reached the network yet
+    Pending,
+    /// Show how many records are open, watched and closed
+    Stats,
     /// Initiate graceful shutdown (same as ctrl+c; a second ctrl+c force-exits)
     Exit,
 }
@@ -63,8 +119,30 @@ pub enum CreateCommands {
         /// trace for the links record, if any
         #[arg(long)]
         links: Option<String>,
+        /// tag to categorise the index with (repeatable)
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+        /// extra metadata as key=value (repeatable), e.g. --meta language=en
+        #[arg(long = "meta")]
+        meta: Vec<String>,
         /// encrypt the trace with a password before printing/copying
         #[arg(long)]
         password: Option<String>,
     },
 }
+
+#[derive(Debug, Subcommand)]
+pub enum ContactCommands {
+    /// Name an account fingerprint, replacing any existing contact for it
+    Add {
+        fingerprint: String,
+        name: String,
+        /// mark the fingerprint as verified out-of-band
+        #[arg(long)]
+        verified: bool,
+    },
+    /// Forget a contact
+    Remove { fingerprint: String },
+    /// List all contacts
+    List,
+}
@@ -12,9 +12,10 @@ use arboard::Clipboard;
 use intersect_core::{documents::*, models::*, *};
 
 use crate::{
-    cli::{Cli, Commands, CreateCommands},
-    prompt::{unlock_trace, Prompt},
-    ui::panel::{AccountPanel, FragmentPanel, IndexPanel, LinksPanel, OpenPanel},
+    cli::{Cli, Commands, ContactCommands, CreateCommands},
+    prompt::{unlock_any_trace, unlock_trace, Prompt},
+    publish,
+    ui::panel::{AccountPanel, FeedPanel, FragmentPanel, IndexPanel, LinksPanel, OpenPanel},
 };
 
 pub enum Output {
@@ -73,13 +74,40 @@ pub async fn execute(
                     name,
                     fragment,
                     links,
+                    tags,
+                    meta,
                     password,
                 },
-        } => cmd_create_index(name, fragment, links, password, &intersect, &tx).await,
+        } => cmd_create_index(name, fragment, links, tags, meta, password, &intersect, &tx).await,
         Commands::Fetch { trace, output } => {
             cmd_fetch(trace, output, &intersect, &tx, prompt).await
         }
         Commands::Open { trace } => cmd_open(trace, &intersect, &tx, &panel_tx, prompt).await,
+        Commands::Mirror {
+            trace,
+            dir,
+            depth,
+            no_accounts,
+        } => cmd_mirror(trace, dir, depth, no_accounts, &intersect, &tx).await,
+        Commands::Export {
+            trace,
+            output,
+            depth,
+        } => cmd_export(trace, output, depth, &intersect, &tx).await,
+        Commands::Import { path } => cmd_import(path, &intersect, &tx).await,
+        Commands::Publish { dir } => cmd_publish(dir, &intersect, &tx).await,
+        Commands::Delete { trace } => cmd_delete(trace, &intersect, &tx, prompt).await,
+        Commands::Pin { trace } => cmd_pin(trace, true, &intersect, &tx, prompt).await,
+        Commands::Unpin { trace } => cmd_pin(trace, false, &intersect, &tx, prompt).await,
+        Commands::Pins => cmd_pins(&intersect, &tx),
+        Commands::Contact { what } => cmd_contact(what, &intersect, &tx).await,
+        Commands::Search { query, limit } => cmd_search(query, limit, &intersect, &tx),
+        Commands::Follow { trace } => cmd_follow(trace, true, &intersect, &tx, prompt).await,
+        Commands::Unfollow { trace } => cmd_follow(trace, false, &intersect, &tx, prompt).await,
+        Commands::Following => cmd_following(&intersect, &tx).await,
+        Commands::Feed => cmd_feed(&intersect, &panel_tx).await,
+        Commands::Pending => cmd_pending(&intersect, &tx),
+        Commands::Stats => cmd_stats(&intersect, &tx),
         // handled at the ui layer before reaching here
         Commands::Exit => Ok(()),
     };
@@ -108,7 +136,7 @@ async fn cmd_login(
         .ok_or_else(|| anyhow!("secret required for account login"))?
         .parse::<AccountSecret>()
         .context("invalid secret")?;
-    let typed_ref = unlock_trace(trace.into_typed::<AccountDocument>()?, prompt).await?;
+    let typed_ref = unlock_trace(trace.into_typed::<AccountDocument>()?, intersect, prompt).await?;
     intersect.login(typed_ref, secret).await?;
     tx.line("logged in");
     Ok(())
@@ -123,7 +151,7 @@ async fn cmd_create_account(
 ) -> anyhow::Result<()> {
     let (typed_ref, secret) = intersect.create_account(name, bio, None).await?;
     tx.line("account created");
-    print_trace(&typed_ref, password.as_deref(), tx)?;
+    print_trace(&typed_ref, password.as_deref(), intersect, tx)?;
     tx.line(format!("secret: {secret}"));
     Ok(())
 }
@@ -140,14 +168,17 @@ async fn cmd_create_fragment(
     let mime = FragmentMime::new(mime).context("invalid mime type")?;
     let typed_ref = intersect.create_fragment(data, mime).await?;
     tx.line("fragment created");
-    print_trace(&typed_ref, password.as_deref(), tx)?;
+    print_trace(&typed_ref, password.as_deref(), intersect, tx)?;
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn cmd_create_index(
     name: String,
     fragment: Option<String>,
     links: Option<String>,
+    tags: Vec<String>,
+    meta: Vec<String>,
     password: Option<String>,
     intersect: &Intersect,
     tx: &Tx,
@@ -155,9 +186,19 @@ async fn cmd_create_index(
     let parse_trace = |s: String| Trace::from_str(&s).context("invalid trace");
     let fragment = fragment.map(parse_trace).transpose()?;
     let links = links.map(parse_trace).transpose()?;
-    let typed_ref = intersect.create_index(name, fragment, links).await?;
+    let mut metadata = IndexMetadata::default();
+    for entry in meta {
+        let (key, value) = entry
+            .split_once('=')
+            .ok_or_else(|| anyhow!("metadata must be key=value, got '{entry}'"))?;
+        metadata = metadata.with(key.to_string(), value.to_string())?;
+    }
+    let metadata = metadata.with_tags(&tags)?;
+    let typed_ref = intersect
+        .create_index(name, fragment, links, metadata)
+        .await?;
     tx.line("index created");
-    print_trace(&typed_ref, password.as_deref(), tx)?;
+    print_trace(&typed_ref, password.as_deref(), intersect, tx)?;
     Ok(())
 }
 
@@ -171,7 +212,8 @@ async fn cmd_fetch(
     let trace = Trace::from_str(&trace).context("invalid trace")?;
     match trace.document_type() {
         DocumentType::Fragment => {
-            let r = unlock_trace(trace.into_typed::<FragmentDocument>()?, prompt).await?;
+            let r =
+                unlock_trace(trace.into_typed::<FragmentDocument>()?, intersect, prompt).await?;
             let view = intersect.fetch(&r).await?;
             match output {
                 Some(path) => {
@@ -183,7 +225,7 @@ async fn cmd_fetch(
             }
         }
         DocumentType::Account => {
-            let r = unlock_trace(trace.into_typed::<AccountDocument>()?, prompt).await?;
+            let r = unlock_trace(trace.into_typed::<AccountDocument>()?, intersect, prompt).await?;
             let view = intersect.fetch(&r).await?;
             match output {
                 Some(path) => {
@@ -195,7 +237,19 @@ async fn cmd_fetch(
             }
         }
         DocumentType::Index => {
-            let r = unlock_trace(trace.into_typed::<IndexDocument>()?, prompt).await?;
+            let r = unlock_trace(trace.into_typed::<IndexDocument>()?, intersect, prompt).await?;
+            let view = intersect.fetch(&r).await?;
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, format!("{view}"))
+                        .with_context(|| format!("failed to write {}", path.display()))?;
+                    tx.line(format!("written to {}", path.display()));
+                }
+                None => tx.line(format!("{view}")),
+            }
+        }
+        DocumentType::Links => {
+            let r = unlock_trace(trace.into_typed::<LinksDocument>()?, intersect, prompt).await?;
             let view = intersect.fetch(&r).await?;
             match output {
                 Some(path) => {
@@ -206,7 +260,6 @@ async fn cmd_fetch(
                 None => tx.line(format!("{view}")),
             }
         }
-        DocumentType::Links => return Err(anyhow!("links documents are not yet supported")),
     }
     Ok(())
 }
@@ -221,12 +274,13 @@ async fn cmd_open(
     let trace = Trace::from_str(&trace).context("invalid trace")?;
     let panel = match trace.document_type() {
         DocumentType::Account => {
-            let r = unlock_trace(trace.into_typed::<AccountDocument>()?, prompt).await?;
+            let r = unlock_trace(trace.into_typed::<AccountDocument>()?, intersect, prompt).await?;
             let doc = intersect.open(&r).await?;
-            OpenPanel::Account(AccountPanel { doc })
+            let contacts = intersect.contacts_watch();
+            OpenPanel::Account(AccountPanel { doc, contacts })
         }
         DocumentType::Index => {
-            let r = unlock_trace(trace.into_typed::<IndexDocument>()?, prompt).await?;
+            let r = unlock_trace(trace.into_typed::<IndexDocument>()?, intersect, prompt).await?;
             let doc = intersect.open(&r).await?;
             let (panel, errors) = IndexPanel::new(doc, intersect, prompt).await;
             for error in errors {
@@ -235,25 +289,384 @@ async fn cmd_open(
             OpenPanel::Index(panel)
         }
         DocumentType::Fragment => {
-            let r = unlock_trace(trace.into_typed::<FragmentDocument>()?, prompt).await?;
+            let r =
+                unlock_trace(trace.into_typed::<FragmentDocument>()?, intersect, prompt).await?;
             let view = intersect.fetch(&r).await?;
             OpenPanel::Fragment(FragmentPanel { view })
         }
-        DocumentType::Links => return Err(anyhow!("links documents are not yet supported")),
+        DocumentType::Links => {
+            let r = unlock_trace(trace.into_typed::<LinksDocument>()?, intersect, prompt).await?;
+            let doc = intersect.open(&r).await?;
+            OpenPanel::Links(LinksPanel { doc })
+        }
     };
     let _ = panel_tx.send(panel);
     Ok(())
 }
 
+async fn cmd_contact(what: ContactCommands, intersect: &Intersect, tx: &Tx) -> anyhow::Result<()> {
+    match what {
+        ContactCommands::Add {
+            fingerprint,
+            name,
+            verified,
+        } => {
+            let trust = if verified {
+                TrustLevel::Verified
+            } else {
+                TrustLevel::Known
+            };
+            intersect.set_contact(&fingerprint, name, trust).await?;
+            tx.line("contact saved");
+        }
+        ContactCommands::Remove { fingerprint } => {
+            if intersect.remove_contact(&fingerprint).await? {
+                tx.line("contact removed");
+            } else {
+                tx.line("no contact with that fingerprint");
+            }
+        }
+        ContactCommands::List => {
+            let contacts = intersect.contacts();
+            if contacts.is_empty() {
+                tx.line("no contacts");
+            }
+            for (fingerprint, contact) in contacts.iter() {
+                let trust = match contact.trust() {
+                    TrustLevel::Verified => "verified",
+                    TrustLevel::Known => "known",
+                };
+                tx.line(format!(
+                    "{} #{fingerprint} ({trust})",
+                    contact.petname().as_ref()
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn cmd_search(
+    query: Vec<String>,
+    limit: usize,
+    intersect: &Intersect,
+    tx: &Tx,
+) -> anyhow::Result<()> {
+    if !intersect.search_enabled() {
+        tx.line("local search is off, start intersect with --search to turn it on");
+        return Ok(());
+    }
+    let results = intersect.search(&query.join(" "), limit);
+    if results.is_empty() {
+        tx.line("no results");
+    }
+    for result in results {
+        let title = if result.title.is_empty() {
+            "(untitled)"
+        } else {
+            &result.title
+        };
+        tx.line(format!("{title} — {}", result.trace));
+        if let Some(snippet) = result.snippet {
+            tx.line(format!("  {snippet}"));
+        }
+    }
+    Ok(())
+}
+
+async fn cmd_follow(
+    trace: String,
+    follow: bool,
+    intersect: &Intersect,
+    tx: &Tx,
+    prompt: &impl Prompt,
+) -> anyhow::Result<()> {
+    let trace = Trace::from_str(&trace).context("invalid trace")?;
+    let r = unlock_trace(trace.into_typed::<AccountDocument>()?, intersect, prompt).await?;
+    if follow {
+        intersect.follow(&r).await?;
+        tx.line("followed");
+    } else if intersect.unfollow(&r).await? {
+        tx.line("unfollowed");
+    } else {
+        tx.line("not following that account");
+    }
+    Ok(())
+}
+
+async fn cmd_delete(
+    trace: String,
+    intersect: &Intersect,
+    tx: &Tx,
+    prompt: &impl Prompt,
+) -> anyhow::Result<()> {
+    let trace = Trace::from_str(&trace).context("invalid trace")?;
+    // protected traces need their password before there's anything to delete with
+    let trace = unlock_any_trace(trace, intersect, prompt).await?;
+    let confirm = prompt
+        .ask(&format!(
+            "delete this {:?}? this can't be undone. type 'yes' to confirm: ",
+            trace.document_type()
+        ))
+        .await;
+    if confirm.as_deref().map(str::trim) != Some("yes") {
+        tx.line("cancelled");
+        return Ok(());
+    }
+    intersect.delete(&trace).await?;
+    tx.line("deleted");
+    Ok(())
+}
+
+async fn cmd_pin(
+    trace: String,
+    pin: bool,
+    intersect: &Intersect,
+    tx: &Tx,
+    prompt: &impl Prompt,
+) -> anyhow::Result<()> {
+    let trace = Trace::from_str(&trace).context("invalid trace")?;
+    if !pin {
+        // unpinning only needs the record, no need to ask for a password
+        match intersect.unpin(&trace).await? {
+            true => tx.line("unpinned"),
+            false => tx.line("wasn't pinned"),
+        }
+        return Ok(());
+    }
+    let trace = unlock_any_trace(trace, intersect, prompt).await?;
+    intersect.pin(&trace).await?;
+    tx.line("pinned, it'll be refreshed in the background from now on");
+    Ok(())
+}
+
+fn cmd_pins(intersect: &Intersect, tx: &Tx) -> anyhow::Result<()> {
+    let pins = intersect.pins();
+    if pins.is_empty() {
+        tx.line("nothing pinned");
+    }
+    let now = Timestamp::now();
+    for pinned in pins {
+        let status = match pinned.status {
+            PinStatus::Waiting => "not refreshed yet".to_string(),
+            PinStatus::Refreshed { at, repushed, lost } => {
+                let mut status = format!("refreshed {}", at.ago(now));
+                if repushed > 0 {
+                    status.push_str(&format!(", {repushed} subkeys pushed again"));
+                }
+                if lost > 0 {
+                    status.push_str(&format!(", {lost} subkeys lost"));
+                }
+                status
+            }
+            PinStatus::Failed(e) => format!("failed: {e}"),
+        };
+        let kind = if pinned.pin.own() { "own" } else { "pinned" };
+        tx.line(format!(
+            "{:?} ({kind}, {status}): {}",
+            pinned.pin.trace().document_type(),
+            pinned.pin.trace()
+        ));
+    }
+    Ok(())
+}
+
+async fn cmd_following(intersect: &Intersect, tx: &Tx) -> anyhow::Result<()> {
+    let following = intersect.following().await?;
+    if following.is_empty() {
+        tx.line("not following anyone");
+    }
+    for trace in following {
+        tx.line(trace.to_string());
+    }
+    Ok(())
+}
+
+async fn cmd_feed(intersect: &Intersect, panel_tx: &SyncSender<OpenPanel>) -> anyhow::Result<()> {
+    let feed = intersect.feed().await?;
+    let contacts = intersect.contacts_watch();
+    let _ = panel_tx.send(OpenPanel::Feed(FeedPanel { feed, contacts }));
+    Ok(())
+}
+
+async fn cmd_mirror(
+    trace: String,
+    dir: std::path::PathBuf,
+    depth: u32,
+    no_accounts: bool,
+    intersect: &Intersect,
+    tx: &Tx,
+) -> anyhow::Result<()> {
+    let trace = Trace::from_str(&trace).context("invalid trace")?;
+    tx.line("crawling...");
+    let crawl = intersect
+        .crawl(&trace, depth, |t| {
+            !(no_accounts && *t.document_type() == DocumentType::Account)
+        })
+        .await?;
+
+    std::fs::create_dir_all(&dir).with_context(|| format!("failed to create {}", dir.display()))?;
+
+    // manifest.toml maps every written file back to the trace it came from
+    let mut manifest = String::new();
+    for node in &crawl.nodes {
+        let (kind, ext, contents) = match &node.view {
+            CrawledView::Index(view) => ("index", "md", format!("{view}").into_bytes()),
+            CrawledView::Links(view) => ("links", "md", format!("{view}").into_bytes()),
+            CrawledView::Account(view) => ("account", "md", format!("{view}").into_bytes()),
+            CrawledView::Fragment(view) => (
+                "fragment",
+                mime_extension(view.mime().as_ref()),
+                view.data().to_vec(),
+            ),
+        };
+        // record keys contain ':' which isn't allowed in file names everywhere
+        let file = format!("{kind}-{}.{ext}", node.id.replace(':', "-"));
+        let path = dir.join(&file);
+        std::fs::write(&path, contents)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+
+        manifest.push_str(&format!(
+            "[[documents]]\nfile = {}\ntrace = {}\ndepth = {}\n\n",
+            toml::Value::String(file),
+            toml::Value::String(node.trace.to_string()),
+            node.depth
+        ));
+    }
+    let path = dir.join("manifest.toml");
+    std::fs::write(&path, manifest)
+        .with_context(|| format!("failed to write {}", path.display()))?;
+
+    for failure in &crawl.failures {
+        tx.error(format!("skipped {}: {}", failure.trace, failure.error));
+    }
+    tx.line(format!(
+        "mirrored {} documents to {} ({} skipped)",
+        crawl.nodes.len(),
+        dir.display(),
+        crawl.failures.len()
+    ));
+    Ok(())
+}
+
+async fn cmd_export(
+    trace: String,
+    output: std::path::PathBuf,
+    depth: u32,
+    intersect: &Intersect,
+    tx: &Tx,
+) -> anyhow::Result<()> {
+    let trace = Trace::from_str(&trace).context("invalid trace")?;
+    tx.line("exporting...");
+    let (archive, failures) = intersect.export(&trace, depth).await?;
+    std::fs::write(&output, archive.to_bytes()?)
+        .with_context(|| format!("failed to write {}", output.display()))?;
+
+    for failure in &failures {
+        tx.error(format!("skipped {}: {}", failure.trace, failure.error));
+    }
+    tx.line(format!(
+        "exported {} documents to {}",
+        archive.entries().len(),
+        output.display()
+    ));
+    Ok(())
+}
+
+async fn cmd_import(
+    path: std::path::PathBuf,
+    intersect: &Intersect,
+    tx: &Tx,
+) -> anyhow::Result<()> {
+    let bytes =
+        std::fs::read(&path).with_context(|| format!("failed to read {}", path.display()))?;
+    let archive = Archive::from_bytes(&bytes).context("invalid archive")?;
+    tx.line(format!(
+        "importing {} documents...",
+        archive.entries().len()
+    ));
+    let root = match intersect.import(&archive).await {
+        Ok(root) => root,
+        Err(IntersectError::PartialImport { created, error }) => {
+            // left on the network, so say what they are in case they're worth cleaning up
+            for trace in &created {
+                tx.line(format!("created before failing: {trace}"));
+            }
+            return Err((*error).into());
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let root_str = root.to_string();
+    tx.line(format!("imported, new root: {root_str}"));
+    copy_to_clipboard(&root_str, tx);
+    Ok(())
+}
+
+async fn cmd_publish(
+    dir: std::path::PathBuf,
+    intersect: &Intersect,
+    tx: &Tx,
+) -> anyhow::Result<()> {
+    tx.line(format!("publishing {}...", dir.display()));
+    let root = publish::publish(&dir, intersect, tx).await?;
+    let root_str = root.to_string();
+    tx.line(format!("published: {root_str}"));
+    copy_to_clipboard(&root_str, tx);
+    Ok(())
+}
+
+fn cmd_pending(intersect: &Intersect, tx: &Tx) -> anyhow::Result<()> {
+    let pending = intersect.pending();
+    if pending.is_empty() {
+        tx.line("everything is synced");
+    }
+    for doc in pending {
+        tx.line(format!(
+            "{:?} ({} subkeys waiting): {}",
+            doc.trace.document_type(),
+            doc.subkeys,
+            doc.trace
+        ));
+    }
+    Ok(())
+}
+
+fn cmd_stats(intersect: &Intersect, tx: &Tx) -> anyhow::Result<()> {
+    let stats = intersect.record_stats();
+    tx.line(format!(
+        "{} records open ({} in use, {} watched), {} closed while idle",
+        stats.open, stats.in_use, stats.watched, stats.closed
+    ));
+    Ok(())
+}
+
 // ==== helpers ====
 
+fn mime_extension(mime: &str) -> &'static str {
+    match mime.split(';').next().unwrap_or_default().trim() {
+        "text/markdown" => "md",
+        "text/plain" => "txt",
+        "text/html" => "html",
+        "application/json" => "json",
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "application/pdf" => "pdf",
+        _ => "bin",
+    }
+}
+
 fn print_trace<D: Document>(
     typed_ref: &TypedReference<D>,
     password: Option<&str>,
+    intersect: &Intersect,
     tx: &Tx,
 ) -> anyhow::Result<()> {
     let (trace, kind) = match password {
-        Some(pw) => (typed_ref.to_protected_trace(pw)?, "trace (protected)"),
+        Some(pw) => (
+            typed_ref.to_protected_trace(intersect, pw)?,
+            "trace (protected)",
+        ),
         None => (typed_ref.to_unlocked_trace(), "trace (unlocked)"),
     };
     let trace_str = trace.to_string();
@@ -1,6 +1,7 @@
 #![recursion_limit = "512"]
 
 use std::{
+    path::PathBuf,
     process::ExitCode,
     sync::{atomic::AtomicBool, Arc, Mutex},
 };
@@ -24,6 +25,7 @@ use crate::{
 mod cli;
 mod commands;
 mod prompt;
+mod publish;
 mod stderr;
 mod ui;
 
@@ -34,6 +36,48 @@ struct Args {
     #[arg(short = 'e', long)]
     ephemeral: bool,
 
+    /// keep a local search index of fetched documents (stored unencrypted in the data dir)
+    #[arg(long)]
+    search: bool,
+
+    /// where to keep the node's data
+    #[arg(long, default_value = "./.intersect")]
+    data_dir: PathBuf,
+
+    /// namespace for the node's stores, lets several nodes share a data dir
+    #[arg(long, default_value = "intersect")]
+    namespace: String,
+
+    /// bootstrap node to use instead of the defaults (can be given more than once)
+    #[arg(long)]
+    bootstrap: Vec<String>,
+
+    /// key for a private network, only peers with the same key can be reached
+    #[arg(long)]
+    network_key: Option<String>,
+
+    /// only listen on 127.0.0.1 at this port, for private test networks
+    #[arg(long)]
+    loopback_port: Option<u16>,
+
+    /// only read documents already cached on this device, never from the network
+    #[arg(long)]
+    cache_only: bool,
+
+    /// keep cached documents on disk, so --cache-only can read them after a restart.
+    /// they're stored decrypted
+    #[arg(long)]
+    persist_cache: bool,
+
+    /// don't read an index's fragment and links ahead of time when it's opened
+    #[arg(long)]
+    no_prefetch: bool,
+
+    /// json file with veilid config overrides (a partial veilid config, e.g. {"network": {"upnp": false}}),
+    /// merged over everything else
+    #[arg(long)]
+    config: Option<PathBuf>,
+
     /// run a single command instead of launching the tui
     #[arg(last = true)]
     command: Vec<String>,
@@ -41,8 +85,30 @@ struct Args {
 #[tokio::main]
 async fn main() -> Result<(), ExitCode> {
     let args = Args::parse();
+    let config_overrides = match args.config.map(std::fs::read_to_string).transpose() {
+        Ok(overrides) => overrides,
+        Err(e) => {
+            eprintln!("error: failed to read config file: {e}");
+            return Err(ExitCode::FAILURE);
+        }
+    };
     let connection_params = ConnectionParams {
         ephemeral: args.ephemeral,
+        local_search: args.search,
+        data_dir: args.data_dir,
+        namespace: args.namespace,
+        bootstrap: args.bootstrap,
+        network_key: args.network_key,
+        loopback_port: args.loopback_port,
+        config_overrides,
+        retry: Default::default(),
+        cache_only: args.cache_only,
+        persist_cache: args.persist_cache,
+        prefetch: if args.no_prefetch {
+            None
+        } else {
+            ConnectionParams::default().prefetch
+        },
     };
 
     if args.command.is_empty() {
@@ -133,7 +199,10 @@ async fn run_command(
 
     // TODO: i think this isn't necessary anymore but i'm nervous to delete it
     // intersect.close _should_ wait for everything to flush but it's scary to rely on
-    intersect.wait_for_attachment().await;
+    // nothing to wait for if nothing gets read from the network
+    if !intersect.cache_only() {
+        intersect.wait_for_attachment().await;
+    }
 
     let (output_tx, output_rx) = Tx::new_channel();
     // no panel support in single-command mode. channel is created but never drained
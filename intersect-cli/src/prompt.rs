@@ -4,7 +4,11 @@ use std::sync::{
 };
 
 use anyhow::{anyhow, Context};
-use intersect_core::{Document, TypedTrace, TypedReference};
+use intersect_core::{
+    documents::{AccountDocument, FragmentDocument, IndexDocument, LinksDocument},
+    models::{DocumentType, Trace},
+    Document, Intersect, TypedReference, TypedTrace,
+};
 
 use cursive::{
     view::Nameable,
@@ -31,6 +35,7 @@ impl Prompt for StdinPrompt {
 /// resolves an TypedTrace to a TypedReference, prompting for a password if needed
 pub(crate) async fn unlock_trace<D: Document>(
     opened: TypedTrace<D>,
+    intersect: &Intersect,
     prompt: &impl Prompt,
 ) -> anyhow::Result<TypedReference<D>> {
     match opened {
@@ -43,11 +48,43 @@ pub(crate) async fn unlock_trace<D: Document>(
                 .ask("password: ")
                 .await
                 .ok_or_else(|| anyhow!("cancelled"))?;
-            protected_ref.unlock(&password).context("wrong password")
+            protected_ref
+                .unlock(intersect, &password)
+                .context("wrong password")
         }
     }
 }
 
+/// same as `unlock_trace` for a trace of any document type, giving back an unlocked trace
+pub(crate) async fn unlock_any_trace(
+    trace: Trace,
+    intersect: &Intersect,
+    prompt: &impl Prompt,
+) -> anyhow::Result<Trace> {
+    Ok(match trace.document_type() {
+        DocumentType::Index => {
+            unlock_trace(trace.into_typed::<IndexDocument>()?, intersect, prompt)
+                .await?
+                .to_unlocked_trace()
+        }
+        DocumentType::Fragment => {
+            unlock_trace(trace.into_typed::<FragmentDocument>()?, intersect, prompt)
+                .await?
+                .to_unlocked_trace()
+        }
+        DocumentType::Links => {
+            unlock_trace(trace.into_typed::<LinksDocument>()?, intersect, prompt)
+                .await?
+                .to_unlocked_trace()
+        }
+        DocumentType::Account => {
+            unlock_trace(trace.into_typed::<AccountDocument>()?, intersect, prompt)
+                .await?
+                .to_unlocked_trace()
+        }
+    })
+}
+
 /// tui impl: pushes a cursive dialog and awaits the result via a oneshot channel
 pub struct CursivePrompt {
     pub cb_sink: cursive::CbSink,
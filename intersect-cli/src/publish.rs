@@ -0,0 +1,348 @@
+//! `publish <dir>`: mirrors a local directory tree onto the network.
+//! files become fragments, directories become indexes with a links record listing their children.
+//! a manifest in the directory remembers what was published, so re-running only uploads changed
+//! files and updates the existing indexes in place (their traces stay the same).
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::{anyhow, Context};
+use intersect_core::{documents::*, models::*, *};
+
+use crate::commands::Tx;
+
+const MANIFEST_NAME: &str = ".intersect-publish";
+// picked as the directory index's content, in order of preference
+const INDEX_FILES: [&str; 3] = ["index.md", "README.md", "readme.md"];
+
+#[derive(Default)]
+struct Manifest {
+    // relative path -> (content hash, fragment trace)
+    files: HashMap<PathBuf, (String, Trace)>,
+    // relative path -> (index trace, links trace)
+    dirs: HashMap<PathBuf, (Trace, Trace)>,
+}
+
+impl Manifest {
+    fn load(path: &Path) -> anyhow::Result<Self> {
+        let mut manifest = Self::default();
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(manifest),
+            Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+        };
+        for (n, line) in contents.lines().enumerate() {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            // path goes last so it can contain anything but a newline
+            let fields: Vec<&str> = line.splitn(4, '\t').collect();
+            let parse = |s: &str| Trace::from_str(s).context("invalid trace");
+            let result = match fields[..] {
+                ["file", hash, trace, rel] => parse(trace).map(|trace| {
+                    manifest
+                        .files
+                        .insert(PathBuf::from(rel), (hash.to_string(), trace));
+                }),
+                ["dir", index, links, rel] => parse(index).and_then(|index| {
+                    manifest
+                        .dirs
+                        .insert(PathBuf::from(rel), (index, parse(links)?));
+                    Ok(())
+                }),
+                _ => Err(anyhow!("unrecognised entry")),
+            };
+            result.with_context(|| format!("{} line {}", path.display(), n + 1))?;
+        }
+        Ok(manifest)
+    }
+
+    fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let mut out = String::from(
+            "# written by `intersect publish`, don't edit by hand.\n\
+             # contains unlocked traces, anyone with this file can read everything published.\n",
+        );
+        let files: BTreeMap<_, _> = self.files.iter().collect();
+        for (rel, (hash, trace)) in files {
+            out.push_str(&format!("file\t{hash}\t{trace}\t{}\n", rel.display()));
+        }
+        let dirs: BTreeMap<_, _> = self.dirs.iter().collect();
+        for (rel, (index, links)) in dirs {
+            out.push_str(&format!("dir\t{index}\t{links}\t{}\n", rel.display()));
+        }
+        std::fs::write(path, out).with_context(|| format!("failed to write {}", path.display()))
+    }
+}
+
+#[derive(Default)]
+struct Counts {
+    uploaded: usize,
+    unchanged: usize,
+    created: usize,
+    updated: usize,
+}
+
+/// publishes `root` and returns the trace of its index
+pub async fn publish(root: &Path, intersect: &Intersect, tx: &Tx) -> anyhow::Result<Trace> {
+    // indexes are written with the current keypair, and an anonymous one changes every session
+    if intersect.account().is_none() {
+        return Err(anyhow!(
+            "publishing requires a logged in account, so later runs can update what was published"
+        ));
+    }
+
+    let manifest_path = root.join(MANIFEST_NAME);
+    let old = Manifest::load(&manifest_path)?;
+    let mut new = Manifest::default();
+    let mut counts = Counts::default();
+
+    let mut dirs = Vec::new();
+    walk(root, PathBuf::new(), &mut dirs)?;
+
+    // deepest directories first, so every subdirectory's index exists before its parent's links
+    dirs.sort_by_key(|(rel, _)| std::cmp::Reverse(rel.components().count()));
+    for (rel, children) in dirs {
+        let mut links = Vec::new();
+        let mut content = None;
+        for (name, is_dir) in children {
+            let child = rel.join(&name);
+            let trace = if is_dir {
+                new.dirs
+                    .get(&child)
+                    .map(|(index, _)| index.clone())
+                    .ok_or_else(|| anyhow!("{} wasn't published", child.display()))?
+            } else {
+                let trace = publish_file(root, &child, &old, &mut new, &mut counts, intersect)
+                    .await
+                    .with_context(|| format!("failed to publish {}", child.display()))?;
+                if content.is_none() && INDEX_FILES.contains(&name.as_str()) {
+                    content = Some(trace.clone());
+                }
+                trace
+            };
+            links.push(Link::new(trace, Some(LinkName::new(name)?)));
+        }
+
+        let name = match rel.file_name() {
+            Some(name) => name.to_string_lossy().into_owned(),
+            None => dir_name(root),
+        };
+        let entry = publish_dir(
+            name,
+            content,
+            links,
+            old.dirs.get(&rel),
+            &mut counts,
+            intersect,
+        )
+        .await
+        .with_context(|| format!("failed to publish directory {}", rel.display()))?;
+        new.dirs.insert(rel, entry);
+    }
+
+    new.save(&manifest_path)?;
+    tx.line(format!(
+        "{} files uploaded, {} unchanged. {} indexes created, {} updated",
+        counts.uploaded, counts.unchanged, counts.created, counts.updated
+    ));
+
+    let (index, _) = new
+        .dirs
+        .get(Path::new(""))
+        .ok_or_else(|| anyhow!("root directory wasn't published"))?;
+    Ok(index.clone())
+}
+
+/// collects every directory (relative to `base`) along with its children, sorted by name.
+/// dotfiles are skipped, which also keeps the manifest and things like .git out.
+/// so are symlinked directories, symlinked files are read through.
+fn walk(
+    base: &Path,
+    rel: PathBuf,
+    out: &mut Vec<(PathBuf, Vec<(String, bool)>)>,
+) -> anyhow::Result<()> {
+    let path = base.join(&rel);
+    let mut children = Vec::new();
+    let entries =
+        std::fs::read_dir(&path).with_context(|| format!("failed to read {}", path.display()))?;
+    for entry in entries {
+        let entry = entry?;
+        let Ok(name) = entry.file_name().into_string() else {
+            continue;
+        };
+        if name.starts_with('.') {
+            continue;
+        }
+        // follows symlinks, dangling ones are skipped
+        let Ok(metadata) = std::fs::metadata(entry.path()) else {
+            continue;
+        };
+        let is_dir = metadata.is_dir();
+        // symlinked files are published like any other, but linked directories could loop
+        if is_dir && entry.file_type()?.is_symlink() {
+            continue;
+        }
+        // fragments can't be empty
+        if !is_dir && metadata.len() == 0 {
+            continue;
+        }
+        if is_dir {
+            walk(base, rel.join(&name), out)?;
+        }
+        children.push((name, is_dir));
+    }
+    children.sort();
+    out.push((rel, children));
+    Ok(())
+}
+
+async fn publish_file(
+    root: &Path,
+    rel: &Path,
+    old: &Manifest,
+    new: &mut Manifest,
+    counts: &mut Counts,
+    intersect: &Intersect,
+) -> anyhow::Result<Trace> {
+    let data = std::fs::read(root.join(rel))?;
+    let hash = intersect.content_hash(&data);
+
+    let trace = match old.files.get(rel) {
+        Some((old_hash, trace)) if *old_hash == hash => {
+            counts.unchanged += 1;
+            trace.clone()
+        }
+        _ => {
+            let mime = FragmentMime::new(mime_for_path(rel).to_string())?;
+            let typed_ref = intersect.create_fragment(data, mime).await?;
+            counts.uploaded += 1;
+            typed_ref.to_unlocked_trace()
+        }
+    };
+    new.files.insert(rel.to_owned(), (hash, trace.clone()));
+    Ok(trace)
+}
+
+async fn publish_dir(
+    name: String,
+    content: Option<Trace>,
+    links: Vec<Link>,
+    existing: Option<&(Trace, Trace)>,
+    counts: &mut Counts,
+    intersect: &Intersect,
+) -> anyhow::Result<(Trace, Trace)> {
+    let Some((index_trace, links_trace)) = existing else {
+        let links_ref = intersect.create_links(links).await?;
+        let links_trace = links_ref.to_unlocked_trace();
+        let index_ref = intersect
+            .create_index(
+                name,
+                content,
+                Some(links_trace.clone()),
+                IndexMetadata::default(),
+            )
+            .await?;
+        counts.created += 1;
+        return Ok((index_ref.to_unlocked_trace(), links_trace));
+    };
+
+    let index_ref = index_trace
+        .clone()
+        .into_typed::<IndexDocument>()?
+        .into_unlocked()?;
+    let links_ref = links_trace
+        .clone()
+        .into_typed::<LinksDocument>()?
+        .into_unlocked()?;
+    let index = intersect.open(&index_ref).await?;
+    let links_doc = intersect.open(&links_ref).await?;
+    let current_index = index.updates.borrow().clone()?;
+    let current_links = links_doc.updates.borrow().clone()?;
+
+    let mut changed = false;
+    // keep whatever prefix is still the same, then rewrite the rest
+    let keep = current_links
+        .links()
+        .iter()
+        .zip(&links)
+        .take_while(|(a, b)| a == b)
+        .count();
+    for position in (keep..current_links.links().len()).rev() {
+        intersect
+            .update(&links_doc, LinksUpdate::Remove(position))
+            .await
+            .map_err(explain)?;
+        changed = true;
+    }
+    for link in links.into_iter().skip(keep) {
+        intersect
+            .update(&links_doc, LinksUpdate::Add(link))
+            .await
+            .map_err(explain)?;
+        changed = true;
+    }
+
+    if current_index.fragment() != content.as_ref() {
+        intersect
+            .update(&index, IndexUpdate::Fragment(content))
+            .await
+            .map_err(explain)?;
+        changed = true;
+    }
+    if current_index.name().as_ref() != name {
+        intersect
+            .update(&index, IndexUpdate::Name(IndexName::new(name)?))
+            .await
+            .map_err(explain)?;
+        changed = true;
+    }
+
+    if changed {
+        counts.updated += 1;
+    }
+    Ok((index_trace.clone(), links_trace.clone()))
+}
+
+// updates only work with the account that published in the first place
+fn explain(e: IntersectError) -> anyhow::Error {
+    match e.record_error() {
+        Some(RecordError::NotWriter) => anyhow!(
+            "{e}. it was published from a different account, log in with that one \
+             or remove {MANIFEST_NAME} to publish it again from scratch"
+        ),
+        _ => e.into(),
+    }
+}
+
+fn dir_name(root: &Path) -> String {
+    root.canonicalize()
+        .ok()
+        .and_then(|p| p.file_name().map(|n| n.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| "published".to_string())
+}
+
+fn mime_for_path(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "md" | "markdown" => "text/markdown",
+        "txt" => "text/plain",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "csv" => "text/csv",
+        "json" => "application/json",
+        "toml" => "application/toml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "pdf" => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
@@ -1,10 +1,16 @@
 use cursive::{view::Nameable, views::TextView, Cursive};
-use intersect_core::{documents::AccountDocument, OpenDocument};
+use intersect_core::{
+    documents::{AccountDocument, AccountView},
+    models::{ContactStatus, Contacts, TrustLevel},
+    OpenDocument,
+};
+use tokio::sync::watch;
 
-use super::{subview, Panel};
+use super::{error_label, subview, Panel};
 
 pub struct AccountPanel {
     pub doc: OpenDocument<AccountDocument>,
+    pub contacts: watch::Receiver<Contacts>,
 }
 
 impl Panel for AccountPanel {
@@ -20,14 +26,15 @@ impl Panel for AccountPanel {
 
     fn has_updates(&self) -> bool {
         self.doc.updates.has_changed().unwrap_or(false)
+            || self.contacts.has_changed().unwrap_or(false)
     }
 
     fn build_view(&mut self, id: usize) -> Box<dyn cursive::View> {
-        Box::new(TextView::new(render(&mut self.doc)).with_name(subview(id, "content")))
+        Box::new(TextView::new(self.render()).with_name(subview(id, "content")))
     }
 
     fn make_update(&mut self, id: usize) -> Box<dyn FnOnce(&mut Cursive)> {
-        let content = render(&mut self.doc);
+        let content = self.render();
         let name = subview(id, "content");
         Box::new(move |s| {
             s.call_on_name(&name, |v: &mut TextView| v.set_content(content));
@@ -35,9 +42,32 @@ impl Panel for AccountPanel {
     }
 }
 
-fn render(doc: &mut OpenDocument<AccountDocument>) -> String {
-    match &*doc.updates.borrow_and_update() {
-        Ok(view) => format!("{view}"),
-        Err(e) => format!("error: {e}"),
+impl AccountPanel {
+    fn render(&mut self) -> String {
+        let contacts = self.contacts.borrow_and_update().clone();
+        match &*self.doc.updates.borrow_and_update() {
+            Ok(view) => render_with_contact(view, &contacts),
+            Err(e) => error_label(e),
+        }
     }
 }
+
+/// renders an account view with a contact line above it.
+/// shared with the index panel's author section.
+pub(crate) fn render_with_contact(view: &AccountView, contacts: &Contacts) -> String {
+    let status = match view.contact_status(contacts) {
+        ContactStatus::Known(contact) => match contact.trust() {
+            TrustLevel::Verified => format!("✓ verified contact: {}", contact.petname().as_ref()),
+            TrustLevel::Known => format!("contact: {}", contact.petname().as_ref()),
+        },
+        ContactStatus::NameConflict {
+            contact,
+            fingerprint,
+        } => format!(
+            "⚠ warning: name matches your contact \"{}\" (#{fingerprint}), but the fingerprint is different",
+            contact.petname().as_ref()
+        ),
+        ContactStatus::Unknown => "not in your contacts".to_string(),
+    };
+    format!("{status}\n\n{view}")
+}
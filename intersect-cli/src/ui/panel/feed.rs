@@ -0,0 +1,80 @@
+use cursive::{view::Nameable, views::TextView, Cursive};
+use intersect_core::{
+    models::{Contacts, Timestamp},
+    Feed, FeedChange, FeedItem,
+};
+use tokio::sync::watch;
+
+use super::{subview, Panel};
+
+pub struct FeedPanel {
+    pub feed: Feed,
+    pub contacts: watch::Receiver<Contacts>,
+}
+
+impl Panel for FeedPanel {
+    fn title(&self) -> String {
+        "feed".to_string()
+    }
+
+    fn has_updates(&self) -> bool {
+        self.feed.items.has_changed().unwrap_or(false)
+            || self.contacts.has_changed().unwrap_or(false)
+    }
+
+    fn build_view(&mut self, id: usize) -> Box<dyn cursive::View> {
+        Box::new(TextView::new(self.render()).with_name(subview(id, "content")))
+    }
+
+    fn make_update(&mut self, id: usize) -> Box<dyn FnOnce(&mut Cursive)> {
+        let content = self.render();
+        let name = subview(id, "content");
+        Box::new(move |s| {
+            s.call_on_name(&name, |v: &mut TextView| v.set_content(content));
+        })
+    }
+}
+
+impl FeedPanel {
+    fn render(&mut self) -> String {
+        let contacts = self.contacts.borrow_and_update().clone();
+        let items = self.feed.items.borrow_and_update();
+        if items.is_empty() {
+            return "nothing yet. follow some accounts with `follow <trace>`".to_string();
+        }
+        items
+            .iter()
+            .map(|item| render_item(item, &items, &contacts))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+fn render_item(item: &FeedItem, items: &[FeedItem], contacts: &Contacts) -> String {
+    let age = item.timestamp.ago(Timestamp::now());
+    // home changes don't carry the account itself, so borrow the latest profile we've seen for it
+    let profile = match &item.change {
+        FeedChange::Account(view) => Some(view),
+        FeedChange::Home(_) => items.iter().find_map(|i| match &i.change {
+            FeedChange::Account(view) if i.account == item.account => Some(view),
+            _ => None,
+        }),
+    };
+    let who = match profile {
+        Some(view) => {
+            // prefer our own petname over whatever the account calls itself
+            let fingerprint = view.public_key().fingerprint();
+            let name = contacts
+                .get(&fingerprint)
+                .map(|c| c.petname().as_ref().to_owned())
+                .or_else(|| view.name().map(|n| n.as_ref().to_owned()))
+                .unwrap_or("anonymous".to_string());
+            format!("{name} #{fingerprint}")
+        }
+        None => "?".to_string(),
+    };
+    match &item.change {
+        FeedChange::Account(view) => format!("[{age}] {who} updated their profile\n{view}"),
+        FeedChange::Home(index) => format!("[{age}] {who} updated their home\n{index}"),
+    }
+}
@@ -5,17 +5,22 @@ use cursive::{
 };
 use intersect_core::{
     documents::{AccountDocument, FragmentDocument, FragmentView, IndexDocument},
+    models::Contacts,
     Intersect, OpenDocument,
 };
+use tokio::sync::watch;
 
 use crate::prompt::{unlock_trace, Prompt};
 
-use super::{subview, Panel};
+use super::{
+    account::render_with_contact, error_label, status_changed, status_label, subview, Panel,
+};
 
 pub struct IndexPanel {
     pub doc: OpenDocument<IndexDocument>,
     pub fragment: Option<FragmentView>,
     pub author: Option<OpenDocument<AccountDocument>>,
+    pub contacts: watch::Receiver<Contacts>,
 }
 
 impl IndexPanel {
@@ -28,6 +33,7 @@ impl IndexPanel {
         prompt: &impl Prompt,
     ) -> (Self, Vec<String>) {
         let mut errors = Vec::new();
+        let contacts = intersect.contacts_watch();
 
         // borrow in its own block so doc isn't held when we might move it below
         let view_result = {
@@ -47,18 +53,19 @@ impl IndexPanel {
                         doc,
                         fragment: None,
                         author: None,
+                        contacts,
                     },
                     errors,
                 );
             }
         };
 
-        // fragment is immutable, so just fetch it
+        // fragment is immutable, so a cached copy (likely prefetched with the index) is as good as any
         let fragment = if let Some(trace) = view.fragment() {
             let result: anyhow::Result<_> = async {
                 let opened = trace.clone().into_typed::<FragmentDocument>()?;
-                let r = unlock_trace(opened, prompt).await?;
-                Ok(intersect.fetch(&r).await?)
+                let r = unlock_trace(opened, intersect, prompt).await?;
+                Ok(intersect.fetch_cached(&r).await?)
             }
             .await;
             match result {
@@ -76,7 +83,7 @@ impl IndexPanel {
         let author = if let Some(trace) = view.author() {
             let result: anyhow::Result<_> = async {
                 let opened = trace.clone().into_typed::<AccountDocument>()?;
-                let r = unlock_trace(opened, prompt).await?;
+                let r = unlock_trace(opened, intersect, prompt).await?;
                 Ok(intersect.open(&r).await?)
             }
             .await;
@@ -96,6 +103,7 @@ impl IndexPanel {
                 doc,
                 fragment,
                 author,
+                contacts,
             },
             errors,
         )
@@ -112,22 +120,29 @@ impl Panel for IndexPanel {
 
     fn has_updates(&self) -> bool {
         self.doc.updates.has_changed().unwrap_or(false)
+            || status_changed(&self.doc)
             || self
                 .author
                 .as_ref()
                 .map(|a| a.updates.has_changed().unwrap_or(false))
                 .unwrap_or(false)
+            || self.contacts.has_changed().unwrap_or(false)
     }
 
     fn build_view(&mut self, id: usize) -> Box<dyn cursive::View> {
         let mut layout = LinearLayout::vertical();
 
+        let sync = status_label(&mut self.doc);
+        layout.add_child(TextView::new(sync).with_name(subview(id, "sync")));
         layout
             .add_child(TextView::new(render_index(&mut self.doc)).with_name(subview(id, "index")));
 
+        let contacts = self.contacts.borrow_and_update().clone();
         if let Some(author) = self.author.as_mut() {
             layout.add_child(TextView::new("\n── author ──"));
-            layout.add_child(TextView::new(render_author(author)).with_name(subview(id, "author")));
+            layout.add_child(
+                TextView::new(render_author(author, &contacts)).with_name(subview(id, "author")),
+            );
         }
 
         if let Some(fragment) = &self.fragment {
@@ -140,13 +155,20 @@ impl Panel for IndexPanel {
     }
 
     fn make_update(&mut self, id: usize) -> Box<dyn FnOnce(&mut Cursive)> {
+        let sync = status_label(&mut self.doc);
         let index_content = render_index(&mut self.doc);
-        let author_content = self.author.as_mut().map(render_author);
+        let contacts = self.contacts.borrow_and_update().clone();
+        let author_content = self
+            .author
+            .as_mut()
+            .map(|author| render_author(author, &contacts));
 
+        let sync_name = subview(id, "sync");
         let index_name = subview(id, "index");
         let author_name = subview(id, "author");
 
         Box::new(move |s| {
+            s.call_on_name(&sync_name, |v: &mut TextView| v.set_content(sync));
             s.call_on_name(&index_name, |v: &mut TextView| v.set_content(index_content));
             if let Some(content) = author_content {
                 s.call_on_name(&author_name, |v: &mut TextView| v.set_content(content));
@@ -158,13 +180,14 @@ impl Panel for IndexPanel {
 fn render_index(doc: &mut OpenDocument<IndexDocument>) -> String {
     match &*doc.updates.borrow_and_update() {
         Ok(view) => format!("{view}"),
-        Err(e) => format!("error: {e}"),
+        Err(e) => error_label(e),
     }
 }
 
-fn render_author(doc: &mut OpenDocument<AccountDocument>) -> String {
+fn render_author(doc: &mut OpenDocument<AccountDocument>, contacts: &Contacts) -> String {
     match &*doc.updates.borrow_and_update() {
-        Ok(view) => format!("{view}"),
+        Ok(view) => render_with_contact(view, contacts),
+        Err(e) if e.is_transient() => "(author unavailable, retrying...)".to_string(),
         Err(e) => format!("(author unavailable: {e})"),
     }
 }
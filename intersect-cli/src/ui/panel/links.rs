@@ -1,7 +1,11 @@
-use cursive::{view::Nameable, views::TextView, Cursive};
+use cursive::{
+    view::Nameable,
+    views::{LinearLayout, TextView},
+    Cursive,
+};
 use intersect_core::{documents::LinksDocument, OpenDocument};
 
-use super::{subview, Panel};
+use super::{error_label, status_changed, status_label, subview, Panel};
 
 pub struct LinksPanel {
     pub doc: OpenDocument<LinksDocument>,
@@ -9,27 +13,42 @@ pub struct LinksPanel {
 
 impl Panel for LinksPanel {
     fn title(&self) -> String {
-        "links".to_string()
+        match &*self.doc.updates.borrow() {
+            Ok(view) => format!("links ({})", view.links().len()),
+            Err(_) => "links: ?".to_string(),
+        }
     }
 
     fn has_updates(&self) -> bool {
-        self.doc.updates.has_changed().unwrap_or(false)
+        self.doc.updates.has_changed().unwrap_or(false) || status_changed(&self.doc)
     }
 
     fn build_view(&mut self, id: usize) -> Box<dyn cursive::View> {
-        // TODO: render links list once LinksDocument is implemented
-        let _ = self.doc.updates.borrow_and_update();
-        Box::new(TextView::new("(links not yet implemented)").with_name(subview(id, "content")))
+        let sync = status_label(&mut self.doc);
+        Box::new(
+            LinearLayout::vertical()
+                .child(TextView::new(sync).with_name(subview(id, "sync")))
+                .child(
+                    TextView::new(render_links(&mut self.doc)).with_name(subview(id, "content")),
+                ),
+        )
     }
 
     fn make_update(&mut self, id: usize) -> Box<dyn FnOnce(&mut Cursive)> {
-        // TODO: render links list once LinksDocument is implemented
-        let _ = self.doc.updates.borrow_and_update();
+        let sync = status_label(&mut self.doc);
+        let content = render_links(&mut self.doc);
+        let sync_name = subview(id, "sync");
         let name = subview(id, "content");
         Box::new(move |s| {
-            s.call_on_name(&name, |v: &mut TextView| {
-                v.set_content("(links not yet implemented)");
-            });
+            s.call_on_name(&sync_name, |v: &mut TextView| v.set_content(sync));
+            s.call_on_name(&name, |v: &mut TextView| v.set_content(content));
         })
     }
 }
+
+fn render_links(doc: &mut OpenDocument<LinksDocument>) -> String {
+    match &*doc.updates.borrow_and_update() {
+        Ok(view) => format!("{view}"),
+        Err(e) => error_label(e),
+    }
+}
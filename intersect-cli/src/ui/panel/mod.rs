@@ -1,9 +1,11 @@
 mod account;
+mod feed;
 mod fragment;
 mod index;
 mod links;
 
 pub use account::AccountPanel;
+pub use feed::FeedPanel;
 pub use fragment::FragmentPanel;
 pub use index::IndexPanel;
 pub use links::LinksPanel;
@@ -17,6 +19,10 @@ use cursive::{
     Cursive,
 };
 
+use intersect_core::{
+    DocumentError, MutableDocument, OpenDocument, RecordError, SyncState, WatchMode,
+};
+
 use super::AppState;
 
 pub trait Panel {
@@ -29,11 +35,49 @@ pub trait Panel {
     fn make_update(&mut self, id: usize) -> Box<dyn FnOnce(&mut Cursive)>;
 }
 
+/// short status line for a document's local writes, and whether it's being polled for changes.
+/// empty once everything's on the network and changes are watched
+pub fn status_label<D: MutableDocument>(doc: &mut OpenDocument<D>) -> String {
+    let sync = match &*doc.sync.borrow_and_update() {
+        SyncState::Synced => None,
+        SyncState::Syncing => Some("syncing changes to the network...".to_string()),
+        SyncState::LocalOnly => Some("saved locally, will sync once connected".to_string()),
+        SyncState::Failed(e) => Some(format!("last change failed to save: {e}")),
+    };
+    let mode = match *doc.mode.borrow_and_update() {
+        WatchMode::Watching => None,
+        WatchMode::Polling => Some("checking for changes every so often".to_string()),
+    };
+    let parts: Vec<String> = sync.into_iter().chain(mode).collect();
+    match parts.is_empty() {
+        true => String::new(),
+        false => format!("({})", parts.join(", ")),
+    }
+}
+
+/// a failed read. ones that might pass are retried in the background, so they don't read as final
+pub fn error_label(e: &DocumentError) -> String {
+    match e.record_error() {
+        Some(RecordError::NotFound) => {
+            "not found on the network. it might have expired, or never made it there".to_string()
+        }
+        Some(RecordError::Offline) => "offline, will load once connected...".to_string(),
+        Some(e) if e.is_transient() => format!("couldn't reach the network, retrying... ({e})"),
+        _ => format!("error: {e}"),
+    }
+}
+
+/// whether the status line needs redrawing
+pub fn status_changed<D: MutableDocument>(doc: &OpenDocument<D>) -> bool {
+    doc.sync.has_changed().unwrap_or(false) || doc.mode.has_changed().unwrap_or(false)
+}
+
 pub enum OpenPanel {
     Index(IndexPanel),
     Account(AccountPanel),
     Fragment(FragmentPanel),
     Links(LinksPanel),
+    Feed(FeedPanel),
 }
 
 // TODO: perhaps an enum was the wrong choice here, these matches seem silly
@@ -44,6 +88,7 @@ impl Panel for OpenPanel {
             Self::Account(p) => p.title(),
             Self::Fragment(p) => p.title(),
             Self::Links(p) => p.title(),
+            Self::Feed(p) => p.title(),
         }
     }
 
@@ -53,6 +98,7 @@ impl Panel for OpenPanel {
             Self::Account(p) => p.has_updates(),
             Self::Fragment(p) => p.has_updates(),
             Self::Links(p) => p.has_updates(),
+            Self::Feed(p) => p.has_updates(),
         }
     }
 
@@ -62,6 +108,7 @@ impl Panel for OpenPanel {
             Self::Account(p) => p.build_view(id),
             Self::Fragment(p) => p.build_view(id),
             Self::Links(p) => p.build_view(id),
+            Self::Feed(p) => p.build_view(id),
         }
     }
 
@@ -71,6 +118,7 @@ impl Panel for OpenPanel {
             Self::Account(p) => p.make_update(id),
             Self::Fragment(p) => p.make_update(id),
             Self::Links(p) => p.make_update(id),
+            Self::Feed(p) => p.make_update(id),
         }
     }
 }
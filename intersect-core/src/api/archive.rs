@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+
+use veilid_core::RecordKey;
+
+use crate::{
+    api::{Crawl, CrawlFailure, CrawledView, Intersect, IntersectError},
+    documents::{IndexDocument, LinksDocument},
+    models::{Archive, ArchiveEntry, ArchivedDocument, DocumentType, Link, Trace},
+};
+
+/// see `Intersect::export`
+pub(crate) async fn export(
+    intersect: &Intersect,
+    root: &Trace,
+    depth: u32,
+) -> Result<(Archive, Vec<CrawlFailure>), IntersectError> {
+    // accounts can't be re-created by the importer, so don't bother fetching them
+    let Crawl { nodes, failures } = intersect
+        .crawl(root, depth, |t| *t.document_type() != DocumentType::Account)
+        .await?;
+
+    let entries = nodes
+        .into_iter()
+        .filter_map(|node| {
+            let document = match node.view {
+                CrawledView::Index(view) => ArchivedDocument::Index {
+                    name: view.name().clone(),
+                    fragment: view.fragment().cloned(),
+                    links: view.links().cloned(),
+                    metadata: view.metadata().clone(),
+                },
+                CrawledView::Fragment(view) => ArchivedDocument::Fragment {
+                    data: view.data().to_vec(),
+                    mime: view.mime().clone(),
+                },
+                CrawledView::Links(view) => ArchivedDocument::Links(view.links().to_vec()),
+                // filtered out above
+                CrawledView::Account(_) => return None,
+            };
+            Some(ArchiveEntry::new(node.trace, document))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok((Archive::new(root.clone(), entries)?, failures))
+}
+
+/// see `Intersect::import`
+pub(crate) async fn import(
+    intersect: &Intersect,
+    archive: &Archive,
+) -> Result<Trace, IntersectError> {
+    let mut created = Vec::new();
+    import_into(intersect, archive, &mut created)
+        .await
+        .map_err(|e| IntersectError::PartialImport {
+            created,
+            error: Box::new(e),
+        })
+}
+
+// pushes every record it makes onto `created`, so a failure can say what's been left behind
+async fn import_into(
+    intersect: &Intersect,
+    archive: &Archive,
+    created: &mut Vec<Trace>,
+) -> Result<Trace, IntersectError> {
+    // original record -> trace of its re-created copy
+    let mut traces: HashMap<RecordKey, Trace> = HashMap::new();
+    let rewrite = |traces: &HashMap<RecordKey, Trace>, trace: &Trace| {
+        // references to anything outside the archive are left pointing at the original
+        traces
+            .get(trace.record())
+            .cloned()
+            .unwrap_or_else(|| trace.clone())
+    };
+
+    // fragments are immutable leaves, so they go first
+    for entry in archive.entries() {
+        if let ArchivedDocument::Fragment { data, mime } = entry.document() {
+            let typed_ref = intersect
+                .create_fragment(data.clone(), mime.clone())
+                .await?;
+            created.push(typed_ref.to_unlocked_trace());
+            traces.insert(
+                entry.trace().record().clone(),
+                typed_ref.to_unlocked_trace(),
+            );
+        }
+    }
+
+    // indexes and links can point at each other, so reserve an empty record for each
+    // first, then write each one exactly once with every trace already rewritten
+    let mut pending_indexes = Vec::new();
+    let mut pending_links = Vec::new();
+    for entry in archive.entries() {
+        match entry.document() {
+            ArchivedDocument::Index {
+                name,
+                fragment,
+                links,
+                metadata,
+            } => {
+                let typed_ref = intersect.reserve::<IndexDocument>().await?;
+                created.push(typed_ref.to_unlocked_trace());
+                traces.insert(
+                    entry.trace().record().clone(),
+                    typed_ref.to_unlocked_trace(),
+                );
+                pending_indexes.push((typed_ref, name, fragment, links, metadata));
+            }
+            ArchivedDocument::Links(links) => {
+                let typed_ref = intersect.reserve::<LinksDocument>().await?;
+                created.push(typed_ref.to_unlocked_trace());
+                traces.insert(
+                    entry.trace().record().clone(),
+                    typed_ref.to_unlocked_trace(),
+                );
+                pending_links.push((typed_ref, links));
+            }
+            ArchivedDocument::Fragment { .. } => {}
+        }
+    }
+
+    for (typed_ref, name, fragment, links, metadata) in pending_indexes {
+        intersect
+            .fill_index(
+                &typed_ref,
+                name.clone(),
+                fragment.as_ref().map(|t| rewrite(&traces, t)),
+                links.as_ref().map(|t| rewrite(&traces, t)),
+                metadata.clone(),
+            )
+            .await?;
+    }
+
+    for (typed_ref, links) in pending_links {
+        let links = links
+            .iter()
+            .map(|link| Link::new(rewrite(&traces, link.trace()), link.name().cloned()))
+            .collect();
+        intersect.fill_links(&typed_ref, links).await?;
+    }
+
+    // infallible, archives always contain their root
+    Ok(rewrite(&traces, archive.root()))
+}
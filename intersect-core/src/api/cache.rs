@@ -0,0 +1,412 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use tokio::sync::{mpsc, oneshot};
+use veilid_core::RecordKey;
+use veilid_tools::{get_timestamp, spawn_detached};
+
+use crate::{
+    api::{Document, TypedReference},
+    models::{CacheUsage, CachedView, DocumentType},
+    veilid::{Connection, LocalStore, StoreError},
+};
+
+const CACHE_TABLE: &str = "intersect-views";
+// size, revision and last use of every persisted view, under the same key
+const USAGE_TABLE: &str = "intersect-views-usage";
+// kept in memory on top of the persisted copies. a single fragment can be 32MiB, so this counts bytes
+const MAX_MEMORY_BYTES: usize = 64 * 1024 * 1024;
+// past this, the least recently used views are dropped from disk as well
+const MAX_DISK_BYTES: u64 = 256 * 1024 * 1024;
+
+type CacheKey = (RecordKey, DocumentType);
+
+struct Remembered {
+    view: CachedView,
+    size: usize,
+    // µs timestamp
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct Memory {
+    views: HashMap<CacheKey, Remembered>,
+    bytes: usize,
+}
+
+impl Memory {
+    fn insert(&mut self, key: CacheKey, remembered: Remembered) {
+        self.bytes += remembered.size;
+        if let Some(old) = self.views.insert(key, remembered) {
+            self.bytes -= old.size;
+        }
+    }
+
+    fn remove(&mut self, key: &CacheKey) {
+        if let Some(old) = self.views.remove(key) {
+            self.bytes -= old.size;
+        }
+    }
+}
+
+// changes to the persisted views. they're applied one at a time and in order,
+// so an older revision can't overwrite a newer one on the way to disk
+enum Persist {
+    Store(Vec<u8>, CachedView, CacheUsage),
+    Touch(Vec<u8>),
+    Remove(Vec<u8>),
+    Flush(oneshot::Sender<()>),
+}
+
+// last known views of documents, so they show up straight away while they're read again,
+// and can still be read while offline. the most recently used ones are held in memory,
+// and with `ConnectionParams::persist_cache` they're kept on disk as well.
+// keyed by record and document type, the same record read as another type is garbage rather than a view.
+// documents reached through a password-protected trace are never cached, the cache holds them decrypted.
+#[derive(Clone)]
+pub(crate) struct ViewCache {
+    // None unless views are persisted
+    store: Option<LocalStore>,
+    writes: Option<mpsc::UnboundedSender<Persist>>,
+    memory: Arc<Mutex<Memory>>,
+    cache_only: Arc<AtomicBool>,
+}
+
+impl ViewCache {
+    pub(crate) async fn open(
+        connection: &Connection,
+        cache_only: bool,
+        persist: bool,
+    ) -> Result<Self, StoreError> {
+        let (store, writes) = if persist {
+            let store = LocalStore::open(connection, CACHE_TABLE).await?;
+            let usage = LocalStore::open(connection, USAGE_TABLE).await?;
+            let (writes, rx) = mpsc::unbounded_channel();
+            spawn_detached("intersect-view-cache", run_writer(store.clone(), usage, rx));
+            (Some(store), Some(writes))
+        } else {
+            (None, None)
+        };
+        Ok(Self {
+            store,
+            writes,
+            memory: Arc::new(Mutex::new(Memory::default())),
+            cache_only: Arc::new(AtomicBool::new(cache_only)),
+        })
+    }
+
+    /// whether documents are only read from the cache, never from the network
+    pub(crate) fn cache_only(&self) -> bool {
+        self.cache_only.load(Ordering::Relaxed)
+    }
+
+    /// returns whether it was cache-only before
+    pub(crate) fn set_cache_only(&self, cache_only: bool) -> bool {
+        self.cache_only.swap(cache_only, Ordering::Relaxed)
+    }
+
+    /// the last known view of a document, if there is one.
+    /// a persisted copy that can't be read anymore is treated as missing
+    pub(crate) async fn get<D: Document>(&self, typed_ref: &TypedReference<D>) -> Option<D::View> {
+        if typed_ref.is_protected() {
+            return None;
+        }
+        let key = (typed_ref.reference().record().clone(), D::DOCUMENT_TYPE);
+        let remembered = self.memory.lock().unwrap().views.get_mut(&key).map(|r| {
+            r.last_used = get_timestamp();
+            r.view.clone()
+        });
+        let cached = match (remembered, &self.store) {
+            (Some(cached), _) => cached,
+            (None, None) => return None,
+            (None, Some(store)) => {
+                let cached = match store.load::<CachedView>(&store_key(&key)).await {
+                    Ok(cached) => cached?,
+                    Err(e) => {
+                        crate::log!("failed to load cached view: {e}");
+                        return None;
+                    }
+                };
+                self.send(Persist::Touch(store_key(&key)));
+                if let Ok(size) = cached.size() {
+                    self.remember(key, cached.clone(), size);
+                }
+                cached
+            }
+        };
+        let revision = cached.revision();
+        D::from_cached(cached.into_document(), revision)
+    }
+
+    /// keeps a freshly read view, persisting it in the background.
+    /// an older revision never replaces a newer one (fragments don't have any, they never change),
+    /// and documents that aren't cached are ignored
+    pub(crate) fn put<D: Document>(&self, typed_ref: &TypedReference<D>, view: &D::View) {
+        if typed_ref.is_protected() {
+            return;
+        }
+        let Some(document) = D::to_cached(view) else {
+            return;
+        };
+        let cached = CachedView::new(D::revision(view), document);
+        let size = match cached.size() {
+            Ok(size) => size,
+            Err(e) => {
+                crate::log!("failed to cache view: {e}");
+                return;
+            }
+        };
+        let key = (typed_ref.reference().record().clone(), D::DOCUMENT_TYPE);
+        // queued under the memory lock, so writes reach the writer in the same order they passed the check
+        let memory = self.memory.lock().unwrap();
+        let known = memory
+            .views
+            .get(&key)
+            .is_some_and(|r| r.view == cached || r.view.revision() > cached.revision());
+        if known {
+            return;
+        }
+        let usage = CacheUsage::new(cached.revision(), size as u64, get_timestamp());
+        self.send(Persist::Store(store_key(&key), cached.clone(), usage));
+        drop(memory);
+        self.remember(key, cached, size);
+    }
+
+    /// forgets every cached view of a record, e.g. after it was deleted
+    pub(crate) fn remove(&self, record: &RecordKey) {
+        let types = [
+            DocumentType::Index,
+            DocumentType::Fragment,
+            DocumentType::Links,
+            DocumentType::Account,
+        ];
+        let mut memory = self.memory.lock().unwrap();
+        for document_type in types {
+            let key = (record.clone(), document_type);
+            memory.remove(&key);
+            self.send(Persist::Remove(store_key(&key)));
+        }
+    }
+
+    /// waits for every view queued so far to be persisted
+    pub(crate) async fn flush(&self) {
+        let (done, flushed) = oneshot::channel();
+        self.send(Persist::Flush(done));
+        let _ = flushed.await;
+    }
+
+    fn send(&self, persist: Persist) {
+        if let Some(writes) = &self.writes {
+            let _ = writes.send(persist);
+        }
+    }
+
+    // past the budget, the least recently used views are only kept on disk.
+    // views bigger than the whole budget aren't held at all
+    fn remember(&self, key: CacheKey, view: CachedView, size: usize) {
+        if size > MAX_MEMORY_BYTES {
+            return;
+        }
+        let mut memory = self.memory.lock().unwrap();
+        // a newer revision might have come in while this one was loaded
+        if memory
+            .views
+            .get(&key)
+            .is_some_and(|r| r.view.revision() > view.revision())
+        {
+            return;
+        }
+        memory.insert(
+            key,
+            Remembered {
+                view,
+                size,
+                last_used: get_timestamp(),
+            },
+        );
+        while memory.bytes > MAX_MEMORY_BYTES {
+            let oldest = memory
+                .views
+                .iter()
+                .min_by_key(|(_, r)| r.last_used)
+                .map(|(k, _)| k.clone());
+            match oldest {
+                Some(oldest) => memory.remove(&oldest),
+                None => break,
+            }
+        }
+    }
+}
+
+fn store_key((record, document_type): &CacheKey) -> Vec<u8> {
+    format!("{record}:{document_type:?}").into_bytes()
+}
+
+// applies persisted changes in order, and keeps the views on disk within `MAX_DISK_BYTES`.
+// exits once every copy of the cache is dropped
+async fn run_writer(
+    store: LocalStore,
+    usage_store: LocalStore,
+    mut writes: mpsc::UnboundedReceiver<Persist>,
+) {
+    let mut usage: HashMap<Vec<u8>, CacheUsage> = match usage_store.load_all().await {
+        Ok(usage) => usage.into_iter().collect(),
+        Err(e) => {
+            crate::log!("failed to load view cache usage: {e}");
+            HashMap::new()
+        }
+    };
+    // views without usage can't be accounted for, so they go
+    match store.keys().await {
+        Ok(keys) => {
+            for key in keys.into_iter().filter(|k| !usage.contains_key(k)) {
+                if let Err(e) = store.delete(&key).await {
+                    crate::log!("failed to remove untracked cached view: {e}");
+                }
+            }
+        }
+        Err(e) => crate::log!("failed to list cached views: {e}"),
+    }
+
+    while let Some(persist) = writes.recv().await {
+        let result = match persist {
+            Persist::Store(key, view, new) => {
+                store_view(&store, &usage_store, &mut usage, key, view, new).await
+            }
+            Persist::Touch(key) => match usage.get_mut(&key) {
+                Some(entry) => {
+                    *entry = CacheUsage::new(entry.revision(), entry.size(), get_timestamp());
+                    usage_store.store(&key, &*entry).await
+                }
+                None => Ok(()),
+            },
+            Persist::Remove(key) => remove_view(&store, &usage_store, &mut usage, &key).await,
+            Persist::Flush(done) => {
+                let _ = done.send(());
+                Ok(())
+            }
+        };
+        if let Err(e) = result {
+            crate::log!("failed to persist cached view: {e}");
+        }
+    }
+}
+
+async fn store_view(
+    store: &LocalStore,
+    usage_store: &LocalStore,
+    usage: &mut HashMap<Vec<u8>, CacheUsage>,
+    key: Vec<u8>,
+    view: CachedView,
+    new: CacheUsage,
+) -> Result<(), StoreError> {
+    // memory might have forgotten about a newer revision by the time an older one was put
+    if usage
+        .get(&key)
+        .is_some_and(|old| old.revision() > new.revision())
+    {
+        return Ok(());
+    }
+    if new.size() > MAX_DISK_BYTES {
+        return remove_view(store, usage_store, usage, &key).await;
+    }
+    store.store(&key, &view).await?;
+    usage_store.store(&key, &new).await?;
+    usage.insert(key.clone(), new);
+
+    let mut total: u64 = usage.values().map(CacheUsage::size).sum();
+    while total > MAX_DISK_BYTES {
+        let Some(oldest) = usage
+            .iter()
+            .filter(|(k, _)| **k != key)
+            .min_by_key(|(_, u)| u.last_used())
+            .map(|(k, _)| k.clone())
+        else {
+            break;
+        };
+        total -= usage[&oldest].size();
+        remove_view(store, usage_store, usage, &oldest).await?;
+    }
+    Ok(())
+}
+
+async fn remove_view(
+    store: &LocalStore,
+    usage_store: &LocalStore,
+    usage: &mut HashMap<Vec<u8>, CacheUsage>,
+    key: &[u8],
+) -> Result<(), StoreError> {
+    usage.remove(key);
+    store.delete(key).await?;
+    usage_store.delete(key).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use veilid_core::SharedSecret;
+
+    use super::*;
+    use crate::{
+        api::Reference,
+        documents::{LinksDocument, LinksView},
+        models::{Access, CachedDocument, Link, Trace},
+    };
+
+    fn cache() -> ViewCache {
+        ViewCache {
+            store: None,
+            writes: None,
+            memory: Arc::new(Mutex::new(Memory::default())),
+            cache_only: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    fn key(c: char) -> RecordKey {
+        RecordKey::from_str(&format!(
+            "VLD0:{c}X9L_EV3JAy5ozyK875WErKAyFhBy4jZ-6DZajlDr9c:KpS0JtGg9OfJhpsIVCFY8FI9arViozN3kw3duglNkmY"
+        ))
+        .unwrap()
+    }
+
+    fn links(revision: u32, targets: &[char]) -> LinksView {
+        let links = targets
+            .iter()
+            .map(|&c| {
+                Link::new(
+                    Trace::new(DocumentType::Index, &key(c), Access::Locked),
+                    None,
+                )
+            })
+            .collect();
+        let subkeys = (1..=targets.len() as u32).collect();
+        LinksDocument::from_cached(CachedDocument::Links { links, subkeys }, revision).unwrap()
+    }
+
+    #[test]
+    fn older_links_dont_replace_newer() {
+        let cache = cache();
+        let typed_ref = TypedReference::<LinksDocument>::new(Reference::new(
+            key('a'),
+            SharedSecret::from_str("VLD0:KpS0JtGg9OfJhpsIVCFY8FI9arViozN3kw3duglNkmY").unwrap(),
+        ));
+
+        cache.put(&typed_ref, &links(2, &['b', 'c']));
+        // e.g. a slow read from before the second link was added
+        cache.put(&typed_ref, &links(1, &['b']));
+        let cached = tokio_test::block_on(cache.get(&typed_ref)).unwrap();
+        assert_eq!(cached, links(2, &['b', 'c']));
+        assert_eq!(LinksDocument::revision(&cached), 2);
+
+        cache.put(&typed_ref, &links(3, &['c']));
+        let cached = tokio_test::block_on(cache.get(&typed_ref)).unwrap();
+        assert_eq!(cached, links(3, &['c']));
+    }
+}
@@ -0,0 +1,63 @@
+use std::sync::Arc;
+
+use tokio::sync::watch;
+
+use crate::{
+    models::{Contact, Contacts},
+    veilid::{Connection, LocalStore, StoreError},
+};
+
+const CONTACTS_TABLE: &str = "intersect-contacts";
+
+// persisted contact list with an in-memory copy for synchronous lookups.
+// the watch channel holds the in-memory copy, so there's only one source of truth while running.
+#[derive(Clone)]
+pub(crate) struct ContactBook {
+    store: LocalStore,
+    contacts_tx: Arc<watch::Sender<Contacts>>,
+}
+
+impl ContactBook {
+    pub(crate) async fn open(connection: &Connection) -> Result<Self, StoreError> {
+        let store = LocalStore::open(connection, CONTACTS_TABLE).await?;
+
+        let mut contacts = Contacts::default();
+        for (key, contact) in store.load_all::<Contact>().await? {
+            // keys are the fingerprint strings as bytes
+            match String::from_utf8(key) {
+                Ok(fingerprint) => contacts.insert(fingerprint, contact),
+                Err(_) => crate::log!("skipping contact with invalid fingerprint key"),
+            }
+        }
+
+        let (contacts_tx, _) = watch::channel(contacts);
+        Ok(Self {
+            store,
+            contacts_tx: Arc::new(contacts_tx),
+        })
+    }
+
+    pub(crate) fn snapshot(&self) -> Contacts {
+        self.contacts_tx.borrow().clone()
+    }
+
+    pub(crate) fn subscribe(&self) -> watch::Receiver<Contacts> {
+        self.contacts_tx.subscribe()
+    }
+
+    pub(crate) async fn set(&self, fingerprint: &str, contact: Contact) -> Result<(), StoreError> {
+        // persist first so the in-memory copy never claims something that isn't on disk
+        self.store.store(fingerprint.as_bytes(), &contact).await?;
+        self.contacts_tx
+            .send_modify(|c| c.insert(fingerprint.to_owned(), contact));
+        Ok(())
+    }
+
+    /// returns true if the contact existed
+    pub(crate) async fn remove(&self, fingerprint: &str) -> Result<bool, StoreError> {
+        let existed = self.store.delete(fingerprint.as_bytes()).await?;
+        self.contacts_tx
+            .send_if_modified(|c| c.remove(fingerprint).is_some());
+        Ok(existed)
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::{HashSet, VecDeque};
+
+use veilid_core::RecordKey;
+
+use crate::{
+    api::{Document, Intersect, IntersectError},
+    documents::{
+        AccountDocument, AccountView, FragmentDocument, FragmentView, IndexDocument, IndexView,
+        LinksDocument, LinksView,
+    },
+    models::{DocumentType, Trace},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CrawledView {
+    Index(IndexView),
+    Links(LinksView),
+    Fragment(FragmentView),
+    Account(AccountView),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrawlNode {
+    pub trace: Trace,
+    /// stable identifier for the underlying record, handy for file names and the like
+    pub id: String,
+    /// how many links were followed to get here. an index's fragment, author and links share its depth.
+    pub depth: u32,
+    pub view: CrawledView,
+}
+
+/// a trace that was reached but couldn't be fetched (locked, protected, offline, corrupt...)
+#[derive(Debug, Clone)]
+pub struct CrawlFailure {
+    pub trace: Trace,
+    pub depth: u32,
+    pub error: IntersectError,
+}
+
+/// everything reachable from a root trace, in breadth-first order
+#[derive(Debug, Clone, Default)]
+pub struct Crawl {
+    pub nodes: Vec<CrawlNode>,
+    pub failures: Vec<CrawlFailure>,
+}
+
+/// see `Intersect::crawl`
+pub(crate) async fn crawl(
+    intersect: &Intersect,
+    root: &Trace,
+    depth: u32,
+    filter: impl Fn(&Trace) -> bool,
+) -> Result<Crawl, IntersectError> {
+    let mut crawl = Crawl::default();
+    let mut seen: HashSet<RecordKey> = HashSet::new();
+    let mut queue: VecDeque<(Trace, u32)> = VecDeque::from([(root.clone(), 0)]);
+
+    while let Some((trace, level)) = queue.pop_front() {
+        if !filter(&trace) || !seen.insert(trace.record().clone()) {
+            continue;
+        }
+        let is_root = seen.len() == 1;
+
+        let view = match fetch_trace(intersect, &trace).await {
+            Ok(view) => view,
+            Err(e) if is_root => return Err(e),
+            Err(error) => {
+                crawl.failures.push(CrawlFailure {
+                    trace,
+                    depth: level,
+                    error,
+                });
+                continue;
+            }
+        };
+
+        match &view {
+            CrawledView::Index(index) => {
+                let parts = [index.fragment(), index.author(), index.links()];
+                queue.extend(parts.into_iter().flatten().map(|t| (t.clone(), level)));
+            }
+            CrawledView::Links(links) if level < depth => {
+                queue.extend(links.links().iter().map(|l| (l.trace().clone(), level + 1)));
+            }
+            // fragments are leaves, and accounts aren't followed any further
+            // (every author's home would quickly turn a crawl into the whole network)
+            _ => {}
+        }
+
+        crawl.nodes.push(CrawlNode {
+            id: trace.record().to_string(),
+            trace,
+            depth: level,
+            view,
+        });
+    }
+
+    Ok(crawl)
+}
+
+async fn fetch_trace(intersect: &Intersect, trace: &Trace) -> Result<CrawledView, IntersectError> {
+    Ok(match trace.document_type() {
+        DocumentType::Index => CrawledView::Index(fetch::<IndexDocument>(intersect, trace).await?),
+        DocumentType::Links => CrawledView::Links(fetch::<LinksDocument>(intersect, trace).await?),
+        DocumentType::Fragment => {
+            CrawledView::Fragment(fetch::<FragmentDocument>(intersect, trace).await?)
+        }
+        DocumentType::Account => {
+            CrawledView::Account(fetch::<AccountDocument>(intersect, trace).await?)
+        }
+    })
+}
+
+async fn fetch<D: Document>(
+    intersect: &Intersect,
+    trace: &Trace,
+) -> Result<D::View, IntersectError> {
+    let typed_ref = trace.clone().into_typed::<D>()?.into_unlocked()?;
+    intersect.fetch(&typed_ref).await
+}
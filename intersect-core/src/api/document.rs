@@ -4,10 +4,14 @@ pub const LARGE_SUBKEYS: u16 = 32;
 pub const MANY_SUBKEYS: u16 = 256;
 
 use std::fmt::Debug;
-use tokio::sync::watch;
+use tokio::sync::{broadcast, watch};
 use veilid_core::KeyPair;
 
-use crate::{api::TypedReference, models::DocumentType, veilid::RecordPool};
+use crate::{
+    api::{Reference, TypedReference},
+    models::{CachedDocument, DocumentType, SearchText, Trace},
+    veilid::{RecordPool, SyncState, WatchGuard, WatchMode},
+};
 
 // TODO: consider sealing this trait to hide read/create/update from the public api entirely. (along with the MutableDocument one)
 // currently they're hidden from docs but still technically pub.
@@ -37,6 +41,20 @@ pub trait Document: Sized + Send + Sync + 'static {
         // (gotta be Send so it can be called from the WatchCoordinator task)
     ) -> impl Future<Output = Result<Self::View, DocumentError>> + Send + 'a;
 
+    /// re-reads an open document after `subkeys` of its root record changed,
+    /// taking everything else from `current`. the changed values are already stored locally.
+    /// defaults to reading the whole document again.
+    #[doc(hidden)]
+    fn read_changed<'a>(
+        typed_ref: &'a TypedReference<Self>,
+        identity: Option<&'a KeyPair>,
+        _current: &'a Self::View,
+        _subkeys: &'a [u32],
+        pool: &'a RecordPool,
+    ) -> impl Future<Output = Result<Self::View, DocumentError>> + Send + 'a {
+        Self::read(typed_ref, identity, false, pool)
+    }
+
     // create takes an owned view to avoid unnnecessary cloning
     #[doc(hidden)]
     fn create(
@@ -44,6 +62,61 @@ pub trait Document: Sized + Send + Sync + 'static {
         identity: &KeyPair,
         pool: &RecordPool,
     ) -> impl Future<Output = Result<TypedReference<Self>, DocumentError>> + Send;
+
+    /// overwrites everything the document wrote with tombstones, including any other records it spans,
+    /// then schedules its records for removal from local storage.
+    /// the header goes last, so a delete that fails halfway can be retried.
+    #[doc(hidden)]
+    fn delete<'a>(
+        typed_ref: &'a TypedReference<Self>,
+        identity: &'a KeyPair,
+        pool: &'a RecordPool,
+    ) -> impl Future<Output = Result<(), DocumentError>> + Send + 'a;
+
+    /// every record the document is stored in, starting with the root.
+    /// only fragments span more than one.
+    #[doc(hidden)]
+    fn records<'a>(
+        typed_ref: &'a TypedReference<Self>,
+        _pool: &'a RecordPool,
+    ) -> impl Future<Output = Result<Vec<Reference>, DocumentError>> + Send + 'a {
+        async move { Ok(vec![typed_ref.reference().clone()]) }
+    }
+
+    /// sequence number the view was read at, so the view cache never replaces a newer view with an older one.
+    /// 0 (the default) for documents that don't keep track
+    #[doc(hidden)]
+    fn revision(_view: &Self::View) -> u32 {
+        0
+    }
+
+    /// the view in the form the view cache keeps it in.
+    /// None (the default) for document types that are never cached
+    #[doc(hidden)]
+    fn to_cached(_view: &Self::View) -> Option<CachedDocument> {
+        None
+    }
+
+    /// turns a cached view back into a view, None if it's for another document type
+    #[doc(hidden)]
+    fn from_cached(_cached: CachedDocument, _revision: u32) -> Option<Self::View> {
+        None
+    }
+
+    /// other documents this one points at that are likely to be opened next,
+    /// read in the background when it's opened (see `ConnectionParams::prefetch`).
+    /// empty (the default) for documents that don't point anywhere worth reading ahead
+    #[doc(hidden)]
+    fn prefetch(_view: &Self::View) -> Vec<Trace> {
+        Vec::new()
+    }
+
+    /// text to feed the local search index with whenever a view of this document is fetched or opened.
+    /// None (the default) for document types that aren't searchable.
+    #[doc(hidden)]
+    fn search_text(_view: &Self::View) -> Option<SearchText> {
+        None
+    }
 }
 
 pub trait MutableDocument: Document {
@@ -66,13 +139,41 @@ pub trait MutableDocument: Document {
 pub struct OpenDocument<D: MutableDocument> {
     pub reference: TypedReference<D>,
     pub updates: watch::Receiver<Result<D::View, DocumentError>>,
+    /// what changed, for every new view sent on `updates`
+    pub changes: broadcast::Receiver<DocumentChange>,
+    /// whether changes arrive through a watch, or have to be polled for
+    pub mode: watch::Receiver<WatchMode>,
+    /// whether local writes to the document have made it to the network yet
+    pub sync: watch::Receiver<SyncState>,
+    // the record stays watched for as long as any handle to it is around
+    pub(crate) _watch: WatchGuard,
+}
+
+/// what changed between two views of an open document
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum DocumentChange {
+    /// only these subkeys of the root record changed, the rest was kept from the previous view
+    Subkeys(Vec<u32>),
+    /// the whole document was read again, e.g. after reconnecting
+    Full,
+    /// logged in or out, so the document was decrypted again from the local copy
+    Identity,
+}
+
+/// a document with local writes that haven't been pushed to the network yet
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingDocument {
+    pub trace: Trace,
+    /// number of subkeys still waiting to be pushed
+    pub subkeys: usize,
 }
 
 #[derive(Debug, Clone, thiserror::Error)]
 #[non_exhaustive]
 pub enum DocumentError {
     #[error("record error: {0}")]
-    RecordError(#[from] crate::veilid::RecordError),
+    RecordError(crate::veilid::RecordError),
 
     #[error("serialisation error: {0}")]
     SerialisationError(#[from] crate::serialisation::SerialisationError),
@@ -94,4 +195,33 @@ pub enum DocumentError {
 
     #[error("corrupt document: {0}")]
     Corrupt(String),
+
+    #[error("document has been deleted")]
+    Deleted,
+}
+
+impl DocumentError {
+    /// what went wrong on the network, if that's what failed.
+    /// a deleted record has its own variant, so it isn't reported here
+    pub fn record_error(&self) -> Option<&crate::veilid::RecordError> {
+        match self {
+            Self::RecordError(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    /// whether reading again later might work, e.g. because the network didn't come through
+    pub fn is_transient(&self) -> bool {
+        self.record_error().is_some_and(|e| e.is_transient())
+    }
+}
+
+impl From<crate::veilid::RecordError> for DocumentError {
+    fn from(e: crate::veilid::RecordError) -> Self {
+        match e {
+            // its own variant, so readers can tell a deleted document apart from a broken one
+            crate::veilid::RecordError::Deleted => Self::Deleted,
+            e => Self::RecordError(e),
+        }
+    }
 }
@@ -0,0 +1,222 @@
+use std::{collections::HashMap, sync::Arc};
+
+use futures::{FutureExt, future::pending, select};
+use tokio::sync::watch;
+use veilid_core::RecordKey;
+use veilid_tools::spawn_detached;
+
+use crate::{
+    api::{DocumentError, Intersect, OpenDocument},
+    debug,
+    documents::{AccountDocument, AccountView, IndexDocument, IndexView},
+    models::{Timestamp, Trace},
+};
+
+// oldest items get dropped past this point so a long-running feed doesn't grow forever
+const MAX_FEED_ITEMS: usize = 256;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FeedChange {
+    /// a followed account's profile (name, bio, home)
+    Account(AccountView),
+    /// a followed account's home index
+    Home(IndexView),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedItem {
+    /// the followed account this change came from
+    pub account: Trace,
+    pub change: FeedChange,
+    /// when the change was seen on this device, not when it was made.
+    /// changes made while the feed wasn't running all show up at the time it caught up
+    pub timestamp: Timestamp,
+}
+
+/// live feed of changes from followed accounts, newest first.
+/// backed by the same watch coordinators as `Intersect::open`, so following an account
+/// that's also open elsewhere doesn't cost any extra reads.
+/// the background watchers stop once every receiver has been dropped.
+pub struct Feed {
+    pub items: watch::Receiver<Vec<FeedItem>>,
+}
+
+impl Feed {
+    pub(crate) fn spawn(intersect: Intersect, own: OpenDocument<AccountDocument>) -> Self {
+        let (tx, items) = watch::channel(Vec::new());
+        spawn_detached("intersect-feed", async move {
+            feed_task(intersect, own, tx).await;
+        });
+        Self { items }
+    }
+}
+
+fn push_item(tx: &watch::Sender<Vec<FeedItem>>, account: &Trace, change: FeedChange) {
+    let item = FeedItem {
+        account: account.clone(),
+        change,
+        timestamp: Timestamp::now(),
+    };
+    tx.send_modify(|items| {
+        items.push(item);
+        // stable sort so items seen at the same time keep their arrival order
+        items.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        items.truncate(MAX_FEED_ITEMS);
+    });
+}
+
+// watches our own account for changes to the follow list,
+// spawning and cancelling per-account watchers to match
+async fn feed_task(
+    intersect: Intersect,
+    mut own: OpenDocument<AccountDocument>,
+    tx: watch::Sender<Vec<FeedItem>>,
+) {
+    let tx = Arc::new(tx);
+    // dropping a cancel sender stops its watcher
+    let mut watchers: HashMap<RecordKey, watch::Sender<()>> = HashMap::new();
+
+    loop {
+        let following: Option<Vec<Trace>> = match &*own.updates.borrow_and_update() {
+            Ok(view) => Some(
+                view.private()
+                    .map(|p| p.following().to_vec())
+                    .unwrap_or_default(),
+            ),
+            // keep the current watchers around through transient read errors
+            Err(_) => None,
+        };
+
+        if let Some(following) = following {
+            watchers.retain(|key, _| following.iter().any(|t| t.record() == key));
+            for trace in following {
+                if watchers.contains_key(trace.record()) {
+                    continue;
+                }
+                let (cancel_tx, cancel_rx) = watch::channel(());
+                watchers.insert(trace.record().clone(), cancel_tx);
+                let (intersect, tx) = (intersect.clone(), Arc::clone(&tx));
+                spawn_detached("intersect-feed-account", async move {
+                    account_watcher(intersect, trace, tx, cancel_rx).await;
+                });
+            }
+        }
+
+        let keep_going = select! {
+            r = own.updates.changed().fuse() => r.is_ok(),
+            _ = tx.closed().fuse() => false,
+        };
+        if !keep_going {
+            break;
+        }
+    }
+}
+
+enum WatcherEvent {
+    Account,
+    Home,
+    Stop,
+}
+
+// follows a single account and whatever its current home index is
+async fn account_watcher(
+    intersect: Intersect,
+    trace: Trace,
+    tx: Arc<watch::Sender<Vec<FeedItem>>>,
+    mut cancel_rx: watch::Receiver<()>,
+) {
+    let Ok(Ok(typed_ref)) = trace
+        .clone()
+        .into_typed::<AccountDocument>()
+        .map(|t| t.into_unlocked())
+    else {
+        crate::log!("feed: skipping followed trace that isn't an unlocked account");
+        return;
+    };
+    let mut account = match intersect.open(&typed_ref).await {
+        Ok(doc) => doc,
+        Err(e) => {
+            crate::log!("feed: failed to open followed account: {e}");
+            return;
+        }
+    };
+
+    let mut home: Option<OpenDocument<IndexDocument>> = None;
+    let mut home_trace: Option<Trace> = None;
+    // None until the first read, which is only the starting point rather than a change
+    let mut last_view: Option<AccountView> = None;
+    let mut event = WatcherEvent::Account;
+
+    loop {
+        match event {
+            WatcherEvent::Stop => break,
+            WatcherEvent::Account => {
+                let view = account.updates.borrow_and_update().clone();
+                match view {
+                    Ok(view) => {
+                        // (re)open the home index if it moved
+                        if view.home() != home_trace.as_ref() {
+                            home_trace = view.home().cloned();
+                            home = match &home_trace {
+                                Some(t) => open_home(&intersect, t).await,
+                                None => None,
+                            };
+                            if let Some(Ok(index)) = home.as_mut().map(latest) {
+                                push_item(&tx, &trace, FeedChange::Home(index));
+                            }
+                        }
+                        // re-reads (reattaching, logging in...) often come back with nothing new
+                        if last_view.as_ref().is_some_and(|last| last != &view) {
+                            push_item(&tx, &trace, FeedChange::Account(view.clone()));
+                        }
+                        last_view = Some(view);
+                    }
+                    Err(e) => {
+                        debug!("feed: followed account read failed: {e}");
+                    }
+                }
+            }
+            WatcherEvent::Home => {
+                if let Some(Ok(index)) = home.as_mut().map(latest) {
+                    push_item(&tx, &trace, FeedChange::Home(index));
+                }
+            }
+        }
+
+        event = select! {
+            r = account.updates.changed().fuse() => {
+                if r.is_ok() { WatcherEvent::Account } else { WatcherEvent::Stop }
+            }
+            r = async {
+                match home.as_mut() {
+                    Some(h) => h.updates.changed().await,
+                    None => pending().await,
+                }
+            }.fuse() => {
+                if r.is_ok() { WatcherEvent::Home } else { WatcherEvent::Stop }
+            }
+            _ = cancel_rx.changed().fuse() => WatcherEvent::Stop,
+            _ = tx.closed().fuse() => WatcherEvent::Stop,
+        };
+    }
+}
+
+fn latest(doc: &mut OpenDocument<IndexDocument>) -> Result<IndexView, DocumentError> {
+    doc.updates.borrow_and_update().clone()
+}
+
+async fn open_home(intersect: &Intersect, trace: &Trace) -> Option<OpenDocument<IndexDocument>> {
+    let typed_ref = trace
+        .clone()
+        .into_typed::<IndexDocument>()
+        .ok()?
+        .into_unlocked()
+        .ok()?;
+    match intersect.open(&typed_ref).await {
+        Ok(doc) => Some(doc),
+        Err(e) => {
+            debug!("feed: failed to open home index: {e}");
+            None
+        }
+    }
+}
@@ -3,22 +3,30 @@ use std::sync::{Arc, Mutex};
 use guard_clause::guard;
 use thiserror::Error;
 use veilid_core::KeyPair;
+use veilid_tools::spawn_detached;
 
 use tokio::sync::watch;
 
 use crate::{
-    api::{Document, DocumentError, MutableDocument, OpenDocument, TypedReference},
+    api::{
+        ContactBook, Crawl, CrawlFailure, Document, DocumentError, Feed, LocalSearch,
+        MutableDocument, NotUnlocked, OpenDocument, PendingDocument, PinSet, TypedReference,
+        ViewCache, WrittenSet, WrongDocumentType, archive, crawl, prefetch,
+    },
     documents::{
-        AccountDocument, AccountView, FragmentDocument, FragmentView, IndexDocument, IndexView,
+        AccountDocument, AccountUpdate, AccountView, FragmentDocument, FragmentView, IndexDocument,
+        IndexView, LinksDocument, LinksView,
     },
     models::{
-        AccountBio, AccountName, AccountPrivate, AccountPublicKey, AccountSecret, EncryptionError,
-        FragmentMime, IndexName, Trace, ValidationError,
+        Access, AccountBio, AccountName, AccountPrivate, AccountPublicKey, AccountSecret, Archive,
+        Contact, Contacts, DocumentType, EncryptionError, FragmentMime, IndexMetadata, IndexName,
+        Link, Petname, Pin, PinnedDocument, SearchResult, Trace, TrustLevel, ValidationError,
+        validate_fingerprint,
     },
     serialisation::{DeserialisationError, SerialisationError},
     veilid::{
-        Connection, ConnectionError, ConnectionParams, NetworkState, RecordError, RecordPool,
-        WatchCoordinators, WatchRouter, watch_network_state, with_crypto,
+        Connection, ConnectionError, ConnectionParams, Crypto, NetworkState, RecordError,
+        RecordPool, RecordStats, StoreError, WatchCoordinators, WatchRouter, watch_network_state,
     },
 };
 
@@ -36,17 +44,35 @@ pub struct Intersect {
     watch_router: Arc<WatchRouter>,
     coordinators: WatchCoordinators,
     network_state_rx: watch::Receiver<NetworkState>,
+    // local petnames for account fingerprints, never leaves this device
+    contacts: ContactBook,
+    // None if local search is disabled in the connection params
+    search: Option<LocalSearch>,
+    // documents kept alive on the network, refreshed in the background
+    pins: PinSet,
+    // last known views, shown while documents are read again and all there is while cache-only
+    cache: ViewCache,
+    // link targets to read ahead when an index is opened, None if prefetching is off
+    prefetch: Option<usize>,
+    // traces of every record written from this device, so pending records can be reported as documents
+    written: WrittenSet,
 }
 
 impl Intersect {
     pub async fn init(connection_params: ConnectionParams) -> Result<Self, IntersectError> {
+        let local_search = connection_params.local_search;
+        let retry = connection_params.retry.clone();
+        let cache_only = connection_params.cache_only;
+        let persist_cache = connection_params.persist_cache;
+        let prefetch = connection_params.prefetch;
         let connection = Connection::init(connection_params).await?;
 
-        let pool = RecordPool::new(connection.clone());
+        let pool = RecordPool::new(connection.clone(), retry).await?;
         let watch_router = Arc::new(WatchRouter::new());
         connection.add_update_handler(Box::new(Arc::clone(&watch_router)));
 
         let network_state_rx = watch_network_state(
+            connection.is_private(),
             connection.attachment_state(),
             connection.network_state(),
             pool.pending_sync_watch(),
@@ -55,19 +81,46 @@ impl Intersect {
         // only attach after setting up all the watchers so we avoid potential missed events or races
         connection.attach().await?;
 
-        let keypair = with_crypto(|c| c.generate_keypair());
+        let contacts = ContactBook::open(&connection).await?;
+        let search = if local_search {
+            Some(LocalSearch::open(&connection).await?)
+        } else {
+            None
+        };
+
+        let keypair = Arc::new(Mutex::new(
+            connection.crypto().with(|c| c.generate_keypair()),
+        ));
         let (account_tx, _) = watch::channel(None);
+        let cache = ViewCache::open(&connection, cache_only, persist_cache).await?;
+        let coordinators = WatchCoordinators::new(keypair.lock().unwrap().clone(), cache.clone());
+        watch_router.spawn_keeper(Arc::downgrade(&pool), network_state_rx.clone());
+
+        let written = WrittenSet::open(&connection).await?;
+        written.spawn_pruner(pool.settled_records());
+        let pins = PinSet::open(&connection).await?;
+        pins.spawn_refresher(
+            Arc::downgrade(&pool),
+            Arc::clone(&keypair),
+            network_state_rx.clone(),
+        );
 
         crate::log!("intersect node initialised!");
 
         Ok(Self {
             connection,
             pool,
-            keypair: Arc::new(Mutex::new(keypair)),
+            keypair,
             account_tx: Arc::new(account_tx),
             watch_router,
-            coordinators: WatchCoordinators::new(),
+            coordinators,
             network_state_rx,
+            contacts,
+            search,
+            pins,
+            cache,
+            prefetch,
+            written,
         })
     }
 
@@ -84,8 +137,15 @@ impl Intersect {
     }
 
     pub async fn close(self) {
-        // drain pending writes before disconnecting
+        // drain pending writes before disconnecting, for a while at least.
+        // whatever doesn't make it is pushed next session
         self.pool.wait_for_all_pending().await;
+        // deleted records only go once their tombstones are out, which a short-lived process
+        // would otherwise never stick around for. ones that don't make it in time are left for next session
+        if let Err(e) = self.pool.purge_deleted(true).await {
+            crate::log!("failed to remove deleted records: {e}");
+        }
+        self.cache.flush().await;
         self.connection.close().await;
     }
 
@@ -107,23 +167,31 @@ impl Intersect {
             Err(IntersectError::InvalidLogin)
         );
         let keypair = KeyPair::new_from_parts(public_key.inner().clone(), secret.inner().value());
-        let is_valid = with_crypto(|c| c.validate_keypair(&keypair.key(), &keypair.secret()))
+        let is_valid = self
+            .connection
+            .crypto()
+            .with(|c| c.validate_keypair(&keypair.key(), &keypair.secret()))
             .map_err(|_| IntersectError::InvalidLogin)?;
         if !is_valid {
             return Err(IntersectError::InvalidLogin);
         }
 
         // keypair first then account, so any watches of account don't potentially see a stale keypair
-        *self.keypair.lock().unwrap() = keypair;
+        self.set_keypair(keypair);
         self.account_tx.send_modify(|a| *a = Some(account));
         Ok(())
     }
 
     pub fn logout(&self) {
         // generate a fresh ephemeral keypair to replace the account keypair
-        *self.keypair.lock().unwrap() = with_crypto(|c| c.generate_keypair());
+        self.set_keypair(self.connection.crypto().with(|c| c.generate_keypair()));
         self.account_tx.send_modify(|a| *a = None);
-        // TODO: cancel all active watch coordinators on logout
+    }
+
+    // open documents get re-read with the new keypair, so private sections follow the login
+    fn set_keypair(&self, keypair: KeyPair) {
+        *self.keypair.lock().unwrap() = keypair.clone();
+        self.coordinators.set_identity(keypair);
     }
 
     fn keypair(&self) -> KeyPair {
@@ -140,16 +208,163 @@ impl Intersect {
         self.account_tx.subscribe()
     }
 
-    /// one-time document retrieval guaranteed to return the most recent version on the network
+    /// current local contact list
+    pub fn contacts(&self) -> Contacts {
+        self.contacts.snapshot()
+    }
+
+    /// returns a receiver for the local contact list, updated whenever a contact is added or removed.
+    pub fn contacts_watch(&self) -> watch::Receiver<Contacts> {
+        self.contacts.subscribe()
+    }
+
+    /// adds a contact for an account fingerprint (see `AccountPublicKey::fingerprint`),
+    /// replacing any existing contact for that fingerprint.
+    pub async fn set_contact(
+        &self,
+        fingerprint: &str,
+        petname: String,
+        trust: TrustLevel,
+    ) -> Result<(), IntersectError> {
+        validate_fingerprint(fingerprint)?;
+        let contact = Contact::new(Petname::new(petname)?, trust);
+        Ok(self.contacts.set(fingerprint, contact).await?)
+    }
+
+    /// removes the contact for an account fingerprint. returns false if there wasn't one.
+    pub async fn remove_contact(&self, fingerprint: &str) -> Result<bool, IntersectError> {
+        Ok(self.contacts.remove(fingerprint).await?)
+    }
+
+    /// traces of the accounts the logged in account follows.
+    /// the list lives in the account's private data, so it follows you across devices.
+    pub async fn following(&self) -> Result<Vec<Trace>, IntersectError> {
+        let account = self.account().ok_or(IntersectError::NotLoggedIn)?;
+        let view = self.fetch(&account).await?;
+        Ok(view
+            .private()
+            .map(|p| p.following().to_vec())
+            .unwrap_or_default())
+    }
+
+    /// whether the logged in account follows an account.
+    /// compared by record, so it doesn't matter which trace of the account was followed
+    pub async fn is_following(
+        &self,
+        account: &TypedReference<AccountDocument>,
+    ) -> Result<bool, IntersectError> {
+        let record = account.reference().record();
+        Ok(self.following().await?.iter().any(|t| t.record() == record))
+    }
+
+    /// adds an account to the follow list. following an account twice is a no-op.
+    pub async fn follow(
+        &self,
+        account: &TypedReference<AccountDocument>,
+    ) -> Result<(), IntersectError> {
+        let mut following = self.following().await?;
+        let record = account.reference().record();
+        if following.iter().any(|t| t.record() == record) {
+            return Ok(());
+        }
+        following.push(account.to_unlocked_trace());
+        self.set_following(following).await
+    }
+
+    /// removes an account from the follow list. returns false if it wasn't followed.
+    pub async fn unfollow(
+        &self,
+        account: &TypedReference<AccountDocument>,
+    ) -> Result<bool, IntersectError> {
+        let mut following = self.following().await?;
+        let before = following.len();
+        let record = account.reference().record();
+        following.retain(|t| t.record() != record);
+        if following.len() == before {
+            return Ok(false);
+        }
+        self.set_following(following).await?;
+        Ok(true)
+    }
+
+    async fn set_following(&self, following: Vec<Trace>) -> Result<(), IntersectError> {
+        let account = self.account().ok_or(IntersectError::NotLoggedIn)?;
+        let doc = self.open(&account).await?;
+        self.update(&doc, AccountUpdate::Following(following)).await
+    }
+
+    /// starts a live feed of changes from every followed account and its home index.
+    /// follows and unfollows (from any device) are picked up while the feed is running.
+    pub async fn feed(&self) -> Result<Feed, IntersectError> {
+        let account = self.account().ok_or(IntersectError::NotLoggedIn)?;
+        let own = self.open(&account).await?;
+        Ok(Feed::spawn(self.clone(), own))
+    }
+
+    /// whether documents are only read from the local view cache, never from the network
+    pub fn cache_only(&self) -> bool {
+        self.cache.cache_only()
+    }
+
+    /// switches cache-only reading on or off, e.g. to keep browsing what's already been seen while offline.
+    /// writes still go through as usual, and are pushed once the node can reach the network
+    pub fn set_cache_only(&self, cache_only: bool) {
+        let was_cache_only = self.cache.set_cache_only(cache_only);
+        // open documents skipped every change in the meantime, and weren't watched if opened while cache-only
+        if was_cache_only && !cache_only {
+            self.watch_router.resume();
+        }
+    }
+
+    /// one-time document retrieval guaranteed to return the most recent version on the network.
+    /// while cache-only, returns the last known version instead (or `NotCached` if there isn't one)
     pub async fn fetch<D: Document>(
         &self,
         typed_ref: &TypedReference<D>,
     ) -> Result<D::View, IntersectError> {
+        let record = typed_ref.reference().record();
+        if self.cache.cache_only() {
+            return self
+                .cache
+                .get(typed_ref)
+                .await
+                .ok_or(IntersectError::NotCached);
+        }
         let keypair = self.keypair();
         // always force. immutable implementations ignore this and use cache internally anyway
-        D::read(typed_ref, Some(&keypair), true, &self.pool)
-            .await
-            .map_err(Into::into)
+        let view = match D::read(typed_ref, Some(&keypair), true, &self.pool).await {
+            Ok(view) => view,
+            Err(DocumentError::Deleted) => {
+                self.cache.remove(record);
+                return Err(DocumentError::Deleted.into());
+            }
+            Err(e) => return Err(e.into()),
+        };
+        self.cache.put(typed_ref, &view);
+        self.index_for_search(typed_ref, &view);
+        Ok(view)
+    }
+
+    /// like `fetch`, but returns the last known version straight away if there is one,
+    /// and reads the latest in the background so it's there next time.
+    /// falls back to `fetch` for documents that haven't been seen before
+    pub async fn fetch_cached<D: Document>(
+        &self,
+        typed_ref: &TypedReference<D>,
+    ) -> Result<D::View, IntersectError> {
+        let Some(view) = self.cache.get(typed_ref).await else {
+            return self.fetch(typed_ref).await;
+        };
+        if !self.cache.cache_only() {
+            let intersect = self.clone();
+            let typed_ref = typed_ref.clone();
+            spawn_detached("intersect-revalidate", async move {
+                if let Err(e) = intersect.fetch(&typed_ref).await {
+                    crate::debug!("failed to revalidate cached view: {e}");
+                }
+            });
+        }
+        Ok(view)
     }
 
     /// document retrieval with background watch
@@ -160,50 +375,270 @@ impl Intersect {
         typed_ref: &TypedReference<D>,
     ) -> Result<OpenDocument<D>, IntersectError> {
         let keypair = self.keypair();
+        // nothing may go to the network while cache-only, the watch is set up once that's switched off
+        let watch = self.watch_router.acquire(
+            typed_ref.reference(),
+            &Arc::downgrade(&self.pool),
+            !self.cache.cache_only(),
+        );
 
         // if a coordinator is already running for this record, subscribe for free
         // the receiver starts with the latest cached view, no read needed
-        if let Some(updates) = self
+        if let Some((updates, changes)) = self
             .coordinators
             .try_subscribe::<D>(typed_ref.reference().record())
         {
+            if let Ok(view) = &*updates.borrow() {
+                self.index_for_search(typed_ref, view);
+            }
             return Ok(OpenDocument {
                 reference: typed_ref.clone(),
                 updates,
+                changes,
+                mode: self.watch_router.mode(typed_ref.reference().record()),
+                sync: self.pool.sync_watch(typed_ref.reference().record()),
+                _watch: watch,
             });
         }
 
-        // first open for this record. do the initial read, then create the coordinator
-        let initial = D::read(typed_ref, Some(&keypair), false, &self.pool).await?;
-        self.pool.watch(typed_ref.reference()).await?;
-        let notify_rx = self
-            .watch_router
-            .subscribe(typed_ref.reference().record().clone());
-
-        let updates = self.coordinators.create::<D>(
+        // first open for this record. start from the cached view if there is one, otherwise read it first
+        let record = typed_ref.reference().record();
+        let cached = self.cache.get(typed_ref).await;
+        let catch_up = cached.is_some() && !self.cache.cache_only();
+        let initial = match cached {
+            Some(view) => view,
+            None if self.cache.cache_only() => return Err(IntersectError::NotCached),
+            None => {
+                let view = D::read(typed_ref, Some(&keypair), false, &self.pool).await?;
+                self.cache.put(typed_ref, &view);
+                view
+            }
+        };
+        self.index_for_search(typed_ref, &initial);
+        // only on the first open, anything opened alongside it since has been read already.
+        // not for protected documents, their targets would end up cached without the password
+        if !typed_ref.is_protected() {
+            self.spawn_prefetch(D::prefetch(&initial));
+        }
+        let (updates, changes) = self.coordinators.create::<D>(
             typed_ref.clone(),
             initial,
             Arc::clone(&self.pool),
-            Some(keypair),
-            notify_rx,
-            Arc::clone(&self.watch_router),
+            &self.watch_router,
         );
+        // the cached view might be stale, so have the coordinator read the latest right away
+        if catch_up {
+            self.watch_router.catch_up(record);
+        }
 
         Ok(OpenDocument {
             reference: typed_ref.clone(),
             updates,
+            changes,
+            mode: self.watch_router.mode(typed_ref.reference().record()),
+            sync: self.pool.sync_watch(typed_ref.reference().record()),
+            _watch: watch,
         })
     }
 
+    fn spawn_prefetch(&self, traces: Vec<Trace>) {
+        let Some(links) = self.prefetch else {
+            return;
+        };
+        if traces.is_empty() || self.cache.cache_only() {
+            return;
+        }
+        let intersect = self.clone();
+        spawn_detached("intersect-prefetch", async move {
+            prefetch::prefetch(&intersect, traces, links).await;
+        });
+    }
+
+    /// walks the document graph from `root`, following
+    /// index → fragment / author / links, and links → whatever they point at.
+    /// `depth` caps how many links records deep to go (0 = just the root and its direct parts).
+    /// `filter` is checked for every trace before it's fetched, the root included.
+    /// each record is only visited once, so cycles are fine.
+    /// only fails outright if the root itself can't be fetched.
+    pub async fn crawl(
+        &self,
+        root: &Trace,
+        depth: u32,
+        filter: impl Fn(&Trace) -> bool,
+    ) -> Result<Crawl, IntersectError> {
+        crawl::crawl(self, root, depth, filter).await
+    }
+
+    /// crawls `root` (see `crawl`) and bundles the decrypted documents into an archive.
+    /// accounts are left out, references to them are kept as-is.
+    /// anything that couldn't be fetched is returned alongside the archive rather than failing the export.
+    pub async fn export(
+        &self,
+        root: &Trace,
+        depth: u32,
+    ) -> Result<(Archive, Vec<CrawlFailure>), IntersectError> {
+        archive::export(self, root, depth).await
+    }
+
+    /// re-creates every document in an archive under the current identity,
+    /// rewriting references between them to point at the new copies.
+    /// returns the trace of the new root. if it fails part-way the error is
+    /// `IntersectError::PartialImport`, listing what was already created.
+    pub async fn import(&self, archive: &Archive) -> Result<Trace, IntersectError> {
+        archive::import(self, archive).await
+    }
+
+    /// deletes a document by overwriting it with tombstones, after which reading it
+    /// fails with `DocumentError::Deleted`. a fragment's overflow records go with it.
+    /// only the author can delete, and copies other nodes already have can't be taken back.
+    pub async fn delete(&self, trace: &Trace) -> Result<(), IntersectError> {
+        match trace.document_type() {
+            DocumentType::Index => self.delete_typed::<IndexDocument>(trace).await?,
+            DocumentType::Fragment => self.delete_typed::<FragmentDocument>(trace).await?,
+            DocumentType::Links => self.delete_typed::<LinksDocument>(trace).await?,
+            DocumentType::Account => self.delete_typed::<AccountDocument>(trace).await?,
+        }
+        if let Some(search) = &self.search {
+            search.remove(trace.record());
+        }
+        self.cache.remove(trace.record());
+        // nothing left worth keeping alive
+        self.pins.remove(trace.record()).await?;
+        Ok(())
+    }
+
+    async fn delete_typed<D: Document>(&self, trace: &Trace) -> Result<(), IntersectError> {
+        let typed_ref = trace.clone().into_typed::<D>()?.into_unlocked()?;
+        let result = D::delete(&typed_ref, &self.keypair(), &self.pool).await;
+        // the tombstones still have to reach the network, so show up in `pending` like any other write
+        self.track_written(&typed_ref).await;
+        result.map_err(Into::into)
+    }
+
+    /// documents this device keeps alive on the network, along with how their last refresh went.
+    /// everything created while logged in is pinned automatically.
+    pub fn pins(&self) -> Vec<PinnedDocument> {
+        self.pins.list()
+    }
+
+    /// keeps a document alive by refreshing it periodically, even if someone else wrote it.
+    /// the trace has to be unlocked, a fragment's secret is needed to find all of its records.
+    pub async fn pin(&self, trace: &Trace) -> Result<(), IntersectError> {
+        match trace.access() {
+            Access::Unlocked { .. } => {}
+            Access::Locked => return Err(NotUnlocked::Locked.into()),
+            Access::Protected { .. } => return Err(NotUnlocked::Protected.into()),
+        }
+        self.pins.add(Pin::new(trace.clone())).await?;
+        Ok(())
+    }
+
+    /// returns true if the document was pinned
+    pub async fn unpin(&self, trace: &Trace) -> Result<bool, IntersectError> {
+        Ok(self.pins.remove(trace.record()).await?)
+    }
+
+    // anonymous keypairs are gone after this session, so there'd be no way to push it again
+    async fn pin_own<D: Document>(&self, typed_ref: &TypedReference<D>) {
+        if self.account().is_none() {
+            return;
+        }
+        let pin = Pin::new_own(typed_ref.to_unlocked_trace(), self.keypair().key());
+        // the document is already created, so a failed pin shouldn't fail the whole thing
+        if let Err(e) = self.pins.add(pin).await {
+            crate::log!("failed to pin created document: {e}");
+        }
+    }
+
+    pub(crate) fn crypto(&self) -> &Crypto {
+        self.connection.crypto()
+    }
+
+    /// hash of some data using the network's crypto system, as a string.
+    /// handy for local change detection.
+    pub fn content_hash(&self, data: &[u8]) -> String {
+        self.connection
+            .crypto()
+            .with(|c| c.generate_hash(data))
+            .to_string()
+    }
+
+    /// searches the local index of documents fetched or opened on this device.
+    /// every word in the query has to match (as a prefix) for a document to be returned.
+    /// always empty if local search is disabled.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SearchResult> {
+        match &self.search {
+            Some(search) => search.search(query, limit),
+            None => Vec::new(),
+        }
+    }
+
+    /// whether local search was turned on in the connection params
+    pub fn search_enabled(&self) -> bool {
+        self.search.is_some()
+    }
+
+    // documents unlocked with a password stay out, the index would hand out their trace unprotected
+    fn index_for_search<D: Document>(&self, typed_ref: &TypedReference<D>, view: &D::View) {
+        let Some(search) = &self.search else {
+            return;
+        };
+        if typed_ref.is_protected() {
+            return;
+        }
+        if let Some(text) = D::search_text(view) {
+            search.add(typed_ref.to_unlocked_trace(), text);
+        }
+    }
+
     pub async fn update<D: MutableDocument>(
         &self,
         doc: &OpenDocument<D>,
         update: D::Update,
     ) -> Result<(), IntersectError> {
         let keypair = self.keypair();
-        D::update(update, doc, &keypair, &self.pool)
-            .await
-            .map_err(Into::into)
+        let result = D::update(update, doc, &keypair, &self.pool).await;
+        // even a failed update might have written some of it
+        self.track_written(&doc.reference).await;
+        result.map_err(Into::into)
+    }
+
+    /// documents written from this device that still have changes waiting to be pushed to the network,
+    /// e.g. because they were written while detached, including ones left over from a previous session.
+    pub fn pending(&self) -> Vec<PendingDocument> {
+        let offline = self.pool.offline_subkeys();
+        // overflow records (large fragments) aren't tracked, but their root record always is
+        offline
+            .into_iter()
+            .filter_map(|(key, subkeys)| {
+                self.written
+                    .get(&key)
+                    .map(|trace| PendingDocument { trace, subkeys })
+            })
+            .collect()
+    }
+
+    /// how many records are currently open, for diagnostics
+    pub fn record_stats(&self) -> RecordStats {
+        self.pool.stats()
+    }
+
+    // call after writing, so a record that's already settled by then isn't kept around
+    async fn track_written<D: Document>(&self, typed_ref: &TypedReference<D>) {
+        // the trace is stored unlocked, which would give away the password
+        if typed_ref.is_protected() {
+            return;
+        }
+        let record = typed_ref.reference().record();
+        // only used to describe pending records, so losing it shouldn't fail the write
+        let result = match self.written.add(typed_ref.to_unlocked_trace()).await {
+            // settled before we got to add it, so it won't be pruned
+            Ok(()) if !self.pool.is_dirty(record) => self.written.remove(record).await,
+            result => result,
+        };
+        if let Err(e) = result {
+            crate::log!("failed to remember written document: {e}");
+        }
     }
 
     /// create a new index document.
@@ -213,12 +648,16 @@ impl Intersect {
         name: String,
         fragment: Option<Trace>,
         links: Option<Trace>,
+        metadata: IndexMetadata,
     ) -> Result<TypedReference<IndexDocument>, IntersectError> {
         let keypair = self.keypair();
         // convert the account reference to an unlocked trace so the reader can follow it
         let author = self.account().map(|r| r.to_unlocked_trace());
-        let view = IndexView::new(IndexName::new(name)?, author, fragment, links);
-        Ok(IndexDocument::create(view, &keypair, &self.pool).await?)
+        let view = IndexView::new(IndexName::new(name)?, author, fragment, links, metadata);
+        let typed_ref = IndexDocument::create(view, &keypair, &self.pool).await?;
+        self.track_written(&typed_ref).await;
+        self.pin_own(&typed_ref).await;
+        Ok(typed_ref)
     }
 
     /// upload a fragment with a given mimetype to the network
@@ -229,7 +668,63 @@ impl Intersect {
     ) -> Result<TypedReference<FragmentDocument>, IntersectError> {
         let keypair = self.keypair();
         let view = FragmentView::new(data, mime);
-        Ok(FragmentDocument::create(view, &keypair, &self.pool).await?)
+        let typed_ref = FragmentDocument::create(view, &keypair, &self.pool).await?;
+        self.track_written(&typed_ref).await;
+        self.pin_own(&typed_ref).await;
+        Ok(typed_ref)
+    }
+
+    // an empty record to be filled in later with `fill_index` or `fill_links`,
+    // so the importer knows every new trace before writing anything that points at them
+    pub(crate) async fn reserve<D: Document>(&self) -> Result<TypedReference<D>, IntersectError> {
+        let record = self.pool.create(&self.keypair(), D::MAX_SUBKEYS).await?;
+        let typed_ref = TypedReference::new(record.reference().clone());
+        self.pin_own(&typed_ref).await;
+        Ok(typed_ref)
+    }
+
+    pub(crate) async fn fill_index(
+        &self,
+        typed_ref: &TypedReference<IndexDocument>,
+        name: IndexName,
+        fragment: Option<Trace>,
+        links: Option<Trace>,
+        metadata: IndexMetadata,
+    ) -> Result<(), IntersectError> {
+        let author = self.account().map(|r| r.to_unlocked_trace());
+        let view = IndexView::new(name, author, fragment, links, metadata);
+        let result = IndexDocument::fill(typed_ref, view, &self.keypair(), &self.pool).await;
+        self.track_written(typed_ref).await;
+        result.map_err(Into::into)
+    }
+
+    pub(crate) async fn fill_links(
+        &self,
+        typed_ref: &TypedReference<LinksDocument>,
+        links: Vec<Link>,
+    ) -> Result<(), IntersectError> {
+        let result = LinksDocument::fill(
+            typed_ref,
+            LinksView::new(links),
+            &self.keypair(),
+            &self.pool,
+        )
+        .await;
+        self.track_written(typed_ref).await;
+        result.map_err(Into::into)
+    }
+
+    /// create a new links record
+    pub async fn create_links(
+        &self,
+        links: Vec<Link>,
+    ) -> Result<TypedReference<LinksDocument>, IntersectError> {
+        let keypair = self.keypair();
+        let view = LinksView::new(links);
+        let typed_ref = LinksDocument::create(view, &keypair, &self.pool).await?;
+        self.track_written(&typed_ref).await;
+        self.pin_own(&typed_ref).await;
+        Ok(typed_ref)
     }
 
     /// creates a new account, generating a keypair internally.
@@ -244,8 +739,8 @@ impl Intersect {
         if self.account().is_some() {
             return Err(IntersectError::AlreadyLoggedIn);
         }
-        let keypair = with_crypto(|c| c.generate_keypair());
-        let private = AccountPrivate::new(None);
+        let keypair = self.connection.crypto().with(|c| c.generate_keypair());
+        let private = AccountPrivate::new(None, Vec::new());
         let view = AccountView::new(
             AccountPublicKey::new(keypair.key()),
             name.map(AccountName::new).transpose()?,
@@ -254,10 +749,13 @@ impl Intersect {
             Some(private),
         );
         let reference = AccountDocument::create(view, &keypair, &self.pool).await?;
+        self.track_written(&reference).await;
         let secret = AccountSecret::new(keypair.secret());
-        *self.keypair.lock().unwrap() = keypair;
+        self.set_keypair(keypair);
         self.account_tx
             .send_modify(|a| *a = Some(reference.clone()));
+        // logged in now, so this counts as our own
+        self.pin_own(&reference).await;
         Ok((reference, secret))
     }
 }
@@ -286,9 +784,49 @@ pub enum IntersectError {
     #[error("record error: {0}")]
     RecordError(#[from] RecordError),
 
+    #[error("local store error: {0}")]
+    StoreError(#[from] StoreError),
+
     #[error("invalid login")]
     InvalidLogin,
 
     #[error("already logged in")]
     AlreadyLoggedIn,
+
+    #[error("not logged in")]
+    NotLoggedIn,
+
+    #[error("{0}")]
+    WrongDocumentType(#[from] WrongDocumentType),
+
+    #[error("{0}")]
+    NotUnlocked(#[from] NotUnlocked),
+
+    #[error("no cached copy of this document to read offline")]
+    NotCached,
+
+    /// an import failed part-way. `created` lists the records it had already made,
+    /// which are left on the network
+    #[error("import failed after creating {} records: {error}", created.len())]
+    PartialImport {
+        created: Vec<Trace>,
+        error: Box<IntersectError>,
+    },
+}
+
+impl IntersectError {
+    /// what went wrong on the network, if that's what failed, whether directly or while reading a document
+    pub fn record_error(&self) -> Option<&RecordError> {
+        match self {
+            Self::RecordError(e) => Some(e),
+            Self::DocumentError(e) => e.record_error(),
+            Self::PartialImport { error, .. } => error.record_error(),
+            _ => None,
+        }
+    }
+
+    /// whether trying again later might work, e.g. because the network didn't come through
+    pub fn is_transient(&self) -> bool {
+        self.record_error().is_some_and(RecordError::is_transient)
+    }
 }
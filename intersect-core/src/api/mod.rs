@@ -1,15 +1,32 @@
+mod archive;
+mod cache;
+mod contacts;
+mod crawl;
 mod document;
+mod feed;
 mod intersect;
+mod pins;
+mod prefetch;
 mod reference;
+mod search;
 mod trace;
+mod written;
 
 // public types (re-exported from lib.rs)
-pub use document::{Document, DocumentError, MutableDocument, OpenDocument};
+pub use crawl::{Crawl, CrawlFailure, CrawlNode, CrawledView};
+pub use document::{
+    Document, DocumentChange, DocumentError, MutableDocument, OpenDocument, PendingDocument,
+};
+pub use feed::{Feed, FeedChange, FeedItem};
 pub use intersect::{Intersect, IntersectError};
 pub use reference::TypedReference;
 pub use trace::{LockedTypedReference, NotUnlocked, ProtectedTypedReference, TypedTrace, WrongDocumentType};
 
 // crate-internal types
-#[allow(unused_imports)] //TODO: remove
 pub(crate) use document::{LARGE_SUBKEYS, MANY_SUBKEYS};
+pub(crate) use cache::ViewCache;
+pub(crate) use contacts::ContactBook;
+pub(crate) use pins::PinSet;
 pub(crate) use reference::Reference;
+pub(crate) use search::LocalSearch;
+pub(crate) use written::WrittenSet;
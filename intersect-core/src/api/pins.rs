@@ -0,0 +1,178 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex, Weak},
+};
+
+use tokio::sync::watch;
+use veilid_core::{KeyPair, RecordKey};
+use veilid_tools::{sleep::sleep, spawn::spawn_detached};
+
+use crate::{
+    api::{Document, IntersectError},
+    documents::{AccountDocument, FragmentDocument, IndexDocument, LinksDocument},
+    models::{DocumentType, Pin, PinStatus, PinnedDocument, Timestamp, Trace},
+    veilid::{Connection, LocalStore, NetworkState, RecordPool, Refreshed, StoreError},
+};
+
+const PINS_TABLE: &str = "intersect-pins";
+// dht values hang around for a good while without being touched, this is plenty
+const REFRESH_INTERVAL_MS: u32 = 60 * 60 * 1000;
+// give the node a moment to settle in before the first round
+const FIRST_REFRESH_DELAY_MS: u32 = 30_000;
+
+// persisted pin list, with each pin's refresh status held in memory alongside it.
+// keyed by record, so pinning the same document twice is a no-op.
+#[derive(Clone)]
+pub(crate) struct PinSet {
+    store: LocalStore,
+    pins: Arc<Mutex<BTreeMap<String, PinnedDocument>>>,
+}
+
+impl PinSet {
+    pub(crate) async fn open(connection: &Connection) -> Result<Self, StoreError> {
+        let store = LocalStore::open(connection, PINS_TABLE).await?;
+
+        let mut pins = BTreeMap::new();
+        for (_, pin) in store.load_all::<Pin>().await? {
+            pins.insert(
+                pin.key(),
+                PinnedDocument {
+                    pin,
+                    status: PinStatus::default(),
+                },
+            );
+        }
+
+        Ok(Self {
+            store,
+            pins: Arc::new(Mutex::new(pins)),
+        })
+    }
+
+    pub(crate) fn list(&self) -> Vec<PinnedDocument> {
+        self.pins.lock().unwrap().values().cloned().collect()
+    }
+
+    /// pinning something that's already pinned merges the two, see `Pin::merge`
+    pub(crate) async fn add(&self, pin: Pin) -> Result<(), StoreError> {
+        let key = pin.key();
+        let existing = self.pins.lock().unwrap().get(&key).map(|p| p.pin.clone());
+        let pin = match existing {
+            Some(existing) => {
+                let merged = existing.merge(pin);
+                if merged == existing {
+                    return Ok(());
+                }
+                merged
+            }
+            None => pin,
+        };
+        // persist first so the in-memory copy never claims something that isn't on disk
+        self.store.store(key.as_bytes(), &pin).await?;
+        self.pins.lock().unwrap().insert(
+            key,
+            PinnedDocument {
+                pin,
+                status: PinStatus::default(),
+            },
+        );
+        Ok(())
+    }
+
+    /// returns true if the document was pinned
+    pub(crate) async fn remove(&self, record: &RecordKey) -> Result<bool, StoreError> {
+        let key = record.to_string();
+        let existed = self.store.delete(key.as_bytes()).await?;
+        self.pins.lock().unwrap().remove(&key);
+        Ok(existed)
+    }
+
+    fn set_status(&self, key: &str, status: PinStatus) {
+        // might have been unpinned while it was being refreshed
+        if let Some(pinned) = self.pins.lock().unwrap().get_mut(key) {
+            pinned.status = status;
+        }
+    }
+
+    /// starts refreshing every pin periodically in the background.
+    /// the task exits once the pool is dropped.
+    pub(crate) fn spawn_refresher(
+        &self,
+        pool: Weak<RecordPool>,
+        keypair: Arc<Mutex<KeyPair>>,
+        network: watch::Receiver<NetworkState>,
+    ) {
+        spawn_detached(
+            "pin_refresher",
+            run_refresher(self.clone(), pool, keypair, network),
+        );
+    }
+}
+
+async fn run_refresher(
+    pins: PinSet,
+    pool: Weak<RecordPool>,
+    keypair: Arc<Mutex<KeyPair>>,
+    mut network: watch::Receiver<NetworkState>,
+) {
+    sleep(FIRST_REFRESH_DELAY_MS).await;
+    loop {
+        // nothing to refresh against while detached, and every pin would just fail
+        if network.wait_for(|n| n.attached).await.is_err() {
+            break;
+        }
+        let Some(pool) = pool.upgrade() else {
+            break;
+        };
+        for pinned in pins.list() {
+            let pin = pinned.pin;
+            // only our own content can be pushed again, and only with the keypair it was written with.
+            // pins from another account wait until it's logged in again
+            let current = keypair.lock().unwrap().clone();
+            let writer = match pin.own() {
+                true if pin.writable_by(&current.key()) => Some(current),
+                true => continue,
+                false => None,
+            };
+            let status = match refresh(pin.trace(), writer.as_ref(), &pool).await {
+                Ok(Refreshed { repushed, lost }) => PinStatus::Refreshed {
+                    at: Timestamp::now(),
+                    repushed,
+                    lost,
+                },
+                Err(e) => PinStatus::Failed(e.to_string()),
+            };
+            pins.set_status(&pin.key(), status);
+        }
+        drop(pool);
+        sleep(REFRESH_INTERVAL_MS).await;
+    }
+}
+
+async fn refresh(
+    trace: &Trace,
+    writer: Option<&KeyPair>,
+    pool: &RecordPool,
+) -> Result<Refreshed, IntersectError> {
+    match trace.document_type() {
+        DocumentType::Index => refresh_typed::<IndexDocument>(trace, writer, pool).await,
+        DocumentType::Fragment => refresh_typed::<FragmentDocument>(trace, writer, pool).await,
+        DocumentType::Links => refresh_typed::<LinksDocument>(trace, writer, pool).await,
+        DocumentType::Account => refresh_typed::<AccountDocument>(trace, writer, pool).await,
+    }
+}
+
+async fn refresh_typed<D: Document>(
+    trace: &Trace,
+    writer: Option<&KeyPair>,
+    pool: &RecordPool,
+) -> Result<Refreshed, IntersectError> {
+    let typed_ref = trace.clone().into_typed::<D>()?.into_unlocked()?;
+    let mut total = Refreshed::default();
+    for reference in D::records(&typed_ref, pool).await? {
+        let refreshed = pool.refresh(&reference, writer).await?;
+        total.repushed += refreshed.repushed;
+        total.lost += refreshed.lost;
+    }
+    Ok(total)
+}
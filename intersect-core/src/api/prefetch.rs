@@ -0,0 +1,47 @@
+use futures::future::join_all;
+
+use crate::{
+    api::{Document, Intersect, IntersectError},
+    documents::{FragmentDocument, IndexDocument, LinksDocument, LinksView},
+    models::{DocumentType, Trace},
+};
+
+/// see `ConnectionParams::prefetch`. reads every trace in parallel, then the first `links`
+/// targets of any links document among them, so they're already local by the time they're opened.
+/// accounts are skipped, their views are never cached. failures are only logged, nobody's waiting on these
+pub(crate) async fn prefetch(intersect: &Intersect, traces: Vec<Trace>, links: usize) {
+    let read = join_all(traces.iter().map(|trace| read(intersect, trace))).await;
+    // targets are only read, not followed any further
+    let targets: Vec<&Trace> = read
+        .iter()
+        .flatten()
+        .flat_map(|view| view.links().iter().take(links).map(|l| l.trace()))
+        .collect();
+    join_all(targets.into_iter().map(|trace| read(intersect, trace))).await;
+}
+
+// the links view, if that's what the trace points at, so its targets can be read next
+async fn read(intersect: &Intersect, trace: &Trace) -> Option<LinksView> {
+    let result = match trace.document_type() {
+        DocumentType::Index => warm::<IndexDocument>(intersect, trace).await.map(|_| None),
+        DocumentType::Fragment => warm::<FragmentDocument>(intersect, trace)
+            .await
+            .map(|_| None),
+        // a link pointing at an account
+        DocumentType::Account => Ok(None),
+        DocumentType::Links => warm::<LinksDocument>(intersect, trace).await.map(Some),
+    };
+    result.unwrap_or_else(|e| {
+        crate::debug!("failed to prefetch {}: {e}", trace.record());
+        None
+    })
+}
+
+// locked and protected traces can't be read without asking, so they fail here and are skipped
+async fn warm<D: Document>(
+    intersect: &Intersect,
+    trace: &Trace,
+) -> Result<D::View, IntersectError> {
+    let typed_ref = trace.clone().into_typed::<D>()?.into_unlocked()?;
+    intersect.fetch_cached(&typed_ref).await
+}
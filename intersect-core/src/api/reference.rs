@@ -3,7 +3,7 @@ use std::marker::PhantomData;
 use veilid_core::{RecordKey, SharedSecret};
 
 use crate::{
-    api::Document,
+    api::{Document, Intersect},
     models::{EncryptionError, Trace},
 };
 
@@ -30,9 +30,11 @@ impl Reference {
 // typed handle for a document. wraps a Reference with the document type baked in.
 // eliminates the need for explicit type annotations when calling open/write/update.
 // convert to/from Trace for serialising and sharing.
-#[derive(PartialEq, Debug, Eq)]
+#[derive(Debug)]
 pub struct TypedReference<D: Document> {
     reference: Reference,
+    // unlocked with a password, so nothing read through it is kept on this device's disk
+    protected: bool,
     _phantom: PhantomData<D>,
 }
 
@@ -42,19 +44,43 @@ impl<D: Document> Clone for TypedReference<D> {
     fn clone(&self) -> Self {
         Self {
             reference: self.reference.clone(),
+            protected: self.protected,
             _phantom: PhantomData,
         }
     }
 }
 
+// the same document however it was unlocked
+impl<D: Document> PartialEq for TypedReference<D> {
+    fn eq(&self, other: &Self) -> bool {
+        self.reference == other.reference
+    }
+}
+
+impl<D: Document> Eq for TypedReference<D> {}
+
 impl<D: Document> TypedReference<D> {
     pub(crate) fn new(reference: Reference) -> Self {
         Self {
             reference,
+            protected: false,
             _phantom: PhantomData,
         }
     }
 
+    pub(crate) fn new_protected(reference: Reference) -> Self {
+        Self {
+            protected: true,
+            ..Self::new(reference)
+        }
+    }
+
+    /// whether this was unlocked from a password-protected trace.
+    /// documents read through it are never indexed for search or cached on disk
+    pub fn is_protected(&self) -> bool {
+        self.protected
+    }
+
     pub(crate) fn reference(&self) -> &Reference {
         &self.reference
     }
@@ -71,12 +97,18 @@ impl<D: Document> TypedReference<D> {
         Trace::locked(D::DOCUMENT_TYPE, self.reference.record())
     }
 
-    pub fn to_protected_trace(&self, password: &str) -> Result<Trace, EncryptionError> {
+    /// the password is stretched with the node's crypto, so this needs one to hand
+    pub fn to_protected_trace(
+        &self,
+        intersect: &Intersect,
+        password: &str,
+    ) -> Result<Trace, EncryptionError> {
         Trace::protected(
             D::DOCUMENT_TYPE,
             self.reference.record(),
             self.reference.secret(),
             password,
+            intersect.crypto(),
         )
     }
 }
@@ -0,0 +1,70 @@
+use std::sync::{Arc, Mutex};
+
+use veilid_core::RecordKey;
+
+use crate::{
+    models::{SearchEntry, SearchIndex, SearchResult, SearchText, Trace},
+    veilid::{Connection, LocalStore, StoreError},
+};
+
+const SEARCH_TABLE: &str = "intersect-search";
+
+// persisted search entries with the token index held in memory.
+// entries are keyed by record, so re-fetching a document replaces its old text.
+#[derive(Clone)]
+pub(crate) struct LocalSearch {
+    store: LocalStore,
+    index: Arc<Mutex<SearchIndex>>,
+}
+
+impl LocalSearch {
+    pub(crate) async fn open(connection: &Connection) -> Result<Self, StoreError> {
+        let store = LocalStore::open(connection, SEARCH_TABLE).await?;
+
+        let mut index = SearchIndex::default();
+        for (_, entry) in store.load_all::<SearchEntry>().await? {
+            index.insert(entry);
+        }
+
+        Ok(Self {
+            store,
+            index: Arc::new(Mutex::new(index)),
+        })
+    }
+
+    /// indexes a document's text, persisting it in the background.
+    /// failures are only logged, search is best-effort and should never break a fetch.
+    pub(crate) fn add(&self, trace: Trace, text: SearchText) {
+        let entry = SearchEntry::new(trace, text);
+        {
+            let mut index = self.index.lock().unwrap();
+            // the same view gets seen over and over (every open, every fetch), skip the write
+            if index.contains(&entry) {
+                return;
+            }
+            index.insert(entry.clone());
+        }
+        let store = self.store.clone();
+        veilid_tools::spawn_detached("intersect-search-store", async move {
+            if let Err(e) = store.store(entry.key().as_bytes(), &entry).await {
+                crate::log!("failed to persist search entry: {e}");
+            }
+        });
+    }
+
+    /// drops a document from the index, e.g. after deleting it
+    pub(crate) fn remove(&self, record: &RecordKey) {
+        let key = record.to_string();
+        self.index.lock().unwrap().remove(&key);
+        let store = self.store.clone();
+        veilid_tools::spawn_detached("intersect-search-store", async move {
+            if let Err(e) = store.delete(key.as_bytes()).await {
+                crate::log!("failed to remove search entry: {e}");
+            }
+        });
+    }
+
+    pub(crate) fn search(&self, query: &str, limit: usize) -> Vec<SearchResult> {
+        self.index.lock().unwrap().search(query, limit)
+    }
+}
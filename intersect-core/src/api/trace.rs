@@ -3,7 +3,7 @@ use std::marker::PhantomData;
 use veilid_core::RecordKey;
 
 use crate::{
-    api::{Document, Reference, TypedReference},
+    api::{Document, Intersect, Reference, TypedReference},
     models::{Access, AccessError, ProtectedSecret, Trace, TraceSecret},
 };
 
@@ -37,17 +37,24 @@ pub struct ProtectedTypedReference<D: Document> {
 }
 
 impl<D: Document> ProtectedTypedReference<D> {
-    pub fn unlock(&self, password: &str) -> Result<TypedReference<D>, AccessError> {
-        let secret = self.protected_secret.unlock(password)?;
-        Ok(TypedReference::new(Reference::new(self.record.clone(), secret)))
+    pub fn unlock(
+        &self,
+        intersect: &Intersect,
+        password: &str,
+    ) -> Result<TypedReference<D>, AccessError> {
+        let secret = self.protected_secret.unlock(password, intersect.crypto())?;
+        Ok(TypedReference::new_protected(Reference::new(
+            self.record.clone(),
+            secret,
+        )))
     }
 }
 
-#[derive(Debug, thiserror::Error)]
+#[derive(Debug, Clone, thiserror::Error)]
 #[error("trace document type does not match expected type")]
 pub struct WrongDocumentType;
 
-#[derive(Debug, thiserror::Error)]
+#[derive(Debug, Clone, thiserror::Error)]
 pub enum NotUnlocked {
     #[error("trace requires a secret key")]
     Locked,
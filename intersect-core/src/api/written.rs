@@ -0,0 +1,87 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::broadcast;
+use veilid_core::RecordKey;
+use veilid_tools::spawn_detached;
+
+use crate::{
+    models::Trace,
+    veilid::{Connection, LocalStore, StoreError},
+};
+
+const WRITTEN_TABLE: &str = "intersect-written";
+
+// traces of records this device has written to, so records with changes still waiting
+// to be pushed can be reported as documents, even ones left over from a previous session.
+// persisted alongside an in-memory copy, since `pending` is checked synchronously.
+// only kept until the record's writes are out (or it's purged), and never for protected documents,
+// the traces are stored unlocked.
+#[derive(Clone)]
+pub(crate) struct WrittenSet {
+    store: LocalStore,
+    traces: Arc<Mutex<HashMap<RecordKey, Trace>>>,
+}
+
+impl WrittenSet {
+    pub(crate) async fn open(connection: &Connection) -> Result<Self, StoreError> {
+        let store = LocalStore::open(connection, WRITTEN_TABLE).await?;
+        let traces = store
+            .load_all::<Trace>()
+            .await?
+            .into_iter()
+            .map(|(_, trace)| (trace.record().clone(), trace))
+            .collect();
+        Ok(Self {
+            store,
+            traces: Arc::new(Mutex::new(traces)),
+        })
+    }
+
+    pub(crate) fn get(&self, record: &RecordKey) -> Option<Trace> {
+        self.traces.lock().unwrap().get(record).cloned()
+    }
+
+    /// only hits the disk the first time a record is seen
+    pub(crate) async fn add(&self, trace: Trace) -> Result<(), StoreError> {
+        if self.traces.lock().unwrap().contains_key(trace.record()) {
+            return Ok(());
+        }
+        self.store
+            .store(trace.record().to_string().as_bytes(), &trace)
+            .await?;
+        self.traces
+            .lock()
+            .unwrap()
+            .insert(trace.record().clone(), trace);
+        Ok(())
+    }
+
+    pub(crate) async fn remove(&self, record: &RecordKey) -> Result<(), StoreError> {
+        if self.traces.lock().unwrap().remove(record).is_none() {
+            return Ok(());
+        }
+        self.store.delete(record.to_string().as_bytes()).await?;
+        Ok(())
+    }
+
+    /// forgets records as they settle, until the pool is dropped
+    pub(crate) fn spawn_pruner(&self, mut settled: broadcast::Receiver<RecordKey>) {
+        let written = self.clone();
+        spawn_detached("intersect-written", async move {
+            loop {
+                let record = match settled.recv().await {
+                    Ok(record) => record,
+                    // missed ones are forgotten once they settle again
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                if let Err(e) = written.remove(&record).await {
+                    crate::log!("failed to forget written document: {e}");
+                }
+            }
+        });
+    }
+}
@@ -6,22 +6,57 @@ use crate::{
         TypedReference,
     },
     models::{
-        AccountBio, AccountName, AccountPrivate, AccountPublic, AccountPublicKey, DocumentType,
-        Encrypted, Trace,
+        AccountBio, AccountName, AccountPrivate, AccountPublic, AccountPublicKey, ContactStatus,
+        Contacts, DocumentType, Encrypted, Trace,
     },
-    veilid::{RecordError, RecordPool, with_crypto},
+    veilid::{Crypto, RecordError, RecordPool},
 };
 
 // derive an encryption key from the identity private key.
 // used for encrypting the private section of the account
-fn private_encryption_key(identity: &KeyPair, reference: &Reference) -> SharedSecret {
-    with_crypto(|c| {
-        c.derive_shared_secret(
-            identity.ref_bare_secret().bytes().as_ref(),
-            reference.record().ref_value().ref_key().bytes().as_ref(),
-        )
-    })
-    .expect("derive_shared_secret failed")
+fn private_encryption_key(
+    identity: &KeyPair,
+    reference: &Reference,
+    crypto: &Crypto,
+) -> SharedSecret {
+    crypto
+        .with(|c| {
+            c.derive_shared_secret(
+                identity.ref_bare_secret().bytes().as_ref(),
+                reference.record().ref_value().ref_key().bytes().as_ref(),
+            )
+        })
+        .expect("derive_shared_secret failed")
+}
+
+async fn read_public(
+    reference: &Reference,
+    force: bool,
+    pool: &RecordPool,
+) -> Result<(AccountPublic, u32), DocumentError> {
+    let (encrypted, revision) = pool.read_versioned(reference, 0, force).await?;
+    let public = encrypted.decrypt(reference.secret(), pool.crypto())?;
+    Ok((public, revision))
+}
+
+// only the owner can read the private section, everyone else gets None
+async fn read_private(
+    reference: &Reference,
+    owner: Option<&KeyPair>,
+    force: bool,
+    pool: &RecordPool,
+) -> Result<Option<AccountPrivate>, DocumentError> {
+    let Some(id) = owner else {
+        return Ok(None);
+    };
+    match pool.read(reference, 1, force).await {
+        Ok(encrypted) => Ok(Some(encrypted.decrypt(
+            &private_encryption_key(id, reference, pool.crypto()),
+            pool.crypto(),
+        )?)),
+        Err(RecordError::SubkeyEmpty(_)) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
 }
 
 #[derive(PartialEq, Debug, Clone, Eq)]
@@ -35,6 +70,8 @@ pub struct AccountView {
     home: Option<Trace>,
     // None if identity not loaded or not the account owner
     private: Option<AccountPrivate>,
+    // sequence number of the public subkey, bumped on every profile edit
+    revision: u32,
 }
 
 impl AccountView {
@@ -51,6 +88,18 @@ impl AccountView {
             bio,
             home,
             private,
+            revision: 0,
+        }
+    }
+
+    fn from_parts(public: AccountPublic, private: Option<AccountPrivate>, revision: u32) -> Self {
+        Self {
+            public_key: public.public_key().clone(),
+            name: public.name().cloned(),
+            bio: public.bio().cloned(),
+            home: public.home().cloned(),
+            private,
+            revision,
         }
     }
 
@@ -69,6 +118,16 @@ impl AccountView {
     pub fn private(&self) -> Option<&AccountPrivate> {
         self.private.as_ref()
     }
+    /// how many times the public profile has been edited since creation
+    pub fn revision(&self) -> u32 {
+        self.revision
+    }
+
+    /// checks this account against the local contact list.
+    /// warns (via `ContactStatus::NameConflict`) if the display name matches a contact with a different fingerprint.
+    pub fn contact_status(&self, contacts: &Contacts) -> ContactStatus {
+        contacts.status(&self.public_key.fingerprint(), self.name())
+    }
 }
 
 impl std::fmt::Display for AccountView {
@@ -93,6 +152,15 @@ impl std::fmt::Display for AccountView {
         if let Some(bookmarks) = self.private.as_ref().and_then(|p| p.bookmarks()) {
             writeln!(f, "bookmarks = {}", toml_str(&bookmarks.to_string()))?;
         }
+        if let Some(private) = self.private.as_ref().filter(|p| !p.following().is_empty()) {
+            let following: Vec<String> = private
+                .following()
+                .iter()
+                .map(|t| toml_str(&t.to_string()))
+                .collect();
+            writeln!(f, "following = [{}]", following.join(", "))?;
+        }
+        writeln!(f, "revision = {}", self.revision)?;
         write!(f, "+++")
     }
 }
@@ -101,7 +169,9 @@ pub enum AccountUpdate {
     Name(Option<AccountName>),
     Bio(Option<AccountBio>),
     Home(Option<Trace>),
-    // TODO: private account updates (bookmarks, etc.)
+    /// replaces the (private) list of followed account traces. only the owner can do this.
+    Following(Vec<Trace>),
+    // TODO: remaining private account updates (bookmarks, etc.)
 }
 
 impl Document for AccountDocument {
@@ -117,28 +187,31 @@ impl Document for AccountDocument {
         pool: &RecordPool,
     ) -> Result<AccountView, DocumentError> {
         let reference = &typed_ref.reference();
-        let public: AccountPublic = pool
-            .read(reference, 0, force)
-            .await?
-            .decrypt(reference.secret())?;
-
+        let (public, revision) = read_public(reference, force, pool).await?;
         let owner = identity.filter(|id| &id.key() == public.public_key().inner());
-        let private = match owner {
-            None => None,
-            Some(id) => match pool.read(reference, 1, force).await {
-                Ok(encrypted) => Some(encrypted.decrypt(&private_encryption_key(id, reference))?),
-                Err(RecordError::SubkeyEmpty(_)) => None,
-                Err(e) => return Err(e.into()),
-            },
-        };
+        let private = read_private(reference, owner, force, pool).await?;
+        Ok(AccountView::from_parts(public, private, revision))
+    }
 
-        Ok(AccountView {
-            public_key: public.public_key().clone(),
-            name: public.name().cloned(),
-            bio: public.bio().cloned(),
-            home: public.home().cloned(),
-            private,
-        })
+    async fn read_changed(
+        typed_ref: &TypedReference<AccountDocument>,
+        identity: Option<&KeyPair>,
+        current: &AccountView,
+        subkeys: &[u32],
+        pool: &RecordPool,
+    ) -> Result<AccountView, DocumentError> {
+        let reference = &typed_ref.reference();
+        let mut view = current.clone();
+        if subkeys.contains(&0) {
+            let (public, revision) = read_public(reference, false, pool).await?;
+            view = AccountView::from_parts(public, view.private, revision);
+        }
+        if subkeys.contains(&1) {
+            // the public key never changes, so the one we have is good enough to check ownership
+            let owner = identity.filter(|id| &id.key() == view.public_key.inner());
+            view.private = read_private(reference, owner, false, pool).await?;
+        }
+        Ok(view)
     }
 
     async fn create(
@@ -147,9 +220,10 @@ impl Document for AccountDocument {
         pool: &RecordPool,
     ) -> Result<TypedReference<AccountDocument>, DocumentError> {
         // validate identity keypair
-        let keypair_valid =
-            with_crypto(|c| c.validate_keypair(&identity.key(), &identity.secret()))
-                .unwrap_or(false);
+        let keypair_valid = pool
+            .crypto()
+            .with(|c| c.validate_keypair(&identity.key(), &identity.secret()))
+            .unwrap_or(false);
         if !keypair_valid {
             return Err(DocumentError::NotAuthorised);
         }
@@ -161,6 +235,7 @@ impl Document for AccountDocument {
             bio,
             home,
             private,
+            ..
         } = view;
         let private = private.ok_or(DocumentError::NotAuthorised)?;
         if public_key.inner() != &identity.key() {
@@ -171,17 +246,29 @@ impl Document for AccountDocument {
         let reference = record.reference().clone();
 
         let public = AccountPublic::new(public_key.clone(), name, bio, home);
-        let public_encrypted = Encrypted::encrypt(&public, reference.secret())?;
+        let public_encrypted = Encrypted::encrypt(&public, reference.secret(), pool.crypto())?;
         pool.write(&reference, 0, &public_encrypted, identity)
             .await?;
 
-        let key = private_encryption_key(identity, &reference);
-        let private_encrypted = Encrypted::encrypt(&private, &key)?;
+        let key = private_encryption_key(identity, &reference, pool.crypto());
+        let private_encrypted = Encrypted::encrypt(&private, &key, pool.crypto())?;
         pool.write(&reference, 1, &private_encrypted, identity)
             .await?;
 
         Ok(TypedReference::new(reference))
     }
+
+    async fn delete(
+        typed_ref: &TypedReference<AccountDocument>,
+        identity: &KeyPair,
+        pool: &RecordPool,
+    ) -> Result<(), DocumentError> {
+        let reference = typed_ref.reference();
+        pool.tombstone(reference, [1], identity).await?;
+        pool.tombstone(reference, [0], identity).await?;
+        pool.delete_local(reference.record()).await;
+        Ok(())
+    }
 }
 
 impl MutableDocument for AccountDocument {
@@ -203,9 +290,18 @@ impl MutableDocument for AccountDocument {
             AccountUpdate::Name(name) => base.with_name(name),
             AccountUpdate::Bio(bio) => base.with_bio(bio),
             AccountUpdate::Home(home) => base.with_home(home),
+            // private updates go to their own subkey with their own key
+            AccountUpdate::Following(following) => {
+                let private = view.private.ok_or(DocumentError::NotAuthorised)?;
+                let updated = private.with_following(following)?;
+                let key = private_encryption_key(identity, reference, pool.crypto());
+                let encrypted = Encrypted::encrypt(&updated, &key, pool.crypto())?;
+                pool.write(reference, 1, &encrypted, identity).await?;
+                return Ok(());
+            }
         };
 
-        let encrypted = Encrypted::encrypt(&updated, reference.secret())?;
+        let encrypted = Encrypted::encrypt(&updated, reference.secret(), pool.crypto())?;
         pool.write(reference, 0, &encrypted, identity).await?;
 
         Ok(())
@@ -4,11 +4,11 @@ use veilid_core::KeyPair;
 use crate::{
     api::{Document, DocumentError, Reference, TypedReference},
     models::{
-        DocumentType, Encrypted, FRAGMENT_SUBKEYS, FragmentContent, FragmentHeader, FragmentMime,
-        MAX_CHUNK_BYTES, MAX_FRAGMENT_BYTES, ValidationError,
+        CachedDocument, DocumentType, Encrypted, FRAGMENT_SUBKEYS, FragmentContent, FragmentHeader,
+        FragmentMime, MAX_CHUNK_BYTES, MAX_FRAGMENT_BYTES, SearchText, Timestamp, ValidationError,
     },
     serialisation::{Deserialise, Serialise},
-    veilid::{RecordError, RecordPool, with_crypto},
+    veilid::{RecordError, RecordPool},
 };
 
 // subkeys available for chunk data in the primary record (subkey 0 is the header)
@@ -22,11 +22,17 @@ pub struct FragmentDocument;
 pub struct FragmentView {
     data: Vec<u8>,
     mime: FragmentMime,
+    // author-reported upload time, unset on fragments from before timestamps were recorded
+    created: Option<Timestamp>,
 }
 
 impl FragmentView {
     pub fn new(data: Vec<u8>, mime: FragmentMime) -> Self {
-        Self { data, mime }
+        Self {
+            data,
+            mime,
+            created: None,
+        }
     }
 
     pub fn data(&self) -> &[u8] {
@@ -36,6 +42,11 @@ impl FragmentView {
     pub fn mime(&self) -> &FragmentMime {
         &self.mime
     }
+
+    /// fragments are immutable, so this is also when it was last modified
+    pub fn created(&self) -> Option<Timestamp> {
+        self.created
+    }
 }
 
 impl std::fmt::Display for FragmentView {
@@ -47,6 +58,9 @@ impl std::fmt::Display for FragmentView {
         writeln!(f, "+++")?;
         writeln!(f, "mime = {}", toml_str(self.mime.as_ref()))?;
         writeln!(f, "size = {}", self.data.len())?;
+        if let Some(created) = self.created {
+            writeln!(f, "created = {created}")?;
+        }
         writeln!(f, "+++")?;
 
         if is_text {
@@ -74,12 +88,10 @@ impl Document for FragmentDocument {
         let header: FragmentHeader = pool
             .read(reference, 0, false)
             .await?
-            .decrypt(reference.secret())?;
+            .decrypt(reference.secret(), pool.crypto())?;
 
         let fragment_size = header.fragment_size() as usize;
-        let total_chunks = fragment_size.div_ceil(MAX_CHUNK_BYTES);
-        let num_primary = total_chunks.min(MAX_PRIMARY_CHUNKS);
-        let num_overflow = total_chunks - num_primary;
+        let (num_primary, num_overflow) = split_chunks(fragment_size);
 
         // validate that we have the expected amount of overflow records
         let num_overflow_records = num_overflow.div_ceil(MAX_OVERFLOW_CHUNKS);
@@ -122,7 +134,9 @@ impl Document for FragmentDocument {
         assembled.truncate(fragment_size);
 
         // and if the hash matches...
-        let valid = with_crypto(|c| c.validate_hash(&assembled, header.hash()))
+        let valid = pool
+            .crypto()
+            .with(|c| c.validate_hash(&assembled, header.hash()))
             .map_err(|_| DocumentError::HashMismatch)?;
         if !valid {
             return Err(DocumentError::HashMismatch);
@@ -130,11 +144,12 @@ impl Document for FragmentDocument {
 
         // ... then we can decrypt and return!
         let encrypted = Encrypted::deserialise(&assembled)?;
-        let content: FragmentContent = encrypted.decrypt(reference.secret())?;
+        let content: FragmentContent = encrypted.decrypt(reference.secret(), pool.crypto())?;
 
         Ok(FragmentView {
             data: content.into_data(),
             mime: header.mime().clone(),
+            created: header.created(),
         })
     }
 
@@ -148,7 +163,7 @@ impl Document for FragmentDocument {
 
         // encrypt then serialize. the result is what will get hashed and chunked
         let content = FragmentContent::new(view.data);
-        let data = Encrypted::encrypt(&content, reference.secret())?.serialise()?;
+        let data = Encrypted::encrypt(&content, reference.secret(), pool.crypto())?.serialise()?;
 
         if data.len() > MAX_FRAGMENT_BYTES {
             return Err(ValidationError::Invalid(format!(
@@ -157,7 +172,7 @@ impl Document for FragmentDocument {
             .into());
         }
         let fragment_size = data.len() as u32;
-        let hash = with_crypto(|c| c.generate_hash(&data));
+        let hash = pool.crypto().with(|c| c.generate_hash(&data));
 
         let chunks: Vec<&[u8]> = data.chunks(MAX_CHUNK_BYTES).collect();
         let (primary_chunks, overflow_chunks) =
@@ -180,8 +195,9 @@ impl Document for FragmentDocument {
         let (_, overflow_keys) = try_join(write_primary, write_overflow).await?;
 
         // finally, write the header after all other data has been written
-        let header = FragmentHeader::new(hash, fragment_size, view.mime, overflow_keys)?;
-        let header_encrypted = Encrypted::encrypt(&header, reference.secret())?;
+        let created = Some(Timestamp::now());
+        let header = FragmentHeader::new(hash, fragment_size, view.mime, overflow_keys, created)?;
+        let header_encrypted = Encrypted::encrypt(&header, reference.secret(), pool.crypto())?;
         pool.write(&reference, 0, &header_encrypted, identity)
             .await?;
 
@@ -197,6 +213,105 @@ impl Document for FragmentDocument {
 
         Ok(TypedReference::new(reference))
     }
+
+    async fn delete(
+        typed_ref: &TypedReference<FragmentDocument>,
+        identity: &KeyPair,
+        pool: &RecordPool,
+    ) -> Result<(), DocumentError> {
+        let reference = typed_ref.reference();
+        let header: FragmentHeader = pool
+            .read(reference, 0, false)
+            .await?
+            .decrypt(reference.secret(), pool.crypto())?;
+        let (num_primary, num_overflow) = split_chunks(header.fragment_size() as usize);
+
+        // overflow records first, the header is the only way to find them again
+        try_join_all(header.overflow_keys().iter().enumerate().map(|(i, key)| {
+            let overflow_ref = Reference::new(key.clone(), reference.secret().clone());
+            let chunks = num_overflow
+                .saturating_sub(i * MAX_OVERFLOW_CHUNKS)
+                .min(MAX_OVERFLOW_CHUNKS);
+            async move {
+                pool.tombstone(&overflow_ref, 0..chunks as u32, identity)
+                    .await?;
+                pool.delete_local(overflow_ref.record()).await;
+                Ok::<_, RecordError>(())
+            }
+        }))
+        .await?;
+
+        pool.tombstone(reference, 1..=num_primary as u32, identity)
+            .await?;
+        pool.tombstone(reference, [0], identity).await?;
+        pool.delete_local(reference.record()).await;
+        Ok(())
+    }
+
+    async fn records(
+        typed_ref: &TypedReference<FragmentDocument>,
+        pool: &RecordPool,
+    ) -> Result<Vec<Reference>, DocumentError> {
+        let reference = typed_ref.reference();
+        let header: FragmentHeader = pool
+            .read(reference, 0, false)
+            .await?
+            .decrypt(reference.secret(), pool.crypto())?;
+        Ok(std::iter::once(reference.clone())
+            .chain(
+                header
+                    .overflow_keys()
+                    .iter()
+                    .map(|key| Reference::new(key.clone(), reference.secret().clone())),
+            )
+            .collect())
+    }
+
+    fn to_cached(view: &FragmentView) -> Option<CachedDocument> {
+        Some(CachedDocument::Fragment {
+            data: view.data.clone(),
+            mime: view.mime.clone(),
+            created: view.created,
+        })
+    }
+
+    fn from_cached(cached: CachedDocument, _revision: u32) -> Option<FragmentView> {
+        let CachedDocument::Fragment {
+            data,
+            mime,
+            created,
+        } = cached
+        else {
+            return None;
+        };
+        Some(FragmentView {
+            data,
+            mime,
+            created,
+        })
+    }
+
+    // only text is searchable. the title is the first line, minus any markdown heading marker
+    fn search_text(view: &FragmentView) -> Option<SearchText> {
+        if !view.mime.as_ref().starts_with("text/") {
+            return None;
+        }
+        let text = String::from_utf8_lossy(&view.data).into_owned();
+        let title = text
+            .lines()
+            .map(|l| l.trim_start_matches('#').trim())
+            .find(|l| !l.is_empty())
+            .unwrap_or_default()
+            .to_owned();
+        Some(SearchText::new(title, text, Vec::new()))
+    }
+}
+
+// number of chunks in the primary record and across all overflow records
+fn split_chunks(fragment_size: usize) -> (usize, usize) {
+    let total_chunks = fragment_size.div_ceil(MAX_CHUNK_BYTES);
+    let num_primary = total_chunks.min(MAX_PRIMARY_CHUNKS);
+    (num_primary, total_chunks - num_primary)
 }
 
 // helper for writing all subkeys of a record in parallel
@@ -2,7 +2,10 @@ use veilid_core::KeyPair;
 
 use crate::{
     api::{Document, DocumentError, LARGE_SUBKEYS, MutableDocument, OpenDocument, TypedReference},
-    models::{DocumentType, Encrypted, IndexHeader, IndexName, Trace},
+    models::{
+        CachedDocument, DocumentType, Encrypted, IndexHeader, IndexMetadata, IndexName, SearchText,
+        Timestamp, Trace,
+    },
     veilid::RecordPool,
 };
 
@@ -18,6 +21,13 @@ pub struct IndexView {
     fragment: Option<Trace>,
     // reference to the links record, if any
     links: Option<Trace>,
+    // author-reported times, unset on indexes from before timestamps were recorded
+    created: Option<Timestamp>,
+    modified: Option<Timestamp>,
+    // sequence number of the header subkey, bumped on every edit
+    revision: u32,
+    // tags, description, etc
+    metadata: IndexMetadata,
 }
 
 impl IndexView {
@@ -26,12 +36,17 @@ impl IndexView {
         author: Option<Trace>,
         fragment: Option<Trace>,
         links: Option<Trace>,
+        metadata: IndexMetadata,
     ) -> Self {
         Self {
             name,
             author,
             fragment,
             links,
+            created: None,
+            modified: None,
+            revision: 0,
+            metadata,
         }
     }
 
@@ -47,6 +62,20 @@ impl IndexView {
     pub fn links(&self) -> Option<&Trace> {
         self.links.as_ref()
     }
+    pub fn created(&self) -> Option<Timestamp> {
+        self.created
+    }
+    /// last edit time, falling back to creation time for indexes that have never been edited
+    pub fn modified(&self) -> Option<Timestamp> {
+        self.modified.or(self.created)
+    }
+    /// how many times the index has been edited since creation
+    pub fn revision(&self) -> u32 {
+        self.revision
+    }
+    pub fn metadata(&self) -> &IndexMetadata {
+        &self.metadata
+    }
 
     pub fn with_name(self, name: IndexName) -> Self {
         Self { name, ..self }
@@ -57,6 +86,9 @@ impl IndexView {
     pub fn with_links(self, links: Option<Trace>) -> Self {
         Self { links, ..self }
     }
+    pub fn with_metadata(self, metadata: IndexMetadata) -> Self {
+        Self { metadata, ..self }
+    }
 }
 
 impl std::fmt::Display for IndexView {
@@ -74,6 +106,21 @@ impl std::fmt::Display for IndexView {
         if let Some(links) = &self.links {
             writeln!(f, "links = {}", toml_str(&links.to_string()))?;
         }
+        if !self.metadata.is_empty() {
+            let entries: Vec<String> = self
+                .metadata
+                .iter()
+                .map(|(k, v)| format!("{} = {}", toml_str(k), toml_str(v)))
+                .collect();
+            writeln!(f, "metadata = {{ {} }}", entries.join(", "))?;
+        }
+        if let Some(created) = self.created {
+            writeln!(f, "created = {created}")?;
+        }
+        if let Some(modified) = self.modified {
+            writeln!(f, "modified = {modified}")?;
+        }
+        writeln!(f, "revision = {}", self.revision)?;
         write!(f, "+++")
     }
 }
@@ -82,6 +129,8 @@ pub enum IndexUpdate {
     Name(IndexName),
     Fragment(Option<Trace>),
     Links(Option<Trace>),
+    /// replaces the whole metadata map
+    Metadata(IndexMetadata),
 }
 
 impl Document for IndexDocument {
@@ -96,16 +145,18 @@ impl Document for IndexDocument {
         pool: &RecordPool,
     ) -> Result<IndexView, DocumentError> {
         let reference = typed_ref.reference();
-        let header: IndexHeader = pool
-            .read(reference, 0, force)
-            .await?
-            .decrypt(reference.secret())?;
+        let (encrypted, revision) = pool.read_versioned(reference, 0, force).await?;
+        let header: IndexHeader = encrypted.decrypt(reference.secret(), pool.crypto())?;
 
         Ok(IndexView {
             name: header.name().clone(),
             author: header.author().cloned(),
             fragment: header.fragment().cloned(),
             links: header.links().cloned(),
+            created: header.created(),
+            modified: header.modified(),
+            revision,
+            metadata: header.metadata().clone(),
         })
     }
 
@@ -115,13 +166,100 @@ impl Document for IndexDocument {
         pool: &RecordPool,
     ) -> Result<TypedReference<IndexDocument>, DocumentError> {
         let record = pool.create(identity, Self::MAX_SUBKEYS).await?;
-        let reference = record.reference().clone();
+        let typed_ref = TypedReference::new(record.reference().clone());
+        Self::fill(&typed_ref, view, identity, pool).await?;
+        Ok(typed_ref)
+    }
+
+    async fn delete(
+        typed_ref: &TypedReference<IndexDocument>,
+        identity: &KeyPair,
+        pool: &RecordPool,
+    ) -> Result<(), DocumentError> {
+        let reference = typed_ref.reference();
+        pool.tombstone(reference, [0], identity).await?;
+        pool.delete_local(reference.record()).await;
+        Ok(())
+    }
 
-        let header = IndexHeader::new(view.name, view.author, view.fragment, view.links);
-        let encrypted = Encrypted::encrypt(&header, reference.secret())?;
-        pool.write(&reference, 0, &encrypted, identity).await?;
+    fn revision(view: &IndexView) -> u32 {
+        view.revision
+    }
+
+    fn to_cached(view: &IndexView) -> Option<CachedDocument> {
+        Some(CachedDocument::Index(IndexHeader::new(
+            view.name.clone(),
+            view.author.clone(),
+            view.fragment.clone(),
+            view.links.clone(),
+            view.created,
+            view.modified,
+            view.metadata.clone(),
+        )))
+    }
 
-        Ok(TypedReference::new(reference))
+    fn from_cached(cached: CachedDocument, revision: u32) -> Option<IndexView> {
+        let CachedDocument::Index(header) = cached else {
+            return None;
+        };
+        Some(IndexView {
+            name: header.name().clone(),
+            author: header.author().cloned(),
+            fragment: header.fragment().cloned(),
+            links: header.links().cloned(),
+            created: header.created(),
+            modified: header.modified(),
+            revision,
+            metadata: header.metadata().clone(),
+        })
+    }
+
+    // whatever shows up alongside the index, links included so their targets can be read ahead too.
+    // not the author, account views aren't cached so reading them ahead would be wasted
+    fn prefetch(view: &IndexView) -> Vec<Trace> {
+        [&view.fragment, &view.links]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect()
+    }
+
+    fn search_text(view: &IndexView) -> Option<SearchText> {
+        Some(SearchText::new(
+            view.name.as_ref().to_owned(),
+            view.metadata.description().unwrap_or_default().to_owned(),
+            view.metadata
+                .tags()
+                .into_iter()
+                .map(str::to_owned)
+                .collect(),
+        ))
+    }
+}
+
+impl IndexDocument {
+    /// writes a new index into a record that was created empty,
+    /// e.g. so documents that point at each other can each be written just once
+    pub(crate) async fn fill(
+        typed_ref: &TypedReference<IndexDocument>,
+        view: IndexView,
+        identity: &KeyPair,
+        pool: &RecordPool,
+    ) -> Result<(), DocumentError> {
+        let reference = typed_ref.reference();
+        let now = Timestamp::now();
+        let header = IndexHeader::new(
+            view.name,
+            view.author,
+            view.fragment,
+            view.links,
+            Some(now),
+            Some(now),
+            view.metadata,
+        );
+        let encrypted = Encrypted::encrypt(&header, reference.secret(), pool.crypto())?;
+        pool.write(reference, 0, &encrypted, identity).await?;
+        Ok(())
     }
 }
 
@@ -141,15 +279,20 @@ impl MutableDocument for IndexDocument {
             IndexUpdate::Name(name) => view.with_name(name),
             IndexUpdate::Fragment(fragment) => view.with_fragment(fragment),
             IndexUpdate::Links(links) => view.with_links(links),
+            IndexUpdate::Metadata(metadata) => view.with_metadata(metadata),
         };
+        // keep the original creation time, even if it's unset on older indexes
         let header = IndexHeader::new(
             updated.name,
             updated.author,
             updated.fragment,
             updated.links,
+            updated.created,
+            Some(Timestamp::now()),
+            updated.metadata,
         );
 
-        let encrypted = Encrypted::encrypt(&header, reference.secret())?;
+        let encrypted = Encrypted::encrypt(&header, reference.secret(), pool.crypto())?;
         pool.write(reference, 0, &encrypted, identity).await?;
 
         Ok(())
@@ -1,61 +1,319 @@
+use std::collections::HashMap;
+
+use futures::future::try_join_all;
 use veilid_core::KeyPair;
 
 use crate::{
-    api::{Document, DocumentError, LARGE_SUBKEYS, MutableDocument, OpenDocument, TypedReference},
-    models::{DocumentType, Trace},
+    api::{
+        Document, DocumentError, MANY_SUBKEYS, MutableDocument, OpenDocument, Reference,
+        TypedReference,
+    },
+    models::{
+        CachedDocument, DocumentType, Encrypted, Link, LinkName, LinksHeader, MAX_LINKS,
+        ValidationError,
+    },
     veilid::RecordPool,
 };
 
 pub struct LinksDocument;
 
-// TODO: a list of named traces pointing at other indexes
-#[derive(PartialEq, Debug, Clone)]
+/// an ordered list of (optionally named) traces, usually pointing at other indexes
+#[derive(Debug, Clone)]
 pub struct LinksView {
-    // TODO: this should also use a newtype for a length limited string
-    pub links: Vec<(String, Trace)>,
+    links: Vec<Link>,
+    // subkey each link was read from, so a change to one slot only re-reads that link.
+    // empty for views that weren't read
+    subkeys: Vec<u32>,
+    // sequence number of the header, every change to the list rewrites it. 0 for views that weren't read
+    revision: u32,
+}
+
+impl LinksView {
+    pub fn new(links: Vec<Link>) -> Self {
+        Self {
+            links,
+            subkeys: Vec::new(),
+            revision: 0,
+        }
+    }
+
+    pub fn links(&self) -> &[Link] {
+        &self.links
+    }
+}
+
+// where the links live is bookkeeping, only the links themselves matter
+impl PartialEq for LinksView {
+    fn eq(&self, other: &Self) -> bool {
+        self.links == other.links
+    }
 }
 
 impl std::fmt::Display for LinksView {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "(links not yet implemented)")
+        use crate::serialisation::toml_str;
+
+        writeln!(f, "+++")?;
+        for link in &self.links {
+            writeln!(f, "[[links]]")?;
+            if let Some(name) = link.name() {
+                writeln!(f, "name = {}", toml_str(name.as_ref()))?;
+            }
+            writeln!(f, "trace = {}", toml_str(&link.trace().to_string()))?;
+        }
+        write!(f, "+++")
     }
 }
 
+pub enum LinksUpdate {
+    /// appends a link to the end of the list
+    Add(Link),
+    /// removes the link at a position
+    Remove(usize),
+    /// renames the link at a position
+    Rename(usize, Option<LinkName>),
+}
+
 impl Document for LinksDocument {
-    const MAX_SUBKEYS: u16 = LARGE_SUBKEYS;
+    // lots of small subkeys, one link each
+    const MAX_SUBKEYS: u16 = MANY_SUBKEYS;
     const DOCUMENT_TYPE: DocumentType = DocumentType::Links;
     type View = LinksView;
 
     async fn read(
-        _typed_ref: &TypedReference<LinksDocument>,
+        typed_ref: &TypedReference<LinksDocument>,
         _identity: Option<&KeyPair>,
-        _force: bool,
-        _pool: &RecordPool,
+        force: bool,
+        pool: &RecordPool,
     ) -> Result<LinksView, DocumentError> {
-        todo!("links document read not yet implemented")
+        let reference = typed_ref.reference();
+        let (encrypted, revision) = pool.read_versioned(reference, 0, force).await?;
+        let header: LinksHeader = encrypted.decrypt(reference.secret(), pool.crypto())?;
+
+        // read every link in parallel, keeping the header's order
+        let subkeys: Vec<u32> = header.subkeys().collect();
+        let links = try_join_all(
+            subkeys
+                .iter()
+                .map(|&subkey| read_link(pool, reference, subkey, force)),
+        )
+        .await?;
+
+        Ok(LinksView {
+            links,
+            subkeys,
+            revision,
+        })
+    }
+
+    async fn read_changed(
+        typed_ref: &TypedReference<LinksDocument>,
+        _identity: Option<&KeyPair>,
+        current: &LinksView,
+        subkeys: &[u32],
+        pool: &RecordPool,
+    ) -> Result<LinksView, DocumentError> {
+        let reference = typed_ref.reference();
+        // the header only changes when links are added, moved or removed
+        let (slots, revision) = if subkeys.contains(&0) {
+            let (encrypted, revision) = pool.read_versioned(reference, 0, false).await?;
+            let header: LinksHeader = encrypted.decrypt(reference.secret(), pool.crypto())?;
+            (header.subkeys().collect(), revision)
+        } else {
+            (current.subkeys.clone(), current.revision)
+        };
+
+        // links in untouched slots are kept, everything else is read
+        let kept: HashMap<u32, &Link> = current
+            .subkeys
+            .iter()
+            .copied()
+            .zip(&current.links)
+            .filter(|(subkey, _)| !subkeys.contains(subkey))
+            .collect();
+        let links = try_join_all(slots.iter().map(|subkey| async {
+            match kept.get(subkey) {
+                Some(&link) => Ok(link.clone()),
+                None => read_link(pool, reference, *subkey, false).await,
+            }
+        }))
+        .await?;
+
+        Ok(LinksView {
+            links,
+            subkeys: slots,
+            revision,
+        })
     }
 
     async fn create(
-        _view: LinksView,
-        _identity: &KeyPair,
-        _pool: &RecordPool,
+        view: LinksView,
+        identity: &KeyPair,
+        pool: &RecordPool,
     ) -> Result<TypedReference<LinksDocument>, DocumentError> {
-        todo!("links document create not yet implemented")
+        if view.links.len() > MAX_LINKS {
+            return Err(too_many_links().into());
+        }
+        let record = pool.create(identity, Self::MAX_SUBKEYS).await?;
+        let typed_ref = TypedReference::new(record.reference().clone());
+        Self::fill(&typed_ref, view, identity, pool).await?;
+        Ok(typed_ref)
+    }
+
+    async fn delete(
+        typed_ref: &TypedReference<LinksDocument>,
+        identity: &KeyPair,
+        pool: &RecordPool,
+    ) -> Result<(), DocumentError> {
+        let reference = typed_ref.reference();
+        let header: LinksHeader = pool
+            .read(reference, 0, false)
+            .await?
+            .decrypt(reference.secret(), pool.crypto())?;
+
+        // removed links are still sitting in their old subkeys, so clear everything ever written
+        pool.tombstone(reference, header.written(), identity)
+            .await?;
+        pool.tombstone(reference, [0], identity).await?;
+        pool.delete_local(reference.record()).await;
+        Ok(())
+    }
+
+    fn revision(view: &LinksView) -> u32 {
+        view.revision
+    }
+
+    fn to_cached(view: &LinksView) -> Option<CachedDocument> {
+        Some(CachedDocument::Links {
+            links: view.links.clone(),
+            subkeys: view.subkeys.clone(),
+        })
+    }
+
+    fn from_cached(cached: CachedDocument, revision: u32) -> Option<LinksView> {
+        let CachedDocument::Links { links, subkeys } = cached else {
+            return None;
+        };
+        Some(LinksView {
+            links,
+            subkeys,
+            revision,
+        })
     }
 }
 
-// TODO: add/remove/rename links
-pub enum LinksUpdate {}
+impl LinksDocument {
+    /// writes a new links list into a record that was created empty,
+    /// e.g. so documents that point at each other can each be written just once
+    pub(crate) async fn fill(
+        typed_ref: &TypedReference<LinksDocument>,
+        view: LinksView,
+        identity: &KeyPair,
+        pool: &RecordPool,
+    ) -> Result<(), DocumentError> {
+        let reference = typed_ref.reference();
+        let mut header = LinksHeader::default();
+        let writes = view
+            .links
+            .iter()
+            .map(|link| {
+                let subkey = header.push().ok_or_else(too_many_links)?;
+                Ok((subkey, link))
+            })
+            .collect::<Result<Vec<_>, ValidationError>>()?;
+        try_join_all(
+            writes
+                .into_iter()
+                .map(|(subkey, link)| write_link(pool, identity, reference, subkey, link)),
+        )
+        .await?;
+
+        // header last, so it never points at links that haven't been written yet
+        let encrypted = Encrypted::encrypt(&header, reference.secret(), pool.crypto())?;
+        pool.write(reference, 0, &encrypted, identity).await?;
+
+        Ok(())
+    }
+}
 
 impl MutableDocument for LinksDocument {
     type Update = LinksUpdate;
 
     async fn update(
-        _update: LinksUpdate,
-        _doc: &OpenDocument<LinksDocument>,
-        _identity: &KeyPair,
-        _pool: &RecordPool,
+        update: LinksUpdate,
+        doc: &OpenDocument<LinksDocument>,
+        identity: &KeyPair,
+        pool: &RecordPool,
     ) -> Result<(), DocumentError> {
-        todo!("links document update not yet implemented")
+        let view = doc.updates.borrow().clone()?;
+        let reference = doc.reference.reference();
+
+        // the view doesn't carry the slot mapping, so grab the header too.
+        // no need to force, the open document's watch keeps the local copy fresh
+        let mut header: LinksHeader = pool
+            .read(reference, 0, false)
+            .await?
+            .decrypt(reference.secret(), pool.crypto())?;
+
+        match update {
+            LinksUpdate::Add(link) => {
+                let subkey = header.push().ok_or_else(too_many_links)?;
+                write_link(pool, identity, reference, subkey, &link).await?;
+            }
+            LinksUpdate::Remove(position) => {
+                if !header.remove(position) {
+                    return Err(no_link_at(position).into());
+                }
+            }
+            LinksUpdate::Rename(position, name) => {
+                let link = view
+                    .links
+                    .get(position)
+                    .cloned()
+                    .ok_or_else(|| no_link_at(position))?
+                    .with_name(name);
+                let subkey = header
+                    .rewrite(position)
+                    .ok_or_else(|| no_link_at(position))?;
+                write_link(pool, identity, reference, subkey, &link).await?;
+            }
+        }
+
+        let encrypted = Encrypted::encrypt(&header, reference.secret(), pool.crypto())?;
+        pool.write(reference, 0, &encrypted, identity).await?;
+
+        Ok(())
     }
 }
+
+async fn read_link(
+    pool: &RecordPool,
+    reference: &Reference,
+    subkey: u32,
+    force: bool,
+) -> Result<Link, DocumentError> {
+    Ok(pool
+        .read(reference, subkey, force)
+        .await?
+        .decrypt(reference.secret(), pool.crypto())?)
+}
+
+async fn write_link(
+    pool: &RecordPool,
+    identity: &KeyPair,
+    reference: &Reference,
+    subkey: u32,
+    link: &Link,
+) -> Result<(), DocumentError> {
+    let encrypted = Encrypted::encrypt(link, reference.secret(), pool.crypto())?;
+    pool.write(reference, subkey, &encrypted, identity).await?;
+    Ok(())
+}
+
+fn too_many_links() -> ValidationError {
+    ValidationError::TooLong(format!("a links record can hold at most {MAX_LINKS} links"))
+}
+
+fn no_link_at(position: usize) -> ValidationError {
+    ValidationError::Invalid(format!("no link at position {position}"))
+}
@@ -9,18 +9,27 @@ mod proto;
 mod serialisation;
 mod veilid;
 
+// private in-process networks for end-to-end tests
+#[cfg(test)]
+mod testing;
+
 // public modules
 pub mod documents;
 pub mod models;
 
 // re-export core api types directly
 pub use api::{
-    Document, Intersect, IntersectError, LockedTypedReference, MutableDocument, OpenDocument,
-    NotUnlocked, ProtectedTypedReference, TypedReference, TypedTrace, WrongDocumentType,
+    Crawl, CrawlFailure, CrawlNode, CrawledView, Document, DocumentChange, DocumentError, Feed,
+    FeedChange, FeedItem, Intersect, IntersectError, LockedTypedReference, MutableDocument,
+    NotUnlocked, OpenDocument, PendingDocument, ProtectedTypedReference, TypedReference,
+    TypedTrace, WrongDocumentType,
 };
 
 // along with the network / connection setup types from veilid
-pub use veilid::{ConnectionParams, ConnectionStrength, NetworkState};
+pub use veilid::{
+    ConnectionParams, ConnectionStrength, NetworkState, PendingSync, RecordError, RecordStats,
+    RetryPolicy, StoreError, SyncState, WatchMode,
+};
 
 /// platform agnostic logger
 #[macro_export]
@@ -6,7 +6,7 @@ use crate::{
     serialisation::{
         DeserialisationError, SerialisableV0, SerialisationError, impl_v0_proto_conversions,
     },
-    veilid::with_crypto,
+    veilid::Crypto,
 };
 
 // TODO: this shouldn't derive debug.
@@ -32,8 +32,9 @@ impl Access {
     pub(crate) fn new_protected(
         secret: &SharedSecret,
         password: &str,
+        crypto: &Crypto,
     ) -> Result<Self, EncryptionError> {
-        let protected = ProtectedSecret::new(secret, password)?;
+        let protected = ProtectedSecret::new(secret, password, crypto)?;
         Ok(Self::Protected {
             protected_secret: protected,
         })
@@ -102,19 +103,28 @@ pub struct ProtectedSecret {
 }
 
 impl ProtectedSecret {
-    pub fn new(secret: &SharedSecret, password: &str) -> Result<Self, EncryptionError> {
-        let salt = with_crypto(|c| c.random_nonce());
-        let (encrypted, _secret) = Encrypted::encrypt_with_password(secret, password, &salt)?;
+    pub(crate) fn new(
+        secret: &SharedSecret,
+        password: &str,
+        crypto: &Crypto,
+    ) -> Result<Self, EncryptionError> {
+        let salt = crypto.with(|c| c.random_nonce());
+        let (encrypted, _secret) =
+            Encrypted::encrypt_with_password(secret, password, &salt, crypto)?;
         Ok(Self {
             salt,
             encrypted_secret: encrypted,
         })
     }
 
-    pub fn unlock(&self, password: &str) -> Result<SharedSecret, AccessError> {
+    pub(crate) fn unlock(
+        &self,
+        password: &str,
+        crypto: &Crypto,
+    ) -> Result<SharedSecret, AccessError> {
         let secret = self
             .encrypted_secret
-            .decrypt_with_password(password, &self.salt)
+            .decrypt_with_password(password, &self.salt, crypto)
             .map_err(|_| AccessError::WrongPassword)?;
         Ok(secret)
     }
@@ -8,7 +8,6 @@ use crate::{
         DeserialisationError, Deserialise, SerialisableV0, SerialisationError, Serialise,
         impl_string_conversions, impl_v0_proto_conversions,
     },
-    veilid::with_crypto,
 };
 
 /// the public half of an account's signing keypair.
@@ -27,12 +26,13 @@ impl AccountPublicKey {
 
     /// hashes the crypto kind + bare key bytes to produce a stable 128-bit fingerprint.
     /// intended for display alongside usernames (which are not unique) to aid disambiguation.
+    /// blake3, same as VLD0's `generate_hash`, but without needing a running node.
     pub fn fingerprint_bytes(&self) -> [u8; 16] {
         let mut data = Vec::with_capacity(36);
         data.extend_from_slice(self.0.kind().bytes()); // 4 bytes: crypto kind (domain separation)
         data.extend_from_slice(&self.0.value()); // 32 bytes: bare public key
-        let hash = with_crypto(|c| c.generate_hash(&data));
-        hash.value()[..16].try_into().unwrap()
+        let hash = blake3::hash(&data);
+        hash.as_bytes()[..16].try_into().unwrap()
     }
 
     /// base58-encoded fingerprint for display
@@ -183,21 +183,43 @@ impl SerialisableV0 for AccountPublic {
 
 impl_v0_proto_conversions! {AccountPublic}
 
+// the private section has to fit in a single subkey along with everything else in it,
+// and an unlocked trace is a bit over 100 bytes serialised
+pub const MAX_FOLLOWING: usize = 200;
+
 #[derive(PartialEq, Eq, Debug, Clone)]
 // TODO: private account data is currently exposed as-is in AccountView.
 // ideally we'd either inline its fields directly into the view or gate access more carefully.
 pub struct AccountPrivate {
     bookmarks: Option<Trace>,
+    // account traces, kept private so follows can't be enumerated by others
+    following: Vec<Trace>,
 }
 
 impl AccountPrivate {
-    pub fn new(bookmarks: Option<Trace>) -> Self {
-        Self { bookmarks }
+    pub fn new(bookmarks: Option<Trace>, following: Vec<Trace>) -> Self {
+        Self {
+            bookmarks,
+            following,
+        }
     }
 
     pub fn bookmarks(&self) -> Option<&Trace> {
         self.bookmarks.as_ref()
     }
+    pub fn following(&self) -> &[Trace] {
+        &self.following
+    }
+
+    pub fn with_following(self, following: Vec<Trace>) -> Result<Self, ValidationError> {
+        guard!(
+            following.len() <= MAX_FOLLOWING,
+            Err(ValidationError::TooLong(format!(
+                "an account can follow at most {MAX_FOLLOWING} accounts"
+            )))
+        );
+        Ok(Self { following, ..self })
+    }
 }
 
 impl SerialisableV0 for AccountPrivate {
@@ -206,12 +228,22 @@ impl SerialisableV0 for AccountPrivate {
     fn to_proto(&self) -> Result<Self::Proto, SerialisationError> {
         Ok(Self::Proto {
             bookmarks: self.bookmarks().map(TryInto::try_into).transpose()?,
+            following: self
+                .following
+                .iter()
+                .map(TryInto::try_into)
+                .collect::<Result<_, _>>()?,
         })
     }
 
     fn from_proto(proto: Self::Proto) -> Result<Self, DeserialisationError> {
         let bookmarks: Option<Trace> = proto.bookmarks.map(TryInto::try_into).transpose()?;
-        Ok(Self::new(bookmarks))
+        let following = proto
+            .following
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_, _>>()?;
+        Ok(Self::new(bookmarks, following))
     }
 }
 
@@ -252,3 +284,28 @@ impl SerialisableV0 for AccountSecret {
 
 impl_v0_proto_conversions! {AccountSecret}
 impl_string_conversions! {AccountSecret}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::models::validate_fingerprint;
+
+    fn key(c: char) -> AccountPublicKey {
+        AccountPublicKey::new(
+            PublicKey::from_str(&format!(
+                "VLD0:{c}X9L_EV3JAy5ozyK875WErKAyFhBy4jZ-6DZajlDr9c"
+            ))
+            .unwrap(),
+        )
+    }
+
+    #[test]
+    fn fingerprint_without_a_node() {
+        let fingerprint = key('a').fingerprint();
+        validate_fingerprint(&fingerprint).unwrap();
+        assert_eq!(fingerprint, key('a').fingerprint());
+        assert_ne!(fingerprint, key('b').fingerprint());
+    }
+}
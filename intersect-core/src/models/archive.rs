@@ -0,0 +1,212 @@
+use std::collections::HashSet;
+
+use guard_clause::guard;
+
+use crate::{
+    models::{DocumentType, FragmentMime, IndexMetadata, IndexName, Link, Trace, ValidationError},
+    proto::{self, v0::intersect::archive_entry},
+    serialisation::{
+        DeserialisationError, Deserialise, SerialisableV0, SerialisationError, Serialise,
+        impl_v0_proto_conversions,
+    },
+};
+
+/// the decrypted contents of a single archived document.
+/// accounts are never archived, they can't be re-created by someone else anyway.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum ArchivedDocument {
+    Index {
+        name: IndexName,
+        fragment: Option<Trace>,
+        links: Option<Trace>,
+        metadata: IndexMetadata,
+    },
+    Fragment {
+        data: Vec<u8>,
+        mime: FragmentMime,
+    },
+    Links(Vec<Link>),
+}
+
+impl ArchivedDocument {
+    pub fn document_type(&self) -> DocumentType {
+        match self {
+            Self::Index { .. } => DocumentType::Index,
+            Self::Fragment { .. } => DocumentType::Fragment,
+            Self::Links(_) => DocumentType::Links,
+        }
+    }
+}
+
+/// an archived document along with the trace it was exported from
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct ArchiveEntry {
+    trace: Trace,
+    document: ArchivedDocument,
+}
+
+impl ArchiveEntry {
+    pub fn new(trace: Trace, document: ArchivedDocument) -> Result<Self, ValidationError> {
+        guard!(
+            *trace.document_type() == document.document_type(),
+            Err(ValidationError::Invalid(
+                "archive entry trace doesn't match its document type".to_string()
+            ))
+        );
+        Ok(Self { trace, document })
+    }
+
+    pub fn trace(&self) -> &Trace {
+        &self.trace
+    }
+    pub fn document(&self) -> &ArchivedDocument {
+        &self.document
+    }
+}
+
+/// a portable bundle of documents, for backups and moving content between namespaces.
+/// see `Intersect::export` and `Intersect::import`.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Archive {
+    root: Trace,
+    entries: Vec<ArchiveEntry>,
+}
+
+impl Archive {
+    /// the root has to be one of the entries, and each record can only appear once
+    pub fn new(root: Trace, entries: Vec<ArchiveEntry>) -> Result<Self, ValidationError> {
+        let mut seen = HashSet::new();
+        guard!(
+            entries.iter().all(|e| seen.insert(e.trace.record())),
+            Err(ValidationError::Invalid(
+                "archive contains the same document twice".to_string()
+            ))
+        );
+        guard!(
+            seen.contains(root.record()),
+            Err(ValidationError::Invalid(
+                "archive root is missing from its entries".to_string()
+            ))
+        );
+        Ok(Self { root, entries })
+    }
+
+    pub fn root(&self) -> &Trace {
+        &self.root
+    }
+    pub fn entries(&self) -> &[ArchiveEntry] {
+        &self.entries
+    }
+
+    /// fourcc-prefixed binary form, for writing to a file
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerialisationError> {
+        self.serialise()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DeserialisationError> {
+        Self::deserialise(bytes)
+    }
+}
+
+impl SerialisableV0 for ArchiveEntry {
+    type Proto = proto::v0::intersect::ArchiveEntry;
+
+    fn to_proto(&self) -> Result<Self::Proto, SerialisationError> {
+        let document = match &self.document {
+            ArchivedDocument::Index {
+                name,
+                fragment,
+                links,
+                metadata,
+            } => archive_entry::Document::Index(proto::v0::intersect::ArchivedIndex {
+                name: name.as_ref().to_owned(),
+                fragment: fragment.as_ref().map(TryInto::try_into).transpose()?,
+                links: links.as_ref().map(TryInto::try_into).transpose()?,
+                metadata: metadata
+                    .iter()
+                    .map(|(k, v)| (k.to_owned(), v.to_owned()))
+                    .collect(),
+            }),
+            ArchivedDocument::Fragment { data, mime } => {
+                archive_entry::Document::Fragment(proto::v0::intersect::ArchivedFragment {
+                    mime: mime.as_ref().to_owned(),
+                    data: data.clone(),
+                })
+            }
+            ArchivedDocument::Links(links) => {
+                archive_entry::Document::Links(proto::v0::intersect::ArchivedLinks {
+                    links: links
+                        .iter()
+                        .map(TryInto::try_into)
+                        .collect::<Result<_, _>>()?,
+                })
+            }
+        };
+        Ok(Self::Proto {
+            trace: Some((&self.trace).try_into()?),
+            document: Some(document),
+        })
+    }
+
+    fn from_proto(proto: Self::Proto) -> Result<Self, DeserialisationError> {
+        let trace = proto
+            .trace
+            .ok_or(DeserialisationError::MissingField("trace".to_owned()))?
+            .try_into()?;
+        let document = match proto
+            .document
+            .ok_or(DeserialisationError::MissingField("document".to_owned()))?
+        {
+            archive_entry::Document::Index(index) => ArchivedDocument::Index {
+                name: IndexName::new(index.name)?,
+                fragment: index.fragment.map(TryInto::try_into).transpose()?,
+                links: index.links.map(TryInto::try_into).transpose()?,
+                metadata: IndexMetadata::new(index.metadata.into_iter().collect())?,
+            },
+            archive_entry::Document::Fragment(fragment) => ArchivedDocument::Fragment {
+                data: fragment.data,
+                mime: FragmentMime::new(fragment.mime)?,
+            },
+            archive_entry::Document::Links(links) => ArchivedDocument::Links(
+                links
+                    .links
+                    .into_iter()
+                    .map(TryInto::try_into)
+                    .collect::<Result<_, _>>()?,
+            ),
+        };
+        Ok(Self::new(trace, document)?)
+    }
+}
+
+impl_v0_proto_conversions! {ArchiveEntry}
+
+impl SerialisableV0 for Archive {
+    type Proto = proto::v0::intersect::Archive;
+
+    fn to_proto(&self) -> Result<Self::Proto, SerialisationError> {
+        Ok(Self::Proto {
+            root: Some((&self.root).try_into()?),
+            entries: self
+                .entries
+                .iter()
+                .map(TryInto::try_into)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    fn from_proto(proto: Self::Proto) -> Result<Self, DeserialisationError> {
+        let root = proto
+            .root
+            .ok_or(DeserialisationError::MissingField("root".to_owned()))?
+            .try_into()?;
+        let entries: Vec<ArchiveEntry> = proto
+            .entries
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_, _>>()?;
+        Ok(Self::new(root, entries)?)
+    }
+}
+
+impl_v0_proto_conversions! {Archive}
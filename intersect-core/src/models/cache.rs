@@ -0,0 +1,243 @@
+use prost::Message;
+
+use crate::{
+    models::{FragmentMime, IndexHeader, Link, Timestamp},
+    proto::{self, v0::intersect::cached_view},
+    serialisation::{
+        DeserialisationError, SerialisableV0, SerialisationError, impl_v0_proto_conversions,
+    },
+};
+
+/// the decrypted contents of a document as last seen on this device.
+/// accounts aren't cached, their views can hold the account's private data.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum CachedDocument {
+    Index(IndexHeader),
+    Fragment {
+        data: Vec<u8>,
+        mime: FragmentMime,
+        created: Option<Timestamp>,
+    },
+    Links {
+        links: Vec<Link>,
+        // subkey each link was read from, so partial re-reads still work on a cached view
+        subkeys: Vec<u32>,
+    },
+}
+
+/// a cached document along with the revision it was read at
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct CachedView {
+    revision: u32,
+    document: CachedDocument,
+}
+
+impl CachedView {
+    pub(crate) fn new(revision: u32, document: CachedDocument) -> Self {
+        Self { revision, document }
+    }
+
+    pub fn revision(&self) -> u32 {
+        self.revision
+    }
+
+    pub(crate) fn into_document(self) -> CachedDocument {
+        self.document
+    }
+
+    /// bytes the view takes once serialised, which is what the cache budgets are counted in
+    pub(crate) fn size(&self) -> Result<usize, SerialisationError> {
+        Ok(self.to_proto()?.encoded_len())
+    }
+}
+
+impl SerialisableV0 for CachedView {
+    type Proto = proto::v0::intersect::CachedView;
+
+    fn to_proto(&self) -> Result<Self::Proto, SerialisationError> {
+        let document = match &self.document {
+            CachedDocument::Index(header) => cached_view::Document::Index(header.try_into()?),
+            CachedDocument::Fragment {
+                data,
+                mime,
+                created,
+            } => cached_view::Document::Fragment(proto::v0::intersect::CachedFragment {
+                mime: mime.as_ref().to_owned(),
+                data: data.clone(),
+                created: created.map(|t| t.as_micros()),
+            }),
+            CachedDocument::Links { links, subkeys } => {
+                cached_view::Document::Links(proto::v0::intersect::CachedLinks {
+                    links: links
+                        .iter()
+                        .map(TryInto::try_into)
+                        .collect::<Result<_, _>>()?,
+                    subkeys: subkeys.clone(),
+                })
+            }
+        };
+        Ok(Self::Proto {
+            revision: self.revision,
+            document: Some(document),
+        })
+    }
+
+    fn from_proto(proto: Self::Proto) -> Result<Self, DeserialisationError> {
+        let document = match proto
+            .document
+            .ok_or(DeserialisationError::MissingField("document".to_owned()))?
+        {
+            cached_view::Document::Index(header) => CachedDocument::Index(header.try_into()?),
+            cached_view::Document::Fragment(fragment) => CachedDocument::Fragment {
+                data: fragment.data,
+                mime: FragmentMime::new(fragment.mime)?,
+                created: fragment.created.map(Timestamp::from_micros),
+            },
+            cached_view::Document::Links(links) => {
+                if links.subkeys.len() != links.links.len() {
+                    return Err(DeserialisationError::Failed(
+                        "cached links don't line up with their subkeys".to_owned(),
+                    ));
+                }
+                CachedDocument::Links {
+                    links: links
+                        .links
+                        .into_iter()
+                        .map(TryInto::try_into)
+                        .collect::<Result<_, _>>()?,
+                    subkeys: links.subkeys,
+                }
+            }
+        };
+        Ok(Self::new(proto.revision, document))
+    }
+}
+
+impl_v0_proto_conversions! {CachedView}
+
+/// how much room a persisted view takes, which revision it is and when it was last used (µs timestamp),
+/// so an older revision never replaces it and the least recently used views go first once the cache is full
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct CacheUsage {
+    revision: u32,
+    size: u64,
+    last_used: u64,
+}
+
+impl CacheUsage {
+    pub(crate) fn new(revision: u32, size: u64, last_used: u64) -> Self {
+        Self {
+            revision,
+            size,
+            last_used,
+        }
+    }
+
+    pub fn revision(&self) -> u32 {
+        self.revision
+    }
+
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn last_used(&self) -> u64 {
+        self.last_used
+    }
+}
+
+impl SerialisableV0 for CacheUsage {
+    type Proto = proto::v0::intersect::CacheUsage;
+
+    fn to_proto(&self) -> Result<Self::Proto, SerialisationError> {
+        Ok(Self::Proto {
+            revision: self.revision,
+            size: self.size,
+            last_used: self.last_used,
+        })
+    }
+
+    fn from_proto(proto: Self::Proto) -> Result<Self, DeserialisationError> {
+        Ok(Self::new(proto.revision, proto.size, proto.last_used))
+    }
+}
+
+impl_v0_proto_conversions! {CacheUsage}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use veilid_core::RecordKey;
+
+    use super::*;
+    use crate::{
+        models::{Access, DocumentType, IndexMetadata, IndexName, LinkName, Trace},
+        serialisation::{Deserialise, Serialise},
+    };
+
+    fn trace(c: char) -> Trace {
+        let key = RecordKey::from_str(&format!(
+            "VLD0:{c}X9L_EV3JAy5ozyK875WErKAyFhBy4jZ-6DZajlDr9c:KpS0JtGg9OfJhpsIVCFY8FI9arViozN3kw3duglNkmY"
+        ))
+        .unwrap();
+        Trace::new(DocumentType::Fragment, &key, Access::Locked)
+    }
+
+    fn roundtrip(view: CachedView) {
+        let bytes = view.serialise().unwrap();
+        assert_eq!(CachedView::deserialise(&bytes).unwrap(), view);
+    }
+
+    #[test]
+    fn view_roundtrip() {
+        let header = IndexHeader::new(
+            IndexName::new("notes".to_string()).unwrap(),
+            None,
+            Some(trace('a')),
+            Some(trace('b')),
+            Some(Timestamp::from_micros(1)),
+            None,
+            IndexMetadata::default(),
+        );
+        roundtrip(CachedView::new(3, CachedDocument::Index(header)));
+        roundtrip(CachedView::new(
+            1,
+            CachedDocument::Fragment {
+                data: b"hello".to_vec(),
+                mime: FragmentMime::new("text/plain".to_string()).unwrap(),
+                created: Some(Timestamp::from_micros(2)),
+            },
+        ));
+        roundtrip(CachedView::new(
+            7,
+            CachedDocument::Links {
+                links: vec![
+                    Link::new(trace('c'), None),
+                    Link::new(trace('d'), Some(LinkName::new("d".to_string()).unwrap())),
+                ],
+                subkeys: vec![2, 1],
+            },
+        ));
+    }
+
+    #[test]
+    fn links_have_to_line_up_with_subkeys() {
+        let view = CachedView::new(
+            1,
+            CachedDocument::Links {
+                links: vec![Link::new(trace('c'), None)],
+                subkeys: vec![1, 2],
+            },
+        );
+        let bytes = view.serialise().unwrap();
+        assert!(CachedView::deserialise(&bytes).is_err());
+    }
+
+    #[test]
+    fn usage_roundtrip() {
+        let usage = CacheUsage::new(3, 1024, 42);
+        let bytes = usage.serialise().unwrap();
+        assert_eq!(CacheUsage::deserialise(&bytes).unwrap(), usage);
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::BTreeMap;
+
+use guard_clause::guard;
+
+use crate::{
+    models::{AccountName, ValidationError},
+    proto,
+    serialisation::{
+        DeserialisationError, SerialisableV0, SerialisationError, impl_v0_proto_conversions,
+    },
+};
+
+const PETNAME_MAX_BYTES: usize = 64;
+// fingerprints are the first 128 bits of the public key hash
+const FINGERPRINT_BYTES: usize = 16;
+
+/// a locally chosen name for an account, max 64 bytes.
+/// unlike AccountName this is never published, it only exists on this device.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Petname(String);
+
+impl Petname {
+    pub fn new(name: String) -> Result<Self, ValidationError> {
+        guard!(
+            !name.trim().is_empty(),
+            Err(ValidationError::Invalid(
+                "petname can't be empty".to_string()
+            ))
+        );
+        guard!(
+            name.len() <= PETNAME_MAX_BYTES,
+            Err(ValidationError::TooLong(format!(
+                "petname can be at most {PETNAME_MAX_BYTES} bytes"
+            )))
+        );
+        Ok(Self(name))
+    }
+
+    // display names and petnames are compared loosely,
+    // since "Alice" vs "alice" is exactly the kind of thing an impersonator would try
+    fn matches(&self, name: &str) -> bool {
+        self.0.trim().to_lowercase() == name.trim().to_lowercase()
+    }
+}
+
+impl AsRef<str> for Petname {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// how much we trust that a fingerprint belongs to who we think it does
+#[derive(PartialEq, Eq, Debug, Clone, Copy, PartialOrd, Ord)]
+pub enum TrustLevel {
+    /// seen and named, but nothing has been checked
+    Known,
+    /// fingerprint has been confirmed out-of-band
+    Verified,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Contact {
+    petname: Petname,
+    trust: TrustLevel,
+}
+
+impl Contact {
+    pub fn new(petname: Petname, trust: TrustLevel) -> Self {
+        Self { petname, trust }
+    }
+
+    pub fn petname(&self) -> &Petname {
+        &self.petname
+    }
+    pub fn trust(&self) -> TrustLevel {
+        self.trust
+    }
+}
+
+impl SerialisableV0 for Contact {
+    type Proto = proto::v0::intersect::Contact;
+
+    fn to_proto(&self) -> Result<Self::Proto, SerialisationError> {
+        Ok(Self::Proto {
+            petname: self.petname.as_ref().to_owned(),
+            trust: match self.trust {
+                TrustLevel::Known => proto::v0::intersect::TrustLevel::Known as i32,
+                TrustLevel::Verified => proto::v0::intersect::TrustLevel::Verified as i32,
+            },
+        })
+    }
+
+    fn from_proto(proto: Self::Proto) -> Result<Self, DeserialisationError> {
+        let trust = match proto::v0::intersect::TrustLevel::try_from(proto.trust)
+            .map_err(|_| DeserialisationError::Failed("invalid trust level".to_string()))?
+        {
+            proto::v0::intersect::TrustLevel::Known => TrustLevel::Known,
+            proto::v0::intersect::TrustLevel::Verified => TrustLevel::Verified,
+        };
+        Ok(Self::new(Petname::new(proto.petname)?, trust))
+    }
+}
+
+impl_v0_proto_conversions! {Contact}
+
+/// what the local contact list has to say about an account
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum ContactStatus {
+    /// the fingerprint belongs to one of our contacts
+    Known(Contact),
+    /// the fingerprint is unknown, but the account's display name matches one of our contacts.
+    /// could be an impersonation attempt, or just a common name. either way the user should know.
+    NameConflict {
+        /// the contact whose petname matched
+        contact: Contact,
+        /// the fingerprint we actually know that contact by
+        fingerprint: String,
+    },
+    /// never seen before, nothing to say
+    Unknown,
+}
+
+/// local petname store, mapping account fingerprints to contacts.
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct Contacts(BTreeMap<String, Contact>);
+
+impl Contacts {
+    pub fn get(&self, fingerprint: &str) -> Option<&Contact> {
+        self.0.get(fingerprint)
+    }
+
+    /// (fingerprint, contact) pairs ordered by fingerprint
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Contact)> {
+        self.0.iter().map(|(f, c)| (f.as_str(), c))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// checks an account's fingerprint and display name against the contact list
+    pub fn status(&self, fingerprint: &str, name: Option<&AccountName>) -> ContactStatus {
+        if let Some(contact) = self.get(fingerprint) {
+            return ContactStatus::Known(contact.clone());
+        }
+        let conflict = name.and_then(|name| {
+            self.0
+                .iter()
+                .find(|(_, c)| c.petname.matches(name.as_ref()))
+        });
+        match conflict {
+            Some((fingerprint, contact)) => ContactStatus::NameConflict {
+                contact: contact.clone(),
+                fingerprint: fingerprint.clone(),
+            },
+            None => ContactStatus::Unknown,
+        }
+    }
+
+    pub(crate) fn insert(&mut self, fingerprint: String, contact: Contact) {
+        self.0.insert(fingerprint, contact);
+    }
+
+    pub(crate) fn remove(&mut self, fingerprint: &str) -> Option<Contact> {
+        self.0.remove(fingerprint)
+    }
+}
+
+/// checks that a string is a well-formed account fingerprint (base58, 16 bytes)
+pub(crate) fn validate_fingerprint(fingerprint: &str) -> Result<(), ValidationError> {
+    let bytes = bs58::decode(fingerprint)
+        .into_vec()
+        .map_err(|_| ValidationError::Invalid("fingerprint is not valid base58".to_string()))?;
+    guard!(
+        bytes.len() == FINGERPRINT_BYTES,
+        Err(ValidationError::Invalid(format!(
+            "fingerprint must be {FINGERPRINT_BYTES} bytes"
+        )))
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contacts_with(fingerprint: &str, petname: &str) -> Contacts {
+        let mut contacts = Contacts::default();
+        let petname = Petname::new(petname.to_string()).unwrap();
+        contacts.insert(
+            fingerprint.to_string(),
+            Contact::new(petname, TrustLevel::Verified),
+        );
+        contacts
+    }
+
+    #[test]
+    fn name_conflict_on_unknown_fingerprint() {
+        let contacts = contacts_with("known", "Alice");
+        let name = AccountName::new("alice ".to_string()).unwrap();
+
+        assert!(matches!(
+            contacts.status("known", Some(&name)),
+            ContactStatus::Known(_)
+        ));
+        assert!(matches!(
+            contacts.status("someone-else", Some(&name)),
+            ContactStatus::NameConflict { fingerprint, .. } if fingerprint == "known"
+        ));
+        assert_eq!(
+            contacts.status("someone-else", None),
+            ContactStatus::Unknown
+        );
+    }
+}
@@ -8,7 +8,7 @@ use crate::{
         DeserialisationError, Deserialise, SerialisableV0, SerialisationError, Serialise,
         impl_v0_proto_conversions,
     },
-    veilid::with_crypto,
+    veilid::Crypto,
 };
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -21,19 +21,22 @@ impl Encrypted {
     pub fn encrypt<T: Serialise>(
         data: &T,
         shared_secret: &SharedSecret,
+        crypto: &Crypto,
     ) -> Result<Self, EncryptionError> {
         let body = data.serialise()?;
-        let nonce = with_crypto(|c| c.random_nonce());
-        let ciphertext = with_crypto(|c| c.encrypt_aead(&body, &nonce, shared_secret, None))
+        let nonce = crypto.with(|c| c.random_nonce());
+        let ciphertext = crypto
+            .with(|c| c.encrypt_aead(&body, &nonce, shared_secret, None))
             .map_err(|e| EncryptionError::EncryptionFailed(e.to_string()))?;
         Ok(Encrypted { nonce, ciphertext })
     }
 
     pub fn encrypt_with_random<T: Serialise>(
         data: &T,
+        crypto: &Crypto,
     ) -> Result<(Self, SharedSecret), EncryptionError> {
-        let key = with_crypto(|c| c.random_shared_secret());
-        let encrypted = Self::encrypt(data, &key)?;
+        let key = crypto.with(|c| c.random_shared_secret());
+        let encrypted = Self::encrypt(data, &key, crypto)?;
         Ok((encrypted, key))
     }
 
@@ -41,29 +44,48 @@ impl Encrypted {
         data: &T,
         password: &str,
         salt: &[u8],
+        crypto: &Crypto,
     ) -> Result<(Self, SharedSecret), EncryptionError> {
-        let hash = Self::password_hash(password, salt)?;
-        let encrypted = Self::encrypt(data, &hash)?;
+        let hash = Self::password_hash(password, salt, crypto)?;
+        let encrypted = Self::encrypt(data, &hash, crypto)?;
         Ok((encrypted, hash))
     }
 
     pub fn decrypt<T: Deserialise>(
         &self,
         shared_secret: &SharedSecret,
+        crypto: &Crypto,
     ) -> Result<T, EncryptionError> {
-        let bytes =
-            with_crypto(|c| c.decrypt_aead(&self.ciphertext, &self.nonce, shared_secret, None))
-                .map_err(|e| EncryptionError::DecryptionFailed(e.to_string()))?;
+        let bytes = crypto
+            .with(|c| c.decrypt_aead(&self.ciphertext, &self.nonce, shared_secret, None))
+            .map_err(|e| EncryptionError::DecryptionFailed(e.to_string()))?;
         Ok(T::deserialise(&bytes)?)
     }
 
+    /// the same plaintext under a fresh nonce, so it can be written again as a new value
+    pub(crate) fn reseal(
+        &self,
+        shared_secret: &SharedSecret,
+        crypto: &Crypto,
+    ) -> Result<Self, EncryptionError> {
+        let body = crypto
+            .with(|c| c.decrypt_aead(&self.ciphertext, &self.nonce, shared_secret, None))
+            .map_err(|e| EncryptionError::DecryptionFailed(e.to_string()))?;
+        let nonce = crypto.with(|c| c.random_nonce());
+        let ciphertext = crypto
+            .with(|c| c.encrypt_aead(&body, &nonce, shared_secret, None))
+            .map_err(|e| EncryptionError::EncryptionFailed(e.to_string()))?;
+        Ok(Encrypted { nonce, ciphertext })
+    }
+
     pub fn decrypt_with_password<T: Deserialise>(
         &self,
         password: &str,
         salt: &[u8],
+        crypto: &Crypto,
     ) -> Result<T, EncryptionError> {
-        let hash = Self::password_hash(password, salt)?;
-        self.decrypt(&hash)
+        let hash = Self::password_hash(password, salt, crypto)?;
+        self.decrypt(&hash, crypto)
     }
 
     fn validate_password(password: &str) -> Result<(), EncryptionError> {
@@ -88,7 +110,11 @@ impl Encrypted {
         Ok(())
     }
 
-    fn password_hash(password: &str, salt: &[u8]) -> Result<SharedSecret, EncryptionError> {
+    fn password_hash(
+        password: &str,
+        salt: &[u8],
+        crypto: &Crypto,
+    ) -> Result<SharedSecret, EncryptionError> {
         Self::validate_password(password)?;
         guard!(
             // limits taken from the underlying implementation in VLD0
@@ -98,7 +124,8 @@ impl Encrypted {
             ))
         );
 
-        let hash = with_crypto(|c| c.derive_shared_secret(password.as_bytes(), salt))
+        let hash = crypto
+            .with(|c| c.derive_shared_secret(password.as_bytes(), salt))
             .map_err(|e| EncryptionError::EncryptionFailed(e.to_string()))?;
         Ok(hash)
     }
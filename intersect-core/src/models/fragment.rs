@@ -2,7 +2,7 @@ use guard_clause::guard;
 use veilid_core::{HashDigest, RecordKey};
 
 use crate::{
-    models::ValidationError,
+    models::{Timestamp, ValidationError},
     proto,
     serialisation::{
         DeserialisationError, SerialisableV0, SerialisationError, impl_v0_proto_conversions,
@@ -61,6 +61,8 @@ pub struct FragmentHeader {
     mime: FragmentMime,
     // overflow records sharing the same writer key and encryption
     overflow_keys: Vec<RecordKey>,
+    // unset for headers written before timestamps were added
+    created: Option<Timestamp>,
 }
 
 impl FragmentHeader {
@@ -69,6 +71,7 @@ impl FragmentHeader {
         fragment_size: u32,
         mime: FragmentMime,
         overflow_keys: Vec<RecordKey>,
+        created: Option<Timestamp>,
     ) -> Result<Self, ValidationError> {
         guard!(
             fragment_size > 0 && fragment_size as usize <= MAX_FRAGMENT_BYTES,
@@ -81,6 +84,7 @@ impl FragmentHeader {
             fragment_size,
             mime,
             overflow_keys,
+            created,
         })
     }
 
@@ -99,6 +103,10 @@ impl FragmentHeader {
     pub fn overflow_keys(&self) -> &[RecordKey] {
         &self.overflow_keys
     }
+
+    pub fn created(&self) -> Option<Timestamp> {
+        self.created
+    }
 }
 
 impl SerialisableV0 for FragmentHeader {
@@ -114,6 +122,7 @@ impl SerialisableV0 for FragmentHeader {
                 .iter()
                 .map(|k| k.try_into())
                 .collect::<Result<_, _>>()?,
+            created: self.created.map(|t| t.as_micros()),
         })
     }
 
@@ -129,7 +138,8 @@ impl SerialisableV0 for FragmentHeader {
             .into_iter()
             .map(RecordKey::from)
             .collect();
-        Self::new(hash, proto.fragment_size, mime, overflow_keys)
+        let created = proto.created.map(Timestamp::from_micros);
+        Self::new(hash, proto.fragment_size, mime, overflow_keys, created)
             .map_err(|e| DeserialisationError::Failed(e.to_string()))
     }
 }
@@ -1,7 +1,9 @@
+use std::{collections::BTreeMap, str::FromStr};
+
 use guard_clause::guard;
 
 use crate::{
-    models::{Trace, ValidationError},
+    models::{Timestamp, Trace, ValidationError},
     proto,
     serialisation::{
         DeserialisationError, SerialisableV0, SerialisationError, impl_v0_proto_conversions,
@@ -9,6 +11,18 @@ use crate::{
 };
 
 const INDEX_NAME_MAX_BYTES: usize = 256;
+const METADATA_MAX_ENTRIES: usize = 32;
+const METADATA_KEY_MAX_BYTES: usize = 64;
+const METADATA_VALUE_MAX_BYTES: usize = 1024;
+// keys and values together. the whole header has to fit in one subkey after encryption,
+// and 32 entries at their individual limits wouldn't
+const METADATA_MAX_BYTES: usize = 8 * 1024;
+
+// well-known metadata keys. anything else is allowed too, these just get typed accessors
+pub const METADATA_TAGS: &str = "tags";
+pub const METADATA_DESCRIPTION: &str = "description";
+pub const METADATA_LANGUAGE: &str = "language";
+pub const METADATA_COVER: &str = "cover";
 
 /// display name for an index document with length validation
 #[derive(PartialEq, Eq, Debug, Clone)]
@@ -32,6 +46,141 @@ impl AsRef<str> for IndexName {
     }
 }
 
+/// free-form string key-value metadata for an index.
+/// at most 32 entries, keys at most 64 bytes and values at most 1 KiB, 8 KiB in total.
+/// tags are stored comma-separated under "tags".
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct IndexMetadata(BTreeMap<String, String>);
+
+impl IndexMetadata {
+    pub fn new(entries: BTreeMap<String, String>) -> Result<Self, ValidationError> {
+        guard!(
+            entries.len() <= METADATA_MAX_ENTRIES,
+            Err(ValidationError::TooLong(format!(
+                "metadata can have at most {METADATA_MAX_ENTRIES} entries"
+            )))
+        );
+        for (key, value) in &entries {
+            validate_metadata_entry(key, value)?;
+        }
+        let total: usize = entries.iter().map(|(k, v)| k.len() + v.len()).sum();
+        guard!(
+            total <= METADATA_MAX_BYTES,
+            Err(ValidationError::TooLong(format!(
+                "metadata can be at most {METADATA_MAX_BYTES} bytes in total"
+            )))
+        );
+        Ok(Self(entries))
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    /// (key, value) pairs ordered by key
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// sets a key, replacing any existing value
+    pub fn with(self, key: String, value: String) -> Result<Self, ValidationError> {
+        let mut entries = self.0;
+        entries.insert(key, value);
+        Self::new(entries)
+    }
+
+    pub fn without(self, key: &str) -> Self {
+        let mut entries = self.0;
+        entries.remove(key);
+        Self(entries)
+    }
+
+    pub fn tags(&self) -> Vec<&str> {
+        self.get(METADATA_TAGS)
+            .map(|tags| {
+                tags.split(',')
+                    .map(str::trim)
+                    .filter(|t| !t.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// case-insensitive tag check, for filtering
+    pub fn has_tag(&self, tag: &str) -> bool {
+        let tag = tag.trim().to_lowercase();
+        self.tags().iter().any(|t| t.to_lowercase() == tag)
+    }
+
+    /// replaces the tag list. tags can't contain commas. an empty list removes the key.
+    pub fn with_tags<S: AsRef<str>>(self, tags: &[S]) -> Result<Self, ValidationError> {
+        let tags: Vec<&str> = tags
+            .iter()
+            .map(|t| t.as_ref().trim())
+            .filter(|t| !t.is_empty())
+            .collect();
+        guard!(
+            tags.iter().all(|t| !t.contains(',')),
+            Err(ValidationError::Invalid(
+                "tags can't contain commas".to_string()
+            ))
+        );
+        if tags.is_empty() {
+            return Ok(self.without(METADATA_TAGS));
+        }
+        self.with(METADATA_TAGS.to_string(), tags.join(","))
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.get(METADATA_DESCRIPTION)
+    }
+
+    /// language tag, e.g. "en" or "pt-BR". not validated beyond the usual size limits
+    pub fn language(&self) -> Option<&str> {
+        self.get(METADATA_LANGUAGE)
+    }
+
+    /// trace of a cover image fragment. None if unset or not a valid trace
+    pub fn cover(&self) -> Option<Trace> {
+        self.get(METADATA_COVER)
+            .and_then(|c| Trace::from_str(c).ok())
+    }
+
+    pub fn with_cover(self, cover: &Trace) -> Result<Self, ValidationError> {
+        self.with(METADATA_COVER.to_string(), cover.to_string())
+    }
+}
+
+fn validate_metadata_entry(key: &str, value: &str) -> Result<(), ValidationError> {
+    guard!(
+        !key.trim().is_empty(),
+        Err(ValidationError::Invalid(
+            "metadata key can't be empty".to_string()
+        ))
+    );
+    guard!(
+        key.len() <= METADATA_KEY_MAX_BYTES,
+        Err(ValidationError::TooLong(format!(
+            "metadata key can be at most {METADATA_KEY_MAX_BYTES} bytes"
+        )))
+    );
+    guard!(
+        value.len() <= METADATA_VALUE_MAX_BYTES,
+        Err(ValidationError::TooLong(format!(
+            "metadata value for \"{key}\" can be at most {METADATA_VALUE_MAX_BYTES} bytes"
+        )))
+    );
+    Ok(())
+}
+
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub struct IndexHeader {
     // user-readable name for the index, max 256 bytes
@@ -42,6 +191,10 @@ pub struct IndexHeader {
     fragment: Option<Trace>,
     // reference to the links record, if any
     links: Option<Trace>,
+    // unset for headers written before timestamps were added
+    created: Option<Timestamp>,
+    modified: Option<Timestamp>,
+    metadata: IndexMetadata,
 }
 
 impl IndexHeader {
@@ -50,12 +203,18 @@ impl IndexHeader {
         author: Option<Trace>,
         fragment: Option<Trace>,
         links: Option<Trace>,
+        created: Option<Timestamp>,
+        modified: Option<Timestamp>,
+        metadata: IndexMetadata,
     ) -> Self {
         Self {
             name,
             author,
             fragment,
             links,
+            created,
+            modified,
+            metadata,
         }
     }
 
@@ -71,6 +230,15 @@ impl IndexHeader {
     pub fn links(&self) -> Option<&Trace> {
         self.links.as_ref()
     }
+    pub fn created(&self) -> Option<Timestamp> {
+        self.created
+    }
+    pub fn modified(&self) -> Option<Timestamp> {
+        self.modified
+    }
+    pub fn metadata(&self) -> &IndexMetadata {
+        &self.metadata
+    }
 }
 
 impl SerialisableV0 for IndexHeader {
@@ -82,6 +250,9 @@ impl SerialisableV0 for IndexHeader {
             author: self.author.as_ref().map(TryInto::try_into).transpose()?,
             fragment: self.fragment.as_ref().map(TryInto::try_into).transpose()?,
             links: self.links.as_ref().map(TryInto::try_into).transpose()?,
+            created: self.created.map(|t| t.as_micros()),
+            modified: self.modified.map(|t| t.as_micros()),
+            metadata: self.metadata.0.clone().into_iter().collect(),
         })
     }
 
@@ -91,8 +262,93 @@ impl SerialisableV0 for IndexHeader {
             author: proto.author.map(TryInto::try_into).transpose()?,
             fragment: proto.fragment.map(TryInto::try_into).transpose()?,
             links: proto.links.map(TryInto::try_into).transpose()?,
+            created: proto.created.map(Timestamp::from_micros),
+            modified: proto.modified.map(Timestamp::from_micros),
+            // headers written before metadata existed decode to an empty map
+            metadata: IndexMetadata::new(proto.metadata.into_iter().collect())?,
         })
     }
 }
 
 impl_v0_proto_conversions! {IndexHeader}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+
+    use super::*;
+
+    fn metadata(entries: &[(&str, &str)]) -> Result<IndexMetadata, ValidationError> {
+        IndexMetadata::new(
+            entries
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn metadata_validation() {
+        assert!(metadata(&[("description", "fine")]).is_ok());
+        assert!(matches!(
+            metadata(&[(" ", "value")]),
+            Err(ValidationError::Invalid(_))
+        ));
+        assert!(matches!(
+            metadata(&[("k".repeat(METADATA_KEY_MAX_BYTES + 1).as_str(), "value")]),
+            Err(ValidationError::TooLong(_))
+        ));
+        assert!(matches!(
+            metadata(&[("key", "v".repeat(METADATA_VALUE_MAX_BYTES + 1).as_str())]),
+            Err(ValidationError::TooLong(_))
+        ));
+
+        let too_many: BTreeMap<String, String> = (0..=METADATA_MAX_ENTRIES)
+            .map(|i| (format!("key-{i}"), String::new()))
+            .collect();
+        assert!(matches!(
+            IndexMetadata::new(too_many),
+            Err(ValidationError::TooLong(_))
+        ));
+
+        // every entry is within its own limits, but not all of them together
+        let too_big: BTreeMap<String, String> = (0..METADATA_MAX_ENTRIES)
+            .map(|i| (format!("key-{i}"), "v".repeat(METADATA_VALUE_MAX_BYTES)))
+            .collect();
+        assert!(matches!(
+            IndexMetadata::new(too_big),
+            Err(ValidationError::TooLong(_))
+        ));
+    }
+
+    #[test]
+    fn tags() {
+        let tagged = IndexMetadata::default()
+            .with_tags(&[" Music ", "", "live"])
+            .unwrap();
+        assert_eq!(tagged.tags(), vec!["Music", "live"]);
+        assert!(tagged.has_tag("music"));
+        assert!(matches!(
+            IndexMetadata::default().with_tags(&["a,b"]),
+            Err(ValidationError::Invalid(_))
+        ));
+        assert!(tagged.with_tags::<&str>(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn header_without_metadata() {
+        // what a header written before timestamps and metadata existed looks like on the wire
+        let old = proto::v0::intersect::IndexHeader {
+            name: "old".to_string(),
+            ..Default::default()
+        };
+        let bytes = old.encode_to_vec();
+
+        let proto = proto::v0::intersect::IndexHeader::decode(bytes.as_slice()).unwrap();
+        let header = IndexHeader::from_proto(proto).unwrap();
+        assert_eq!(header.name().as_ref(), "old");
+        assert!(header.metadata().is_empty());
+        assert_eq!(header.created(), None);
+        assert_eq!(header.modified(), None);
+    }
+}
@@ -0,0 +1,246 @@
+use guard_clause::guard;
+
+use crate::{
+    models::{Trace, ValidationError},
+    proto,
+    serialisation::{
+        DeserialisationError, SerialisableV0, SerialisationError, impl_v0_proto_conversions,
+    },
+};
+
+const LINK_NAME_MAX_BYTES: usize = 256;
+// subkey 0 is the header, every other subkey can hold one link
+pub const MAX_LINKS: usize = 255;
+
+/// display name for a link with length validation
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct LinkName(String);
+
+impl LinkName {
+    pub fn new(name: String) -> Result<Self, ValidationError> {
+        guard!(
+            name.len() <= LINK_NAME_MAX_BYTES,
+            Err(ValidationError::TooLong(format!(
+                "link name can be at most {LINK_NAME_MAX_BYTES} bytes"
+            )))
+        );
+        Ok(Self(name))
+    }
+}
+
+impl AsRef<str> for LinkName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// a (optionally named) trace stored in a links record
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Link {
+    trace: Trace,
+    name: Option<LinkName>,
+}
+
+impl Link {
+    pub fn new(trace: Trace, name: Option<LinkName>) -> Self {
+        Self { trace, name }
+    }
+
+    pub fn trace(&self) -> &Trace {
+        &self.trace
+    }
+    pub fn name(&self) -> Option<&LinkName> {
+        self.name.as_ref()
+    }
+
+    pub fn with_name(self, name: Option<LinkName>) -> Self {
+        Self { name, ..self }
+    }
+}
+
+impl SerialisableV0 for Link {
+    type Proto = proto::v0::intersect::Link;
+
+    fn to_proto(&self) -> Result<Self::Proto, SerialisationError> {
+        Ok(Self::Proto {
+            trace: Some((&self.trace).try_into()?),
+            name: self.name.as_ref().map(|n| n.as_ref().to_owned()),
+        })
+    }
+
+    fn from_proto(proto: Self::Proto) -> Result<Self, DeserialisationError> {
+        let trace = proto
+            .trace
+            .ok_or(DeserialisationError::MissingField("trace".to_owned()))?
+            .try_into()?;
+        Ok(Self {
+            trace,
+            name: proto.name.map(LinkName::new).transpose()?,
+        })
+    }
+}
+
+impl_v0_proto_conversions! {Link}
+
+/// maps logical link positions to subkeys, DHTShortArray style.
+/// lets links be reordered or removed by rewriting only the header.
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub struct LinksHeader {
+    // subkey - 1 for each logical position
+    index: Vec<u8>,
+    // per-subkey write counters (indexed by subkey - 1). 0 = never written
+    seqs: Vec<u32>,
+}
+
+impl LinksHeader {
+    pub fn new(index: Vec<u8>, seqs: Vec<u32>) -> Result<Self, ValidationError> {
+        guard!(
+            index.len() <= MAX_LINKS && seqs.len() <= MAX_LINKS,
+            Err(ValidationError::TooLong(format!(
+                "a links record can hold at most {MAX_LINKS} links"
+            )))
+        );
+        let mut seen = [false; MAX_LINKS];
+        for &slot in &index {
+            let slot = slot as usize;
+            guard!(
+                slot < MAX_LINKS && !seen[slot],
+                Err(ValidationError::Invalid(
+                    "links index has an out of range or duplicate slot".to_string()
+                ))
+            );
+            seen[slot] = true;
+        }
+        Ok(Self { index, seqs })
+    }
+
+    /// subkeys holding each link, in logical order
+    pub fn subkeys(&self) -> impl Iterator<Item = u32> + '_ {
+        self.index.iter().map(|&slot| slot as u32 + 1)
+    }
+
+    /// every subkey that has ever held a link, including ones since removed
+    pub fn written(&self) -> impl Iterator<Item = u32> + '_ {
+        self.seqs
+            .iter()
+            .zip(1..)
+            .filter(|(seq, _)| **seq > 0)
+            .map(|(_, subkey)| subkey)
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// claims a free subkey for a new link at the end of the list, bumping its write counter.
+    /// returns None if the record is full.
+    pub(crate) fn push(&mut self) -> Option<u32> {
+        let slot = (0..MAX_LINKS as u8).find(|s| !self.index.contains(s))?;
+        self.index.push(slot);
+        self.bump(slot);
+        Some(slot as u32 + 1)
+    }
+
+    /// subkey of the link at a logical position, bumping its write counter for an overwrite
+    pub(crate) fn rewrite(&mut self, position: usize) -> Option<u32> {
+        let slot = *self.index.get(position)?;
+        self.bump(slot);
+        Some(slot as u32 + 1)
+    }
+
+    /// drops a link from the list. its subkey is left as-is and gets reused by a later push.
+    pub(crate) fn remove(&mut self, position: usize) -> bool {
+        if position >= self.index.len() {
+            return false;
+        }
+        self.index.remove(position);
+        true
+    }
+
+    fn bump(&mut self, slot: u8) {
+        let slot = slot as usize;
+        if self.seqs.len() <= slot {
+            self.seqs.resize(slot + 1, 0);
+        }
+        self.seqs[slot] += 1;
+    }
+}
+
+impl SerialisableV0 for LinksHeader {
+    type Proto = proto::v0::intersect::LinksHeader;
+
+    fn to_proto(&self) -> Result<Self::Proto, SerialisationError> {
+        Ok(Self::Proto {
+            index: self.index.clone(),
+            seqs: self.seqs.clone(),
+        })
+    }
+
+    fn from_proto(proto: Self::Proto) -> Result<Self, DeserialisationError> {
+        Ok(Self::new(proto.index, proto.seqs)?)
+    }
+}
+
+impl_v0_proto_conversions! {LinksHeader}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serialisation::{Deserialise, Serialise};
+
+    #[test]
+    fn header_roundtrip() {
+        let mut header = LinksHeader::default();
+        assert_eq!(header.push(), Some(1));
+        assert_eq!(header.push(), Some(2));
+        assert_eq!(header.push(), Some(3));
+        assert!(header.remove(0));
+        assert_eq!(header.rewrite(0), Some(2));
+
+        let bytes = header.serialise().unwrap();
+        let decoded = LinksHeader::deserialise(&bytes).unwrap();
+        assert_eq!(decoded, header);
+        assert_eq!(decoded.subkeys().collect::<Vec<_>>(), vec![2, 3]);
+        // the removed link's subkey was still written once
+        assert_eq!(decoded.written().collect::<Vec<_>>(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn push_reuses_removed_subkeys() {
+        let mut header = LinksHeader::default();
+        header.push();
+        header.push();
+        header.remove(0);
+        assert_eq!(header.push(), Some(1));
+        assert_eq!(header.subkeys().collect::<Vec<_>>(), vec![2, 1]);
+        // seqs are indexed by slot, so subkey 1's counter is at seqs[0]
+        assert_eq!(header.seqs, vec![2, 1]);
+    }
+
+    #[test]
+    fn header_validation() {
+        assert!(LinksHeader::new(vec![0, 4, 2], vec![1, 0, 1, 0, 1]).is_ok());
+        assert!(matches!(
+            LinksHeader::new(vec![1, 1], Vec::new()),
+            Err(ValidationError::Invalid(_))
+        ));
+        assert!(matches!(
+            LinksHeader::new(vec![MAX_LINKS as u8], Vec::new()),
+            Err(ValidationError::Invalid(_))
+        ));
+        assert!(matches!(
+            LinksHeader::new(Vec::new(), vec![0; MAX_LINKS + 1]),
+            Err(ValidationError::TooLong(_))
+        ));
+
+        let mut full = LinksHeader::default();
+        for _ in 0..MAX_LINKS {
+            assert!(full.push().is_some());
+        }
+        assert_eq!(full.push(), None);
+    }
+}
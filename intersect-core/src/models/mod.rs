@@ -1,24 +1,46 @@
 mod account;
 mod access;
+mod archive;
+mod cache;
+mod contact;
 mod encrypted;
 mod fragment;
 mod index;
+mod links;
+mod pin;
+mod search;
+mod timestamp;
 mod trace;
 
 // public types (re-exported from lib.rs)
-pub use account::{AccountBio, AccountName, AccountPrivate, AccountPublicKey, AccountSecret};
+pub use account::{
+    AccountBio, AccountName, AccountPrivate, AccountPublicKey, AccountSecret, MAX_FOLLOWING,
+};
 pub use access::AccessError;
+pub use archive::{Archive, ArchiveEntry, ArchivedDocument};
+pub use contact::{Contact, ContactStatus, Contacts, Petname, TrustLevel};
 pub use encrypted::EncryptionError;
 pub use fragment::{FragmentMime, FRAGMENT_SUBKEYS, MAX_CHUNK_BYTES, MAX_FRAGMENT_BYTES};
-pub use index::IndexName;
+pub use index::{
+    IndexMetadata, IndexName, METADATA_COVER, METADATA_DESCRIPTION, METADATA_LANGUAGE,
+    METADATA_TAGS,
+};
+pub use links::{Link, LinkName, MAX_LINKS};
+pub use pin::{Pin, PinStatus, PinnedDocument};
+pub use search::{SearchResult, SearchText};
+pub use timestamp::Timestamp;
 pub use trace::{DocumentType, Trace, TraceSecret};
 
 // crate-internal types
 pub(crate) use account::AccountPublic;
 pub(crate) use access::{Access, ProtectedSecret};
+pub(crate) use cache::{CacheUsage, CachedDocument, CachedView};
+pub(crate) use contact::validate_fingerprint;
 pub(crate) use encrypted::Encrypted;
 pub(crate) use fragment::{FragmentContent, FragmentHeader};
 pub(crate) use index::IndexHeader;
+pub(crate) use links::LinksHeader;
+pub(crate) use search::{SearchEntry, SearchIndex};
 
 use thiserror::Error;
 
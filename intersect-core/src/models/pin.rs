@@ -0,0 +1,158 @@
+use veilid_core::PublicKey;
+
+use crate::{
+    models::{Timestamp, Trace},
+    proto,
+    serialisation::{
+        DeserialisationError, SerialisableV0, SerialisationError, impl_v0_proto_conversions,
+    },
+};
+
+/// a document this device keeps alive on the network.
+/// dht values expire if nobody refreshes them, so pinned documents get re-read
+/// (and re-pushed, if we wrote them) every so often.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Pin {
+    trace: Trace,
+    own: bool,
+    // None on explicit pins, and own pins from before this was recorded
+    owner: Option<PublicKey>,
+}
+
+impl Pin {
+    /// an explicit pin
+    pub(crate) fn new(trace: Trace) -> Self {
+        Self {
+            trace,
+            own: false,
+            owner: None,
+        }
+    }
+
+    /// pinned automatically, written with `owner`'s keypair
+    pub(crate) fn new_own(trace: Trace, owner: PublicKey) -> Self {
+        Self {
+            trace,
+            own: true,
+            owner: Some(owner),
+        }
+    }
+
+    pub fn trace(&self) -> &Trace {
+        &self.trace
+    }
+
+    /// pinned automatically because we wrote it, rather than explicitly
+    pub fn own(&self) -> bool {
+        self.own
+    }
+
+    /// whether this pin can be pushed again while `current` is the session's public key.
+    /// own pins written by another account can't, so they're left alone until it's back
+    pub(crate) fn writable_by(&self, current: &PublicKey) -> bool {
+        self.own && self.owner.as_ref().is_none_or(|owner| owner == current)
+    }
+
+    /// stable per-document key, shared by every trace pointing at the same record
+    pub(crate) fn key(&self) -> String {
+        self.trace.record().to_string()
+    }
+
+    /// what's left when the same document is pinned again with `other`.
+    /// once own, a pin stays own (with its owner), so the refresher keeps pushing it with the writer's key
+    pub(crate) fn merge(&self, other: Pin) -> Pin {
+        match self.own {
+            true => self.clone(),
+            false => other,
+        }
+    }
+}
+
+impl SerialisableV0 for Pin {
+    type Proto = proto::v0::intersect::Pin;
+
+    fn to_proto(&self) -> Result<Self::Proto, SerialisationError> {
+        Ok(Self::Proto {
+            trace: Some((&self.trace).try_into()?),
+            own: self.own,
+            owner: self.owner.as_ref().map(Into::into),
+        })
+    }
+
+    fn from_proto(proto: Self::Proto) -> Result<Self, DeserialisationError> {
+        let trace = proto
+            .trace
+            .ok_or(DeserialisationError::MissingField("trace".to_owned()))?
+            .try_into()?;
+        Ok(Self {
+            trace,
+            own: proto.own,
+            owner: proto.owner.map(Into::into),
+        })
+    }
+}
+
+impl_v0_proto_conversions! {Pin}
+
+/// how the last refresh of a pinned document went. only kept for the current session.
+#[derive(PartialEq, Eq, Debug, Clone, Default)]
+pub enum PinStatus {
+    /// not refreshed yet this session, or written by an account other than the current one
+    #[default]
+    Waiting,
+    Refreshed {
+        at: Timestamp,
+        /// subkeys the network had lost and that were pushed again from our copy
+        repushed: usize,
+        /// subkeys the network had lost that couldn't be pushed again,
+        /// because they aren't ours or can't be re-encrypted (fragment data)
+        lost: usize,
+    },
+    Failed(String),
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct PinnedDocument {
+    pub pin: Pin,
+    pub status: PinStatus,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use veilid_core::RecordKey;
+
+    use super::*;
+    use crate::models::{Access, DocumentType};
+
+    fn trace() -> Trace {
+        let key = RecordKey::from_str(
+            "VLD0:sX9L_EV3JAy5ozyK875WErKAyFhBy4jZ-6DZajlDr9c:KpS0JtGg9OfJhpsIVCFY8FI9arViozN3kw3duglNkmY",
+        )
+        .unwrap();
+        Trace::new(DocumentType::Index, &key, Access::Locked)
+    }
+
+    fn owner() -> PublicKey {
+        PublicKey::from_str("VLD0:aX9L_EV3JAy5ozyK875WErKAyFhBy4jZ-6DZajlDr9c").unwrap()
+    }
+
+    #[test]
+    fn explicit_pin_keeps_own() {
+        let own = Pin::new_own(trace(), owner());
+        let merged = own.merge(Pin::new(trace()));
+        assert_eq!(merged, own);
+        assert!(merged.writable_by(&owner()));
+    }
+
+    #[test]
+    fn own_pin_takes_over_explicit() {
+        let own = Pin::new_own(trace(), owner());
+        assert_eq!(Pin::new(trace()).merge(own.clone()), own);
+        assert_eq!(
+            Pin::new(trace()).merge(Pin::new(trace())),
+            Pin::new(trace())
+        );
+    }
+}
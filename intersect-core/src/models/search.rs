@@ -0,0 +1,260 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::{
+    models::Trace,
+    proto,
+    serialisation::{
+        DeserialisationError, SerialisableV0, SerialisationError, impl_v0_proto_conversions,
+    },
+};
+
+// fragments can be up to 32MiB, but there's no point indexing (and persisting) all of that
+const MAX_INDEXED_BODY_BYTES: usize = 64 * 1024;
+// single letters and the like just match everything
+const MIN_TOKEN_CHARS: usize = 2;
+const SNIPPET_BEFORE_BYTES: usize = 40;
+const SNIPPET_AFTER_BYTES: usize = 120;
+
+/// the searchable text of a document.
+/// produced by documents that support local search (see `Document::search_text`).
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct SearchText {
+    title: String,
+    body: String,
+    tags: Vec<String>,
+}
+
+impl SearchText {
+    pub(crate) fn new(title: String, body: String, tags: Vec<String>) -> Self {
+        Self {
+            title,
+            body: truncate(body, MAX_INDEXED_BODY_BYTES),
+            tags,
+        }
+    }
+
+    fn tokens(&self) -> BTreeSet<String> {
+        let mut tokens = tokenise(&self.title);
+        tokens.extend(tokenise(&self.body));
+        tokens.extend(self.tags.iter().flat_map(|t| tokenise(t)));
+        tokens
+    }
+}
+
+/// a document that's been seen locally, along with its text
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub(crate) struct SearchEntry {
+    trace: Trace,
+    text: SearchText,
+}
+
+impl SearchEntry {
+    pub(crate) fn new(trace: Trace, text: SearchText) -> Self {
+        Self { trace, text }
+    }
+
+    /// stable per-document key, shared by every trace pointing at the same record
+    pub(crate) fn key(&self) -> String {
+        self.trace.record().to_string()
+    }
+}
+
+impl SerialisableV0 for SearchEntry {
+    type Proto = proto::v0::intersect::SearchEntry;
+
+    fn to_proto(&self) -> Result<Self::Proto, SerialisationError> {
+        Ok(Self::Proto {
+            trace: Some((&self.trace).try_into()?),
+            title: self.text.title.clone(),
+            body: self.text.body.clone(),
+            tags: self.text.tags.clone(),
+        })
+    }
+
+    fn from_proto(proto: Self::Proto) -> Result<Self, DeserialisationError> {
+        let trace = proto
+            .trace
+            .ok_or(DeserialisationError::MissingField("trace".to_owned()))?
+            .try_into()?;
+        Ok(Self::new(
+            trace,
+            SearchText::new(proto.title, proto.body, proto.tags),
+        ))
+    }
+}
+
+impl_v0_proto_conversions! {SearchEntry}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct SearchResult {
+    pub trace: Trace,
+    pub title: String,
+    /// a bit of text around the first match, if the match was in the body
+    pub snippet: Option<String>,
+}
+
+/// in-memory inverted index over the locally seen documents.
+/// only the entries are persisted, the token map is rebuilt on load.
+#[derive(Default)]
+pub(crate) struct SearchIndex {
+    entries: HashMap<String, SearchEntry>,
+    // token -> keys of entries containing it. ordered so prefix lookups are a range scan
+    tokens: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl SearchIndex {
+    pub(crate) fn insert(&mut self, entry: SearchEntry) {
+        let key = entry.key();
+        self.remove(&key);
+        for token in entry.text.tokens() {
+            self.tokens.entry(token).or_default().insert(key.clone());
+        }
+        self.entries.insert(key, entry);
+    }
+
+    pub(crate) fn remove(&mut self, key: &str) {
+        let Some(old) = self.entries.remove(key) else {
+            return;
+        };
+        for token in old.text.tokens() {
+            if let Some(keys) = self.tokens.get_mut(&token) {
+                keys.remove(key);
+                if keys.is_empty() {
+                    self.tokens.remove(&token);
+                }
+            }
+        }
+    }
+
+    /// true if the entry is already indexed with exactly this text
+    pub(crate) fn contains(&self, entry: &SearchEntry) -> bool {
+        self.entries.get(&entry.key()) == Some(entry)
+    }
+
+    /// every query word has to prefix-match a word in the document.
+    /// results are ranked with title matches first, then by how often the words appear.
+    pub(crate) fn search(&self, query: &str, limit: usize) -> Vec<SearchResult> {
+        let words = tokenise(query);
+        if words.is_empty() {
+            return Vec::new();
+        }
+
+        let mut matching: Option<BTreeSet<&String>> = None;
+        for word in &words {
+            let keys: BTreeSet<&String> = self
+                .tokens
+                .range(word.clone()..)
+                .take_while(|(token, _)| token.starts_with(word.as_str()))
+                .flat_map(|(_, keys)| keys)
+                .collect();
+            matching = Some(match matching {
+                None => keys,
+                Some(m) => m.intersection(&keys).copied().collect(),
+            });
+        }
+
+        let mut scored: Vec<(usize, &SearchEntry)> = matching
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|key| self.entries.get(key))
+            .map(|entry| (score(entry, &words), entry))
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0));
+
+        scored
+            .into_iter()
+            .take(limit)
+            .map(|(_, entry)| SearchResult {
+                trace: entry.trace.clone(),
+                title: entry.text.title.clone(),
+                snippet: snippet(&entry.text.body, &words),
+            })
+            .collect()
+    }
+}
+
+/// lowercased alphanumeric words, deduplicated
+fn tokenise(text: &str) -> BTreeSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.chars().count() >= MIN_TOKEN_CHARS)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+fn score(entry: &SearchEntry, words: &BTreeSet<String>) -> usize {
+    let count = |text: &str| {
+        text.split(|c: char| !c.is_alphanumeric())
+            .map(str::to_lowercase)
+            .filter(|w| words.iter().any(|q| w.starts_with(q.as_str())))
+            .count()
+    };
+    // a title hit is worth a lot more than another mention in the body
+    count(&entry.text.title) * 10
+        + entry.text.tags.iter().map(|t| count(t)).sum::<usize>() * 5
+        + count(&entry.text.body)
+}
+
+fn snippet(body: &str, words: &BTreeSet<String>) -> Option<String> {
+    let mut offset = 0;
+    let hit = body.split(|c: char| !c.is_alphanumeric()).find_map(|w| {
+        let start = offset;
+        // split consumed exactly one separator char after this word
+        offset += w.len()
+            + body[offset + w.len()..]
+                .chars()
+                .next()
+                .map_or(0, char::len_utf8);
+        let lower = w.to_lowercase();
+        words
+            .iter()
+            .any(|q| lower.starts_with(q.as_str()))
+            .then_some(start)
+    })?;
+
+    let start = floor_boundary(body, hit.saturating_sub(SNIPPET_BEFORE_BYTES));
+    let end = floor_boundary(body, (hit + SNIPPET_AFTER_BYTES).min(body.len()));
+    let mut snippet = body[start..end]
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if start > 0 {
+        snippet.insert_str(0, "…");
+    }
+    if end < body.len() {
+        snippet.push('…');
+    }
+    Some(snippet)
+}
+
+fn floor_boundary(s: &str, mut index: usize) -> usize {
+    while !s.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+fn truncate(mut s: String, max_bytes: usize) -> String {
+    if s.len() > max_bytes {
+        s.truncate(floor_boundary(&s, max_bytes));
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenise_and_snippet() {
+        let tokens = tokenise("Hello, wörld! a hello");
+        assert_eq!(
+            tokens.into_iter().collect::<Vec<_>>(),
+            vec!["hello".to_string(), "wörld".to_string()]
+        );
+
+        let words = tokenise("wör");
+        let body = "some text about the wörld and other things";
+        assert_eq!(snippet(body, &words).as_deref(), Some(body));
+        assert_eq!(snippet(body, &tokenise("missing")), None);
+    }
+}
@@ -0,0 +1,65 @@
+/// a point in time, in microseconds since the unix epoch.
+/// these come from the author's clock, so they're a hint for sorting and display, not proof of anything.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, PartialOrd, Ord, Hash)]
+pub struct Timestamp(u64);
+
+impl Timestamp {
+    pub fn from_micros(micros: u64) -> Self {
+        Self(micros)
+    }
+
+    pub fn now() -> Self {
+        Self(veilid_tools::get_timestamp())
+    }
+
+    pub fn as_micros(&self) -> u64 {
+        self.0
+    }
+
+    /// short human readable age relative to `now`, e.g. "3h ago".
+    /// timestamps from the future (clock skew) read as "just now".
+    pub fn ago(&self, now: Timestamp) -> String {
+        let secs = now.0.saturating_sub(self.0) / 1_000_000;
+        match secs {
+            0..60 => "just now".to_string(),
+            60..3600 => format!("{}m ago", secs / 60),
+            3600..86400 => format!("{}h ago", secs / 3600),
+            _ => format!("{}d ago", secs / 86400),
+        }
+    }
+}
+
+impl std::fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEC: u64 = 1_000_000;
+
+    #[test]
+    fn ago_buckets() {
+        let now = Timestamp::from_micros(10 * 86400 * SEC);
+        let at = |secs_ago: u64| Timestamp::from_micros(now.as_micros() - secs_ago * SEC);
+
+        assert_eq!(at(0).ago(now), "just now");
+        assert_eq!(at(59).ago(now), "just now");
+        assert_eq!(at(60).ago(now), "1m ago");
+        assert_eq!(at(3599).ago(now), "59m ago");
+        assert_eq!(at(3600).ago(now), "1h ago");
+        assert_eq!(at(86399).ago(now), "23h ago");
+        assert_eq!(at(86400).ago(now), "1d ago");
+        assert_eq!(at(9 * 86400).ago(now), "9d ago");
+    }
+
+    #[test]
+    fn ago_from_the_future() {
+        let now = Timestamp::from_micros(SEC);
+        let later = Timestamp::from_micros(100 * SEC);
+        assert_eq!(later.ago(now), "just now");
+    }
+}
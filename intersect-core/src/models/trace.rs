@@ -7,9 +7,10 @@ use crate::{
         DeserialisationError, Deserialise, SerialisableV0, SerialisationError, Serialise,
         impl_string_conversions, impl_v0_proto_conversions,
     },
+    veilid::Crypto,
 };
 
-#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
 pub enum DocumentType {
     Account,
     Fragment,
@@ -50,8 +51,9 @@ impl Trace {
         record: &RecordKey,
         secret: &SharedSecret,
         password: &str,
+        crypto: &Crypto,
     ) -> Result<Self, EncryptionError> {
-        let access = Access::new_protected(secret, password)?;
+        let access = Access::new_protected(secret, password, crypto)?;
         Ok(Self::new(document_type, record, access))
     }
 
@@ -222,8 +222,24 @@ pub enum DeserialisationError {
 }
 
 // toml formatting helpers for Display impls, not part of the serialisation system proper
+
+// basic string, with everything toml doesn't allow raw escaped
 pub(crate) fn toml_str(value: &str) -> String {
-    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04X}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
 }
 
 pub(crate) fn toml_multiline(value: &str) -> String {
@@ -235,3 +251,17 @@ pub(crate) fn toml_multiline(value: &str) -> String {
         format!("\"\"\"\n{}\"\"\"", escaped)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toml_str_escapes() {
+        assert_eq!(toml_str("plain"), "\"plain\"");
+        assert_eq!(toml_str("say \"hi\"\\"), "\"say \\\"hi\\\"\\\\\"");
+        assert_eq!(toml_str("two\nlines\ttab"), "\"two\\nlines\\ttab\"");
+        assert_eq!(toml_str("bell\u{7}"), "\"bell\\u0007\"");
+        assert_eq!(toml_str("ünïcode"), "\"ünïcode\"");
+    }
+}
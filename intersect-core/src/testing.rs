@@ -0,0 +1,244 @@
+//! in-process private networks for end-to-end tests.
+//! every node listens on loopback only and shares a random network key,
+//! so the nodes only ever talk to each other and no internet connection is needed.
+//!
+//! every node has its own crypto and stores, so several networks can share a process.
+
+use std::{path::PathBuf, time::Duration};
+
+use futures::future::join_all;
+
+use crate::{ConnectionParams, Intersect};
+
+// attaching to a fresh network takes a while, even on loopback
+const ATTACH_TIMEOUT: Duration = Duration::from_secs(120);
+
+pub(crate) struct TestNetwork {
+    nodes: Vec<Intersect>,
+    root: PathBuf,
+}
+
+impl TestNetwork {
+    /// starts `count` nodes bootstrapping off the first one, and waits for all of them to attach
+    pub(crate) async fn start(count: usize) -> Self {
+        let network_key = format!("intersect-test-{:x}", rand::random::<u64>());
+        let root = std::env::temp_dir().join(&network_key);
+        // random ports, so concurrent test runs don't trip over each other
+        let base_port = 20_000 + rand::random::<u16>() % 20_000;
+        let bootstrap = vec![format!("udp://127.0.0.1:{base_port}")];
+
+        let mut nodes = Vec::with_capacity(count);
+        for i in 0..count {
+            let params = ConnectionParams {
+                ephemeral: false,
+                local_search: false,
+                data_dir: root.join(format!("node-{i}")),
+                namespace: "test".into(),
+                bootstrap: bootstrap.clone(),
+                network_key: Some(network_key.clone()),
+                loopback_port: Some(base_port + i as u16),
+                config_overrides: None,
+                retry: Default::default(),
+                cache_only: false,
+                persist_cache: false,
+                prefetch: None,
+            };
+            let node = Intersect::init(params)
+                .await
+                .unwrap_or_else(|e| panic!("node {i} failed to start: {e}"));
+            nodes.push(node);
+        }
+
+        let attached = join_all(nodes.iter().map(|n| n.wait_for_attachment()));
+        tokio::time::timeout(ATTACH_TIMEOUT, attached)
+            .await
+            .expect("test network didn't attach in time");
+
+        Self { nodes, root }
+    }
+
+    pub(crate) fn node(&self, i: usize) -> &Intersect {
+        &self.nodes[i]
+    }
+
+    /// closes every node (flushing their writes) and removes their data
+    pub(crate) async fn shutdown(self) {
+        for node in self.nodes {
+            node.close().await;
+        }
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{
+        documents::{FragmentDocument, IndexDocument, IndexUpdate, LinksDocument},
+        models::{FragmentMime, IndexMetadata, IndexName, Link, Trace},
+    };
+
+    const UPDATE_TIMEOUT: Duration = Duration::from_secs(60);
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore = "starts a local veilid network, run with `cargo test -- --ignored`"]
+    async fn two_nodes() {
+        let network = TestNetwork::start(2).await;
+        let (alice, bob) = (network.node(0), network.node(1));
+
+        // create → share trace → fetch
+        let mime = FragmentMime::new("text/plain".to_string()).unwrap();
+        let fragment = alice
+            .create_fragment(b"hello bob".to_vec(), mime)
+            .await
+            .unwrap();
+        // only the string crosses over, same as it would between two people
+        let shared = fragment.to_unlocked_trace().to_string();
+        let fragment = Trace::from_str(&shared)
+            .unwrap()
+            .into_typed::<FragmentDocument>()
+            .unwrap()
+            .into_unlocked()
+            .unwrap();
+        let view = bob.fetch(&fragment).await.unwrap();
+        assert_eq!(view.data(), b"hello bob");
+
+        // watch → update
+        let index = alice
+            .create_index("notes".to_string(), None, None, IndexMetadata::default())
+            .await
+            .unwrap();
+        let shared = index.to_unlocked_trace().to_string();
+        let index = Trace::from_str(&shared)
+            .unwrap()
+            .into_typed::<IndexDocument>()
+            .unwrap()
+            .into_unlocked()
+            .unwrap();
+        let mut watching = bob.open(&index).await.unwrap();
+
+        let alices = alice.open(&index).await.unwrap();
+        let renamed = IndexName::new("renamed".to_string()).unwrap();
+        alice
+            .update(&alices, IndexUpdate::Name(renamed))
+            .await
+            .unwrap();
+
+        let updated = watching
+            .updates
+            .wait_for(|view| view.as_ref().is_ok_and(|v| v.name().as_ref() == "renamed"));
+        tokio::time::timeout(UPDATE_TIMEOUT, updated)
+            .await
+            .expect("update didn't reach the other node in time")
+            .unwrap();
+
+        drop((watching, alices));
+        network.shutdown().await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore = "starts a local veilid network, run with `cargo test -- --ignored`"]
+    async fn restart_alongside() {
+        let first = TestNetwork::start(1).await;
+        let second = TestNetwork::start(1).await;
+        first.shutdown().await;
+
+        // the second network keeps working with the first one gone, and a third can start after it
+        let mime = FragmentMime::new("text/plain".to_string()).unwrap();
+        second
+            .node(0)
+            .create_fragment(b"still here".to_vec(), mime.clone())
+            .await
+            .unwrap();
+        let third = TestNetwork::start(1).await;
+        third
+            .node(0)
+            .create_fragment(b"me too".to_vec(), mime)
+            .await
+            .unwrap();
+
+        second.shutdown().await;
+        third.shutdown().await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore = "starts a local veilid network, run with `cargo test -- --ignored`"]
+    async fn export_import() {
+        let network = TestNetwork::start(2).await;
+        let (alice, bob) = (network.node(0), network.node(1));
+
+        // links -> index -> (fragment, the same links), so there's a cycle to rewrite
+        let mime = FragmentMime::new("text/plain".to_string()).unwrap();
+        let fragment = alice
+            .create_fragment(b"archived".to_vec(), mime)
+            .await
+            .unwrap();
+        let index = alice
+            .create_index(
+                "notes".to_string(),
+                Some(fragment.to_unlocked_trace()),
+                None,
+                IndexMetadata::default(),
+            )
+            .await
+            .unwrap();
+        let links = alice
+            .create_links(vec![Link::new(index.to_unlocked_trace(), None)])
+            .await
+            .unwrap();
+        let doc = alice.open(&index).await.unwrap();
+        alice
+            .update(&doc, IndexUpdate::Links(Some(links.to_unlocked_trace())))
+            .await
+            .unwrap();
+        drop(doc);
+
+        let root = links.to_unlocked_trace();
+        let (archive, failures) = alice.export(&root, 3).await.unwrap();
+        assert!(failures.is_empty());
+        assert_eq!(archive.entries().len(), 3);
+
+        let imported = bob.import(&archive).await.unwrap();
+        assert_ne!(imported.record(), root.record());
+
+        let links = imported
+            .into_typed::<LinksDocument>()
+            .unwrap()
+            .into_unlocked()
+            .unwrap();
+        let view = bob.fetch(&links).await.unwrap();
+        let [link] = view.links() else {
+            panic!("expected one link, got {}", view.links().len());
+        };
+        assert_ne!(link.trace().record(), index.reference().record());
+
+        let index = link
+            .trace()
+            .clone()
+            .into_typed::<IndexDocument>()
+            .unwrap()
+            .into_unlocked()
+            .unwrap();
+        let view = bob.fetch(&index).await.unwrap();
+        assert_eq!(view.name().as_ref(), "notes");
+        // the cycle points back at the new copy, not alice's original
+        assert_eq!(
+            view.links().map(|t| t.record()),
+            Some(links.reference().record())
+        );
+
+        let fragment_trace = view.fragment().unwrap();
+        assert_ne!(fragment_trace.record(), fragment.reference().record());
+        let fragment = fragment_trace
+            .clone()
+            .into_typed::<FragmentDocument>()
+            .unwrap()
+            .into_unlocked()
+            .unwrap();
+        assert_eq!(bob.fetch(&fragment).await.unwrap().data(), b"archived");
+
+        network.shutdown().await;
+    }
+}
@@ -1,4 +1,7 @@
-use std::sync::{Arc, Mutex, OnceLock};
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
 use thiserror::Error;
 
 use tokio::sync::watch;
@@ -8,41 +11,86 @@ use veilid_core::{
 };
 
 use crate::veilid::{
-    HandlerChain, NetworkWatcher, UpdateDispatch, UpdateHandler, UpdateLogger, is_attached,
+    HandlerChain, NetworkWatcher, RetryPolicy, UpdateDispatch, UpdateHandler, UpdateLogger,
+    is_attached,
 };
 
 pub const CRYPTO_KIND: CryptoKind = veilid_core::CRYPTO_KIND_VLD0;
 
 #[derive(Debug, Clone)]
 pub struct ConnectionParams {
+    /// use a throwaway namespace instead of `namespace`
     pub ephemeral: bool,
+    /// keep a local full-text index of fetched and opened documents.
+    /// off by default, the index holds their plaintext (and traces that unlock them) on disk
+    pub local_search: bool,
+    /// where veilid keeps its stores. relative paths are relative to the working directory
+    pub data_dir: PathBuf,
+    /// every store is scoped to the namespace,
+    /// so two nodes can share a data dir as long as their namespaces differ
+    pub namespace: String,
+    /// bootstrap nodes to use instead of veilid's defaults
+    pub bootstrap: Vec<String>,
+    /// nodes only talk to peers with the same network key. None means the public network
+    pub network_key: Option<String>,
+    /// only listen on 127.0.0.1 at this port, and don't try to reach the outside world.
+    /// for private test networks, along with `network_key` and `bootstrap`
+    pub loopback_port: Option<u16>,
+    /// partial veilid config as json, merged over everything above,
+    /// e.g. `{"network": {"upnp": false}}`
+    pub config_overrides: Option<String>,
+    /// how record operations are retried when the network doesn't come through
+    pub retry: RetryPolicy,
+    /// only read documents from the local view cache, never from the network.
+    /// can be switched at runtime with `Intersect::set_cache_only`
+    pub cache_only: bool,
+    /// keep the view cache on disk, so documents can be read offline after a restart.
+    /// off by default, the cache holds documents decrypted
+    pub persist_cache: bool,
+    /// when an index is opened, read its fragment and the first this many of its link targets
+    /// in the background, so they're already local by the time they're opened. None turns it off.
+    /// accounts are left alone, they're never cached
+    pub prefetch: Option<usize>,
 }
 
-#[allow(clippy::derivable_impls)]
 impl Default for ConnectionParams {
     fn default() -> Self {
-        Self { ephemeral: false }
+        Self {
+            ephemeral: false,
+            local_search: false,
+            data_dir: PathBuf::from("./.intersect"),
+            namespace: "intersect".into(),
+            bootstrap: Vec::new(),
+            network_key: None,
+            loopback_port: None,
+            config_overrides: None,
+            retry: RetryPolicy::default(),
+            cache_only: false,
+            persist_cache: false,
+            prefetch: Some(8),
+        }
     }
 }
 
-// any network ops will go though Connection, but we do want this static global
-// just so we can build the with_crypto helper below and avoid passing the crypto system around everywhere
-static VEILID: OnceLock<VeilidAPI> = OnceLock::new();
-
-/// executes a closure with access to the crypto system.
-///
-/// trade-off:
-/// `VEILID` is a process-wide singleton set on first `Connection::init`. this avoids threading
-/// a `VeilidAPI` handle through every crypto call site, but it means calling `with_crypto`
-/// after `Connection::close` (which calls `veilid.shutdown()`) will panic.
-pub(crate) fn with_crypto<T, F>(f: F) -> T
-where
-    F: Fn(CryptoSystemGuard<'_>) -> T,
-{
-    let veilid = VEILID.get().expect("Veilid API not initialized");
-    let crypto_component = veilid.crypto().unwrap(); // don't worry kitten, it's fine.
-    let crypto_system = crypto_component.get(CRYPTO_KIND).unwrap(); // the unwraps can't hurt you.
-    f(crypto_system)
+/// a node's crypto system. every `Connection` has its own, so nodes never borrow each other's.
+/// cheap to clone.
+#[derive(Clone)]
+pub(crate) struct Crypto {
+    veilid: VeilidAPI,
+}
+
+impl Crypto {
+    /// executes a closure with access to the crypto system.
+    /// panics if the node has been shut down. `Intersect::close` takes the node by value,
+    /// so only a clone kept around past it could get that far
+    pub(crate) fn with<T, F>(&self, f: F) -> T
+    where
+        F: FnOnce(CryptoSystemGuard<'_>) -> T,
+    {
+        let crypto_component = self.veilid.crypto().expect("node has been shut down");
+        let crypto_system = crypto_component.get(CRYPTO_KIND).unwrap(); // the unwrap can't hurt you.
+        f(crypto_system)
+    }
 }
 
 // most of this is shamelessly stolen from https://codeberg.org/cmars/veilnet/src/branch/main/src/connection/veilid/connection.rs
@@ -51,8 +99,11 @@ where
 #[derive(Clone)] // cloneable cause all fields are Arc<Mutex<>> (VeilidAPI is internally Arc<Mutex<>>)
 pub struct Connection {
     veilid: VeilidAPI,
+    crypto: Crypto,
     update_handlers: Arc<Mutex<HandlerChain>>,
     network_watcher: Arc<NetworkWatcher>,
+    // on a private network, see `is_attached`
+    private: bool,
 }
 
 impl Connection {
@@ -69,17 +120,22 @@ impl Connection {
         });
 
         let network_watcher = Arc::new(NetworkWatcher::new());
+        let private = params.network_key.is_some() || params.loopback_port.is_some();
 
         // initialise the api
-        let veilid = veilid_core::api_startup(update_callback, Self::config(params))
+        let veilid = veilid_core::api_startup(update_callback, Self::config(params)?)
             .await
             .map_err(|e| ConnectionError::StartupFailed(e.to_string()))?;
-        VEILID.get_or_init(|| veilid.clone());
+        let crypto = Crypto {
+            veilid: veilid.clone(),
+        };
 
         let connection = Self {
             veilid,
+            crypto,
             update_handlers,
             network_watcher,
+            private,
         };
 
         // add default handlers
@@ -98,15 +154,14 @@ impl Connection {
             .map_err(|e| ConnectionError::StartupFailed(e.to_string()))
     }
 
-    fn config(params: ConnectionParams) -> VeilidConfig {
-        use std::path::Path;
+    fn config(params: ConnectionParams) -> Result<VeilidConfig, ConnectionError> {
         let namespace = if params.ephemeral {
-            format!("intersect-{:x}", rand::random::<u64>())
+            format!("{}-{:x}", params.namespace, rand::random::<u64>())
         } else {
-            "intersect".into()
+            params.namespace
         };
-        let root_path = Path::new("./.intersect");
-        VeilidConfig {
+        let root_path = params.data_dir.as_path();
+        let mut config = VeilidConfig {
             program_name: "intersect".into(),
             namespace,
             protected_store: veilid_core::VeilidConfigProtectedStore {
@@ -125,7 +180,20 @@ impl Connection {
                 ..Default::default()
             },
             ..Default::default()
+        };
+        if !params.bootstrap.is_empty() {
+            config.network.routing_table.bootstrap = params.bootstrap;
         }
+        if params.network_key.is_some() {
+            config.network.network_key_password = params.network_key;
+        }
+
+        // loopback first, so explicit overrides still get the last word
+        let loopback = params.loopback_port.map(loopback_overrides);
+        for overrides in loopback.iter().chain(&params.config_overrides) {
+            config = apply_overrides(config, overrides)?;
+        }
+        Ok(config)
     }
 
     /// Closes the connection and cleans up resources.
@@ -133,6 +201,10 @@ impl Connection {
         self.veilid.shutdown().await;
     }
 
+    pub(crate) fn crypto(&self) -> &Crypto {
+        &self.crypto
+    }
+
     pub(crate) fn add_update_handler(&self, handler: Box<dyn UpdateHandler + Send + Sync>) {
         self.update_handlers.lock().unwrap().add(handler);
     }
@@ -151,11 +223,21 @@ impl Connection {
     pub(crate) async fn wait_for_attachment(&self) {
         self.network_watcher
             .subscribe_attachment()
-            .wait_for(is_attached)
+            .wait_for(|a| is_attached(a, self.private))
             .await
             .unwrap();
     }
 
+    /// whether the node is on a private network (a network key, or loopback only)
+    pub(crate) fn is_private(&self) -> bool {
+        self.private
+    }
+
+    /// whether the node can do network operations right now, see `is_attached`
+    pub(crate) fn is_attached(&self) -> bool {
+        is_attached(&self.attachment_state().borrow(), self.private)
+    }
+
     /// Gets the underlying Veilid routing context.
     /// Returns an error if the connection has already been shut down or hasn't started yet.
     pub(crate) fn routing_context(&self) -> Result<veilid_core::RoutingContext, ConnectionError> {
@@ -164,19 +246,71 @@ impl Connection {
             .map_err(|_| ConnectionError::NoRoutingContext)
     }
 
+    /// gets the veilid table store for this node's namespace, used for local-only persistence.
+    pub(crate) fn table_store(&self) -> Result<veilid_core::TableStore, ConnectionError> {
+        self.veilid
+            .table_store()
+            .map_err(|_| ConnectionError::NoTableStore)
+    }
+
     pub(crate) fn generate_member_id(&self, key: &PublicKey) -> veilid_core::MemberId {
         self.veilid.generate_member_id(key).unwrap()
     }
 }
 
+// udp and tcp on loopback only. no upnp, websockets or address change detection,
+// there's nothing out there to find anyway
+fn loopback_overrides(port: u16) -> String {
+    let address = format!("127.0.0.1:{port}");
+    format!(
+        r#"{{"network": {{
+            "upnp": false,
+            "detect_address_changes": false,
+            "protocol": {{
+                "udp": {{"enabled": true, "listen_address": "{address}"}},
+                "tcp": {{"connect": true, "listen": true, "listen_address": "{address}"}},
+                "ws": {{"connect": false, "listen": false}},
+                "wss": {{"connect": false, "listen": false}}
+            }}
+        }}}}"#
+    )
+}
+
+// round-trips the config through json so overrides can reach any field, without having to mirror them all
+fn apply_overrides(config: VeilidConfig, overrides: &str) -> Result<VeilidConfig, ConnectionError> {
+    let invalid = |e: serde_json::Error| ConnectionError::InvalidConfig(e.to_string());
+    let mut value = serde_json::to_value(&config).map_err(invalid)?;
+    let overrides: serde_json::Value = serde_json::from_str(overrides).map_err(invalid)?;
+    merge_json(&mut value, overrides);
+    serde_json::from_value(value).map_err(invalid)
+}
+
+// objects are merged key by key, anything else replaces what was there
+fn merge_json(base: &mut serde_json::Value, patch: serde_json::Value) {
+    match (base, patch) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(patch)) => {
+            for (key, value) in patch {
+                merge_json(base.entry(key).or_insert(serde_json::Value::Null), value);
+            }
+        }
+        (base, patch) => *base = patch,
+    }
+}
+
 #[derive(Error, Debug, Clone)]
 #[non_exhaustive]
 pub enum ConnectionError {
     #[error("veilid startup failed: {0}")]
     StartupFailed(String),
 
+    #[error("invalid veilid config: {0}")]
+    InvalidConfig(String),
+
     #[error("no routing context")]
     NoRoutingContext,
+
+    #[error("no table store")]
+    NoTableStore,
 }
 
 #[cfg(target_arch = "wasm32")]
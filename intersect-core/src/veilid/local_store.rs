@@ -0,0 +1,111 @@
+use thiserror::Error;
+use veilid_core::TableDB;
+
+use crate::{
+    serialisation::{DeserialisationError, Deserialise, SerialisationError, Serialise},
+    veilid::{Connection, ConnectionError},
+};
+
+// every store only uses a single column. separate concerns get separate tables instead
+const COLUMN: u32 = 0;
+
+/// typed key-value storage for data that only lives on this device (contacts, indexes, etc).
+/// backed by a veilid table db, so it's scoped to the current namespace
+/// and works the same on native and wasm.
+#[derive(Clone)]
+pub(crate) struct LocalStore {
+    db: TableDB,
+}
+
+impl LocalStore {
+    pub(crate) async fn open(connection: &Connection, name: &str) -> Result<Self, StoreError> {
+        let db = connection
+            .table_store()?
+            .open(name, 1)
+            .await
+            .map_err(|e| StoreError::OpenError(e.to_string()))?;
+        Ok(Self { db })
+    }
+
+    pub(crate) async fn load<T: Deserialise>(&self, key: &[u8]) -> Result<Option<T>, StoreError> {
+        let bytes = self
+            .db
+            .load(COLUMN, key)
+            .await
+            .map_err(|e| StoreError::ReadError(e.to_string()))?;
+        Ok(bytes.map(|b| T::deserialise(&b)).transpose()?)
+    }
+
+    pub(crate) async fn store<T: Serialise>(
+        &self,
+        key: &[u8],
+        value: &T,
+    ) -> Result<(), StoreError> {
+        let bytes = value.serialise()?;
+        self.db
+            .store(COLUMN, key, &bytes)
+            .await
+            .map_err(|e| StoreError::WriteError(e.to_string()))
+    }
+
+    /// stores a key with no value, for stores that are only used as a set (see `keys`)
+    pub(crate) async fn insert(&self, key: &[u8]) -> Result<(), StoreError> {
+        self.db
+            .store(COLUMN, key, &[])
+            .await
+            .map_err(|e| StoreError::WriteError(e.to_string()))
+    }
+
+    /// returns true if there was a value to delete
+    pub(crate) async fn delete(&self, key: &[u8]) -> Result<bool, StoreError> {
+        let old = self
+            .db
+            .delete(COLUMN, key)
+            .await
+            .map_err(|e| StoreError::WriteError(e.to_string()))?;
+        Ok(old.is_some())
+    }
+
+    pub(crate) async fn keys(&self) -> Result<Vec<Vec<u8>>, StoreError> {
+        self.db
+            .get_keys(COLUMN)
+            .await
+            .map_err(|e| StoreError::ReadError(e.to_string()))
+    }
+
+    /// loads every entry in the store, skipping (and logging) any that fail to deserialise
+    /// so one corrupt entry doesn't take the whole store down with it.
+    pub(crate) async fn load_all<T: Deserialise>(&self) -> Result<Vec<(Vec<u8>, T)>, StoreError> {
+        let mut entries = Vec::new();
+        for key in self.keys().await? {
+            match self.load::<T>(&key).await {
+                Ok(Some(value)) => entries.push((key, value)),
+                Ok(None) => {}
+                Err(e) => crate::log!("skipping unreadable local store entry: {e}"),
+            }
+        }
+        Ok(entries)
+    }
+}
+
+#[derive(Error, Debug, Clone)]
+#[non_exhaustive]
+pub enum StoreError {
+    #[error("failed to open local store: {0}")]
+    OpenError(String),
+
+    #[error("failed to read from local store: {0}")]
+    ReadError(String),
+
+    #[error("failed to write to local store: {0}")]
+    WriteError(String),
+
+    #[error("serialisation error: {0}")]
+    SerialisationError(#[from] SerialisationError),
+
+    #[error("deserialisation error: {0}")]
+    DeserialisationError(#[from] DeserialisationError),
+
+    #[error("{0}")]
+    ConnectionError(#[from] ConnectionError),
+}
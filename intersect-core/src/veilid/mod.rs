@@ -4,10 +4,18 @@ mod network_watcher;
 pub use network_watcher::*;
 mod network_state;
 pub use network_state::*;
+mod retry;
+pub use retry::RetryPolicy;
 
 mod updates;
 pub(crate) use updates::*;
 mod record_pool;
+pub use record_pool::RecordError;
 pub(crate) use record_pool::*;
+mod sync_tracker;
+pub(crate) use sync_tracker::SyncTracker;
+mod local_store;
+pub(crate) use local_store::LocalStore;
+pub use local_store::StoreError;
 mod watch_router;
-pub(crate) use watch_router::{WatchCoordinators, WatchRouter};
+pub(crate) use watch_router::{WatchCoordinators, WatchGuard, WatchRouter};
@@ -22,12 +22,55 @@ pub struct PendingSync {
     pub subkeys: usize,
 }
 
+/// how many DHT records the pool is keeping open.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct RecordStats {
+    pub open: usize,
+    /// open records something is currently holding on to
+    pub in_use: usize,
+    /// records with an active watch, these stay open until it's cancelled
+    pub watched: usize,
+    /// records closed for being idle, since startup
+    pub closed: u64,
+}
+
+/// how an open document finds out about changes
+#[derive(Clone, Debug, PartialEq, Default)]
+pub enum WatchMode {
+    /// the network tells us about every change as it happens
+    #[default]
+    Watching,
+    /// the network wouldn't watch the record, so it's re-read every so often instead.
+    /// a watch is tried again now and then
+    Polling,
+}
+
+impl WatchMode {
+    pub fn is_watching(&self) -> bool {
+        matches!(self, Self::Watching)
+    }
+}
+
+/// whether a record's local writes have made it to the network
+#[derive(Clone, Debug, PartialEq, Default)]
+pub enum SyncState {
+    /// written locally while detached, will be pushed once the network is back
+    LocalOnly,
+    /// attached, and local writes are still being pushed
+    Syncing,
+    /// nothing left to push
+    #[default]
+    Synced,
+    /// the last write didn't go through at all. cleared by the next successful write
+    Failed(String),
+}
+
 /// combined network status: veilid attachment/bandwidth state plus
 /// the number of record subkeys not yet flushed to the DHT.
 #[derive(Clone, Debug, PartialEq)]
 pub struct NetworkState {
     pub strength: ConnectionStrength,
-    /// true when public_internet_ready and strength is at least Weak.
+    /// true when public_internet_ready (or local_network_ready, on private networks) and strength is at least Weak.
     /// this is the condition you want for "can i do network ops?".
     pub attached: bool,
     pub bps_down: u64,
@@ -36,9 +79,11 @@ pub struct NetworkState {
     pub pending_sync: PendingSync,
 }
 
-/// checks whether a veilid attachment state represents a usable network connection..
-pub fn is_attached(attachment: &VeilidStateAttachment) -> bool {
-    attachment.public_internet_ready
+/// checks whether a veilid attachment state represents a usable network connection.
+/// `private` networks (with a network key or on loopback) never reach the public internet,
+/// so local readiness counts there too. anywhere else a lan peer alone doesn't get us to the dht
+pub fn is_attached(attachment: &VeilidStateAttachment, private: bool) -> bool {
+    (attachment.public_internet_ready || (private && attachment.local_network_ready))
         && matches!(
             attachment.state,
             AttachmentState::AttachedWeak
@@ -64,7 +109,9 @@ fn connection_strength(state: &AttachmentState) -> ConnectionStrength {
 
 /// merges veilid attachment/network events and pool sync count into a single channel.
 /// the returned receiver reflects the latest combined state and updates on any change.
+/// `private` is passed on to `is_attached`.
 pub fn watch_network_state(
+    private: bool,
     mut attachment_rx: watch::Receiver<VeilidStateAttachment>,
     mut network_rx: watch::Receiver<VeilidStateNetwork>,
     mut pending_sync_rx: watch::Receiver<PendingSync>,
@@ -75,7 +122,7 @@ pub fn watch_network_state(
         let network = network_rx.borrow();
         NetworkState {
             strength: connection_strength(&attachment.state),
-            attached: is_attached(&attachment),
+            attached: is_attached(&attachment, private),
             bps_down: network.bps_down.as_u64(),
             bps_up: network.bps_up.as_u64(),
             peer_count: network.peers.len(),
@@ -94,7 +141,7 @@ pub fn watch_network_state(
             let network = network_rx.borrow_and_update();
             let new_state = NetworkState {
                 strength: connection_strength(&attachment.state),
-                attached: is_attached(&attachment),
+                attached: is_attached(&attachment, private),
                 bps_down: network.bps_down.as_u64(),
                 bps_up: network.bps_up.as_u64(),
                 peer_count: network.peers.len(),
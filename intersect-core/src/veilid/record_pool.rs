@@ -1,29 +1,58 @@
-use std::sync::Arc;
-use std::{collections::HashMap, sync::Mutex};
+use std::sync::{Arc, Weak};
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+    sync::Mutex,
+};
 
+use futures::{FutureExt, future::try_join_all, select};
 use thiserror::Error;
-use tokio::sync::watch;
+use tokio::sync::{RwLock, broadcast, watch};
 use veilid_core::{
     DHTRecordDescriptor, DHTReportScope, DHTSchema, DHTSchemaSMPLMember, KeyPair, RecordKey,
-    SetDHTValueOptions, VeilidAPIError,
+    SetDHTValueOptions, ValueData, VeilidAPIError,
 };
-use veilid_tools::{sleep::sleep, spawn::spawn_detached};
+use veilid_tools::{get_timestamp, sleep::sleep, spawn::spawn_detached};
 
 use crate::{
     api::Reference,
     debug,
     models::Encrypted,
     serialisation::{DeserialisationError, Deserialise, SerialisationError, Serialise},
-    veilid::{CRYPTO_KIND, Connection, ConnectionError, PendingSync, with_crypto},
+    veilid::{
+        CRYPTO_KIND, Connection, ConnectionError, Crypto, LocalStore, PendingSync, RecordStats,
+        RetryPolicy, StoreError, SyncState, SyncTracker,
+    },
 };
 
 const PENDING_SYNC_POLL_INTERVAL_MS: u32 = 250;
+// closing only waits this long for local writes to get out, the rest is pushed next session
+const PENDING_SYNC_TIMEOUT_MS: u32 = 30_000;
+const DELETED_TABLE: &str = "intersect-deleted";
+// records nobody has touched for this long get closed
+const RECORD_IDLE_TIMEOUT_MS: u64 = 120_000;
+// past this many open records, the least recently used unpinned ones are closed early
+const MAX_OPEN_RECORDS: usize = 256;
+const SWEEP_INTERVAL_MS: u32 = 30_000;
+// written over every subkey of a deleted document. not a valid serialisation of anything,
+// so it can't be mistaken for (or forged from) real content
+const TOMBSTONE: &[u8; 4] = b"ISTB";
+// veilid won't store anything bigger in a single subkey
+const MAX_SUBKEY_BYTES: usize = 32 * 1024;
+
+// record, subkey and whether the read is forced
+type ReadKey = (RecordKey, u32, bool);
+// None until the read is done
+type ReadResult = Option<Result<ValueData, RecordError>>;
+type InFlightReads = Mutex<HashMap<ReadKey, watch::Receiver<ReadResult>>>;
 
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub struct OpenRecord {
     descriptor: DHTRecordDescriptor,
     reference: Reference,
     // updates: flume::Receiver<T::Update>,
+    // every clone shares this, so the pool can tell when its own copy is the only one left
+    handle: Arc<()>,
 }
 
 impl OpenRecord {
@@ -40,61 +69,136 @@ impl OpenRecord {
     }
 }
 
+/// what `RecordPool::refresh` had to do about subkeys the network had lost
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Refreshed {
+    /// pushed again from our copy
+    pub(crate) repushed: usize,
+    /// couldn't be pushed again, see `RecordPool::refresh`
+    pub(crate) lost: usize,
+}
+
+struct PooledRecord {
+    record: OpenRecord,
+    // µs timestamp
+    last_used: u64,
+}
+
+// takes a read out of the in-flight map once it's done, or dropped halfway.
+// unless a write already took it out, and another read of the same subkey took its place
+struct FinishRead<'a> {
+    in_flight: &'a InFlightReads,
+    key: ReadKey,
+    read: watch::Receiver<ReadResult>,
+}
+
+impl Drop for FinishRead<'_> {
+    fn drop(&mut self) {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        if in_flight
+            .get(&self.key)
+            .is_some_and(|r| r.same_channel(&self.read))
+        {
+            in_flight.remove(&self.key);
+        }
+    }
+}
+
 pub struct RecordPool {
     // mutex for interior mutability,
     // otherwise get_or_open would need `&mut self` which would make it unusable in most contexts
-    open_records: Mutex<HashMap<RecordKey, OpenRecord>>,
+    open_records: Mutex<HashMap<RecordKey, PooledRecord>>,
+    // held for reading while opening a record, and for writing while closing idle ones,
+    // so a record can't be reopened halfway through being closed
+    open_lock: RwLock<()>,
+    // records with an active watch, these stay open until the watch is cancelled
+    watched: Mutex<HashSet<RecordKey>>,
+    // tombstoned records, dropped from local storage once the tombstones have reached the network.
+    // persisted in `deleted_store` too, so a restart doesn't forget them
+    deleted: Mutex<HashSet<RecordKey>>,
+    deleted_store: LocalStore,
+    // reads on their way, so concurrent reads of the same subkey share one round trip
+    in_flight: InFlightReads,
+    closed: Mutex<u64>,
     connection: Connection,
-    pending_sync_tx: watch::Sender<PendingSync>,
+    // how open/read/write/watch calls are retried
+    retry: RetryPolicy,
+    // which records still have writes that haven't reached the network
+    sync: Arc<SyncTracker>,
 }
 
 impl RecordPool {
-    pub(crate) fn new(connection: Connection) -> Arc<Self> {
-        let (pending_sync_tx, _) = watch::channel(PendingSync::default());
+    pub(crate) async fn new(
+        connection: Connection,
+        retry: RetryPolicy,
+    ) -> Result<Arc<Self>, StoreError> {
+        let deleted_store = LocalStore::open(&connection, DELETED_TABLE).await?;
+        let deleted = deleted_store
+            .keys()
+            .await?
+            .into_iter()
+            .filter_map(|key| {
+                let key = String::from_utf8(key).ok()?;
+                RecordKey::from_str(&key).ok()
+            })
+            .collect();
         let pool = Arc::new(Self {
             open_records: Mutex::new(HashMap::new()),
+            open_lock: RwLock::new(()),
+            watched: Mutex::new(HashSet::new()),
+            deleted: Mutex::new(deleted),
+            deleted_store,
+            in_flight: Mutex::new(HashMap::new()),
+            closed: Mutex::new(0),
+            sync: SyncTracker::new(connection.clone()),
             connection,
-            pending_sync_tx,
-        });
-        // poll offline subkeys across all open records and broadcast the total.
-        // uses a weak ref so the task exits naturally when the pool is dropped.
-        let weak = Arc::downgrade(&pool);
-        spawn_detached("record_pool_sync_poll", async move {
-            loop {
-                sleep(PENDING_SYNC_POLL_INTERVAL_MS).await;
-                let Some(pool) = weak.upgrade() else { break };
-                let keys: Vec<RecordKey> =
-                    pool.open_records.lock().unwrap().keys().cloned().collect();
-                let mut pending = PendingSync::default();
-                // break if we don't have a routing context so we don't panic
-                // if this loses a race during shutdown.
-                let Ok(ctx) = pool.connection.routing_context() else {
-                    break;
-                };
-                // TODO: we may want to add a dirty flag to open records to avoid unnecessary checks.
-                // they're pretty cheap so probably ok for now, but it'd be much cleaner to avoid inspect calls if possible
-                for key in keys {
-                    if let Ok(report) = ctx
-                        .inspect_dht_record(key, None, DHTReportScope::Local)
-                        .await
-                    {
-                        let offline = report.offline_subkeys().len() as usize;
-                        if offline > 0 {
-                            pending.records += 1;
-                            pending.subkeys += offline;
-                        }
-                    }
-                }
-                pool.pending_sync_tx.send_replace(pending);
-            }
+            retry,
         });
-        pool
+        spawn_detached("record_sweeper", run_sweeper(Arc::downgrade(&pool)));
+        Ok(pool)
+    }
+
+    pub(crate) fn stats(&self) -> RecordStats {
+        let records = self.open_records.lock().unwrap();
+        RecordStats {
+            open: records.len(),
+            in_use: records
+                .values()
+                .filter(|r| Arc::strong_count(&r.record.handle) > 1)
+                .count(),
+            watched: self.watched.lock().unwrap().len(),
+            closed: *self.closed.lock().unwrap(),
+        }
     }
 
-    /// returns a receiver that tracks total offline subkeys across all open records.
-    /// updates approximately every 250ms.
+    /// the crypto of the node this pool belongs to
+    pub(crate) fn crypto(&self) -> &Crypto {
+        self.connection.crypto()
+    }
+
+    /// returns a receiver that tracks total offline subkeys across all records with unsynced writes.
     pub fn pending_sync_watch(&self) -> watch::Receiver<PendingSync> {
-        self.pending_sync_tx.subscribe()
+        self.sync.pending_watch()
+    }
+
+    /// returns a receiver tracking whether a record's local writes have reached the network.
+    pub(crate) fn sync_watch(&self, record: &RecordKey) -> watch::Receiver<SyncState> {
+        self.sync.watch(record)
+    }
+
+    /// records whose local writes have all reached the network, or that were purged (see `SyncTracker::settled`)
+    pub(crate) fn settled_records(&self) -> broadcast::Receiver<RecordKey> {
+        self.sync.settled()
+    }
+
+    /// whether a record still has local writes that might not have reached the network
+    pub(crate) fn is_dirty(&self, record: &RecordKey) -> bool {
+        self.sync.is_dirty(record)
+    }
+
+    /// number of subkeys not yet pushed to the network, for every record that has any
+    pub(crate) fn offline_subkeys(&self) -> HashMap<RecordKey, usize> {
+        self.sync.offline()
     }
 
     pub(crate) async fn get_or_open(
@@ -102,29 +206,33 @@ impl RecordPool {
         reference: &Reference,
     ) -> Result<OpenRecord, RecordError> {
         // fast path: already open
-        if let Some(record) = self.open_records.lock().unwrap().get(reference.record()) {
-            return Ok(record.clone());
+        if let Some(pooled) = self
+            .open_records
+            .lock()
+            .unwrap()
+            .get_mut(reference.record())
+        {
+            pooled.last_used = get_timestamp();
+            return Ok(pooled.record.clone());
         }
 
         // slow path: open the record outside the lock (network call)
+        let _open = self.open_lock.read().await;
         let rc = self.connection.routing_context()?;
-        let descriptor = match rc.open_dht_record(reference.record().clone(), None).await {
-            Ok(desc) => desc,
-            Err(VeilidAPIError::TryAgain { .. }) => {
-                // we're probably not connected yet, wait and try again
-                debug!("record open returned TryAgain, waiting for network and retrying...");
-                self.connection.wait_for_attachment().await;
+        let descriptor = self
+            .retry
+            .run("opening a record", &self.connection, || {
                 rc.open_dht_record(reference.record().clone(), None)
-                    .await
-                    .map_err(|e| RecordError::OpenError(e.to_string()))?
-            }
-            Err(e) => Err(RecordError::OpenError(e.to_string()))?,
-        };
+            })
+            .await?;
 
         let record = OpenRecord {
             reference: reference.clone(),
             descriptor,
+            handle: Arc::new(()),
         };
+        // might still have offline writes from a previous session, have a look once
+        self.sync.mark_dirty(reference.record());
 
         // use entry to avoid clobbering a concurrent insert
         // open_dht_record is idempotent so the duplicate call is harmless.
@@ -134,7 +242,11 @@ impl RecordPool {
             .lock()
             .unwrap()
             .entry(reference.record().clone())
-            .or_insert(record)
+            .or_insert(PooledRecord {
+                record,
+                last_used: get_timestamp(),
+            })
+            .record
             .clone())
     }
 
@@ -156,44 +268,99 @@ impl RecordPool {
             .connection
             .routing_context()?
             .create_dht_record(CRYPTO_KIND, schema, None)
-            .await
-            .map_err(|e| RecordError::CreateError(e.to_string()))?;
+            .await?;
 
         let key = descriptor.key();
-        let secret = with_crypto(|c| c.random_shared_secret());
+        let secret = self.crypto().with(|c| c.random_shared_secret());
         let record = OpenRecord {
             reference: Reference::new(key.clone(), secret),
             descriptor,
+            handle: Arc::new(()),
         };
 
         // grab the lock as late as possible to avoid blocking while doing network operations
-        self.open_records
-            .lock()
-            .unwrap()
-            .insert(key, record.clone());
+        self.open_records.lock().unwrap().insert(
+            key,
+            PooledRecord {
+                record: record.clone(),
+                last_used: get_timestamp(),
+            },
+        );
 
         Ok(record)
     }
 
-    pub(crate) async fn read_raw(
+    // joins a read of the same subkey that's already on its way, if there is one
+    async fn get_value(
         &self,
         reference: &Reference,
         subkey: u32,
         force: bool,
-    ) -> Result<Vec<u8>, RecordError> {
+    ) -> Result<ValueData, RecordError> {
+        let key = (reference.record().clone(), subkey, force);
+        let (tx, rx) = watch::channel(None);
+        let joined = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            match in_flight.get(&key) {
+                Some(read) => Some(read.clone()),
+                None => {
+                    in_flight.insert(key.clone(), rx.clone());
+                    None
+                }
+            }
+        };
+        if let Some(mut read) = joined {
+            // the sender is gone if that read was dropped halfway, so do our own instead
+            let shared = read.wait_for(Option::is_some).await.map(|r| r.clone());
+            return match shared {
+                Ok(Some(result)) => result,
+                _ => self.request_value(reference, subkey, force).await,
+            };
+        }
+
+        let _done = FinishRead {
+            in_flight: &self.in_flight,
+            key,
+            read: rx,
+        };
+        let result = self.request_value(reference, subkey, force).await;
+        tx.send_replace(Some(result.clone()));
+        result
+    }
+
+    async fn request_value(
+        &self,
+        reference: &Reference,
+        subkey: u32,
+        force: bool,
+    ) -> Result<ValueData, RecordError> {
         let record = self.get_or_open(reference).await?;
+        let rc = self.connection.routing_context()?;
         let data = self
-            .connection
-            .routing_context()?
-            .get_dht_value(record.descriptor.key(), subkey, force)
-            .await
-            .map_err(|e| RecordError::ReadError(e.to_string()))?
+            .retry
+            .run("reading a record", &self.connection, || {
+                rc.get_dht_value(record.descriptor.key(), subkey, force)
+            })
+            .await?
             .ok_or(RecordError::SubkeyEmpty(subkey))?;
+        if data.data() == TOMBSTONE {
+            return Err(RecordError::Deleted);
+        }
         debug!(
             "read from record with key {} and subkey {}",
             record.descriptor.key(),
             subkey
         );
+        Ok(data)
+    }
+
+    pub(crate) async fn read_raw(
+        &self,
+        reference: &Reference,
+        subkey: u32,
+        force: bool,
+    ) -> Result<Vec<u8>, RecordError> {
+        let data = self.get_value(reference, subkey, force).await?;
         Ok(data.data().to_vec())
     }
 
@@ -210,6 +377,19 @@ impl RecordPool {
         Ok(encrypted)
     }
 
+    /// same as `read`, but also returns the subkey's sequence number.
+    /// the sequence number goes up by one on every write, so it doubles as a revision counter.
+    pub(crate) async fn read_versioned(
+        &self,
+        reference: &Reference,
+        subkey: u32,
+        force: bool,
+    ) -> Result<(Encrypted, u32), RecordError> {
+        let data = self.get_value(reference, subkey, force).await?;
+        let encrypted = Encrypted::deserialise(data.data())?;
+        Ok((encrypted, data.seq()))
+    }
+
     pub(crate) async fn write_raw(
         &self,
         reference: &Reference,
@@ -217,44 +397,290 @@ impl RecordPool {
         value: &[u8],
         writer: &KeyPair,
     ) -> Result<(), RecordError> {
+        // hold on to the record until it's marked dirty, so it can't be closed in between
         let record = self.get_or_open(reference).await?;
-        self.connection
-            .routing_context()?
-            .set_dht_value(
-                record.descriptor.key(),
-                subkey,
-                value.to_vec(),
-                Some(SetDHTValueOptions {
-                    writer: Some(writer.clone()),
-                    ..Default::default()
-                }),
-            )
-            .await
-            .map_err(|e| RecordError::WriteError(e.to_string()))?;
+        // while detached, veilid keeps the write locally and pushes it once reattached
+        let result = self.set_value(&record, subkey, value, writer).await;
+        self.sync.wrote(reference.record(), &result);
+        // reads that started before the write might still return the old value,
+        // so later ones go out on their own instead of joining them
+        let mut in_flight = self.in_flight.lock().unwrap();
+        for force in [false, true] {
+            in_flight.remove(&(reference.record().clone(), subkey, force));
+        }
+        drop(in_flight);
+        result
+    }
+
+    async fn set_value(
+        &self,
+        record: &OpenRecord,
+        subkey: u32,
+        value: &[u8],
+        writer: &KeyPair,
+    ) -> Result<(), RecordError> {
+        // veilid would refuse both of these too, but only with a generic error
+        if value.len() > MAX_SUBKEY_BYTES {
+            return Err(RecordError::ValueTooLarge {
+                size: value.len(),
+                max: MAX_SUBKEY_BYTES,
+            });
+        }
+        if !self.is_writer(record, writer) {
+            return Err(RecordError::NotWriter);
+        }
+        let rc = self.connection.routing_context()?;
+        // writing the same value again is a no-op, so a try that timed out after all is harmless
+        self.retry
+            .run("writing a record", &self.connection, || {
+                rc.set_dht_value(
+                    record.descriptor.key(),
+                    subkey,
+                    value.to_vec(),
+                    Some(SetDHTValueOptions {
+                        writer: Some(writer.clone()),
+                        ..Default::default()
+                    }),
+                )
+            })
+            .await?;
         debug!("wrote record with key {}", record.descriptor.key());
         Ok(())
     }
 
-    pub(crate) async fn watch(&self, reference: &Reference) -> Result<(), RecordError> {
+    // every record we create is SMPL with one member, anything else is left for veilid to judge
+    fn is_writer(&self, record: &OpenRecord, writer: &KeyPair) -> bool {
+        let member = self.connection.generate_member_id(&writer.key()).value();
+        match record.descriptor.schema() {
+            DHTSchema::SMPL(schema) => schema.members().iter().any(|m| m.m_key == member),
+            _ => true,
+        }
+    }
+
+    /// overwrites subkeys with a tombstone, after which reading them fails with `RecordError::Deleted`
+    pub(crate) async fn tombstone(
+        &self,
+        reference: &Reference,
+        subkeys: impl IntoIterator<Item = u32>,
+        writer: &KeyPair,
+    ) -> Result<(), RecordError> {
+        try_join_all(
+            subkeys
+                .into_iter()
+                .map(|subkey| self.write_raw(reference, subkey, TOMBSTONE, writer)),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// keeps a record alive on the network. every subkey we hold a copy of is fetched again,
+    /// and if we can write to the record, any subkey the network has lost (or only has an older
+    /// version of) is pushed again from our copy.
+    /// veilid skips writes of the value it already has, so our copy is re-encrypted first, which
+    /// only works for encrypted subkeys. raw ones (fragment chunks) and subkeys we can't write
+    /// are counted as lost instead.
+    pub(crate) async fn refresh(
+        &self,
+        reference: &Reference,
+        writer: Option<&KeyPair>,
+    ) -> Result<Refreshed, RecordError> {
         let record = self.get_or_open(reference).await?;
-        self.connection
-            .routing_context()?
-            .watch_dht_values(record.descriptor.key(), None, None, None)
+        let rc = self.connection.routing_context()?;
+        let report = self
+            .retry
+            .run("inspecting a record", &self.connection, || {
+                rc.inspect_dht_record(record.key(), None, DHTReportScope::SyncGet)
+            })
+            .await?;
+
+        let mut refreshed = Refreshed::default();
+        let seqs = report.local_seqs().iter().zip(report.network_seqs());
+        for (subkey, (local, network)) in report.subkeys().iter().zip(seqs) {
+            let Some(local) = local else {
+                continue;
+            };
+            if network.is_some_and(|network| network >= *local) {
+                self.get_value(reference, subkey, true).await?;
+                continue;
+            }
+            let Some(writer) = writer else {
+                refreshed.lost += 1;
+                continue;
+            };
+            let data = self.get_value(reference, subkey, false).await?;
+            let resealed = Encrypted::deserialise(data.data())
+                .ok()
+                .and_then(|e| e.reseal(reference.secret(), self.crypto()).ok());
+            match resealed {
+                Some(resealed) => {
+                    self.write(reference, subkey, &resealed, writer).await?;
+                    refreshed.repushed += 1;
+                }
+                None => refreshed.lost += 1,
+            }
+        }
+        Ok(refreshed)
+    }
+
+    /// removes a record from local storage.
+    /// deferred to `purge_deleted`, since deleting it straight away would also throw away
+    /// any tombstones that haven't reached the network yet
+    pub(crate) async fn delete_local(&self, record: &RecordKey) {
+        // the tombstones are already written, so worst case it's only forgotten on restart
+        if let Err(e) = self
+            .deleted_store
+            .insert(record.to_string().as_bytes())
             .await
-            .map_err(|e| RecordError::WatchError(e.to_string()))?;
+        {
+            crate::log!("failed to remember deleted record {record}: {e}");
+        }
+        self.deleted.lock().unwrap().insert(record.clone());
+    }
+
+    /// drops deleted records from local storage once their tombstones have reached the network,
+    /// including ones deleted in an earlier session or already closed. anything still held
+    /// or watched is left for next time, and so is anything left to push. with `wait`,
+    /// tombstones get up to `PENDING_SYNC_TIMEOUT_MS` to get out first
+    pub(crate) async fn purge_deleted(&self, wait: bool) -> Result<(), RecordError> {
+        let candidates: Vec<RecordKey> = self.deleted.lock().unwrap().iter().cloned().collect();
+        if candidates.is_empty() {
+            return Ok(());
+        }
+
+        let rc = self.connection.routing_context()?;
+        let deadline = wait.then(|| get_timestamp() + PENDING_SYNC_TIMEOUT_MS as u64 * 1000);
+        let mut purged = 0;
+        for key in candidates {
+            // a restart or a sweep may have closed it, and it has to be open to inspect.
+            // if it isn't stored locally at all, there's nothing to lose by deleting it.
+            // opened outside the pool, so it's closed again below unless the pool has it
+            let pooled = self.open_records.lock().unwrap().contains_key(&key);
+            if pooled || rc.open_dht_record(key.clone(), None).await.is_ok() {
+                let synced = loop {
+                    match self.inspect_offline(&key).await {
+                        Ok(0) => break true,
+                        Ok(offline) if deadline.is_some_and(|d| get_timestamp() < d) => {
+                            debug!(
+                                "waiting for {offline} tombstones on deleted record {key} to sync"
+                            );
+                            sleep(PENDING_SYNC_POLL_INTERVAL_MS).await;
+                        }
+                        // veilid pushes them once the record is opened again, next session at the latest
+                        Ok(_) => break false,
+                        Err(e) => {
+                            debug!("failed to inspect deleted record {key}: {e}");
+                            break false;
+                        }
+                    }
+                };
+                if !synced {
+                    let _open = self.open_lock.write().await;
+                    self.close_unpooled(&rc, &key).await;
+                    continue;
+                }
+            }
+
+            // same as sweeping, it can't be reopened halfway through
+            let _open = self.open_lock.write().await;
+            let in_use = self
+                .open_records
+                .lock()
+                .unwrap()
+                .get(&key)
+                .is_some_and(|pooled| Arc::strong_count(&pooled.record.handle) > 1);
+            if in_use || self.watched.lock().unwrap().contains(&key) {
+                self.close_unpooled(&rc, &key).await;
+                continue;
+            }
+            self.open_records.lock().unwrap().remove(&key);
+            // already closed if it couldn't be opened above
+            let _ = rc.close_dht_record(key.clone()).await;
+            if let Err(e) = rc.delete_dht_record(key.clone()).await {
+                debug!("failed to delete record {key}: {e}");
+                continue;
+            }
+            self.deleted.lock().unwrap().remove(&key);
+            self.sync.forget(&key);
+            if let Err(e) = self.deleted_store.delete(key.to_string().as_bytes()).await {
+                debug!("failed to forget deleted record {key}: {e}");
+            }
+            purged += 1;
+        }
+        if purged > 0 {
+            debug!("purged {purged} deleted records");
+        }
         Ok(())
     }
 
-    pub(crate) async fn cancel_watch(&self, reference: &Reference) -> Result<(), RecordError> {
-        let record = self.get_or_open(reference).await?;
-        self.connection
+    // closes a record opened straight through veilid, unless the pool has opened it since.
+    // caller holds the open lock, so that can't change halfway through
+    async fn close_unpooled(&self, rc: &veilid_core::RoutingContext, key: &RecordKey) {
+        if !self.open_records.lock().unwrap().contains_key(key) {
+            let _ = rc.close_dht_record(key.clone()).await;
+        }
+    }
+
+    async fn inspect_offline(&self, record: &RecordKey) -> Result<usize, RecordError> {
+        let report = self
+            .connection
             .routing_context()?
-            .cancel_dht_watch(record.descriptor.key(), None)
-            .await
-            .map_err(|e| RecordError::WatchError(e.to_string()))?;
+            .inspect_dht_record(record.clone(), None, DHTReportScope::Local)
+            .await?;
+        Ok(report.offline_subkeys().len() as usize)
+    }
+
+    /// watches the whole record until `expires` (µs timestamp). watching again renews it
+    pub(crate) async fn watch(
+        &self,
+        reference: &Reference,
+        expires: u64,
+    ) -> Result<(), RecordError> {
+        // pin it first, so it can't be closed between opening and watching
+        self.watched
+            .lock()
+            .unwrap()
+            .insert(reference.record().clone());
+        let result = self.watch_inner(reference, expires).await;
+        if result.is_err() {
+            self.watched.lock().unwrap().remove(reference.record());
+        }
+        result
+    }
+
+    async fn watch_inner(&self, reference: &Reference, expires: u64) -> Result<(), RecordError> {
+        let record = self.get_or_open(reference).await?;
+        let rc = self.connection.routing_context()?;
+        let active = self
+            .retry
+            .run("watching a record", &self.connection, || {
+                rc.watch_dht_values(
+                    record.descriptor.key(),
+                    None,
+                    Some(veilid_core::Timestamp::new(expires)),
+                    None,
+                )
+            })
+            .await?;
+        if !active {
+            return Err(RecordError::WatchRejected);
+        }
         Ok(())
     }
 
+    pub(crate) async fn cancel_watch(&self, reference: &Reference) -> Result<(), RecordError> {
+        let record = self.get_or_open(reference).await?;
+        let rc = self.connection.routing_context()?;
+        let result = self
+            .retry
+            .run("cancelling a watch", &self.connection, || {
+                rc.cancel_dht_watch(record.descriptor.key(), None)
+            })
+            .await;
+        // unpin either way, nobody's listening anymore
+        self.watched.lock().unwrap().remove(reference.record());
+        result.map(|_| ())
+    }
+
     pub(crate) async fn write(
         &self,
         reference: &Reference,
@@ -266,30 +692,79 @@ impl RecordPool {
         self.write_raw(reference, subkey, &serialised, writer).await
     }
 
+    /// closes records that aren't needed anymore: nobody holds a handle to them,
+    /// they aren't watched and have no unsynced writes. those idle for longer than
+    /// `RECORD_IDLE_TIMEOUT_MS` go, and so do the least recently used ones past `MAX_OPEN_RECORDS`.
+    async fn sweep(&self) -> Result<(), RecordError> {
+        let _open = self.open_lock.write().await;
+        let now = get_timestamp();
+        let closing: Vec<RecordKey> = {
+            let mut records = self.open_records.lock().unwrap();
+            let watched = self.watched.lock().unwrap();
+            // deleted records are left to `purge_deleted`
+            let deleted = self.deleted.lock().unwrap();
+            let idle = records
+                .iter()
+                .filter(|(key, pooled)| {
+                    Arc::strong_count(&pooled.record.handle) == 1
+                        && !watched.contains(*key)
+                        && !deleted.contains(*key)
+                        && !self.sync.is_dirty(key)
+                })
+                .map(|(key, pooled)| (key.clone(), pooled.last_used))
+                .collect();
+            let over = records.len().saturating_sub(MAX_OPEN_RECORDS);
+            let closing: Vec<RecordKey> = select_idle(idle, over, now)
+                .into_iter()
+                .map(|(key, _)| key)
+                .collect();
+            for key in &closing {
+                records.remove(key);
+            }
+            closing
+        };
+        if closing.is_empty() {
+            return Ok(());
+        }
+
+        let rc = self.connection.routing_context()?;
+        for key in &closing {
+            // already gone from the pool, the next get_or_open just opens it again
+            if let Err(e) = rc.close_dht_record(key.clone()).await {
+                debug!("failed to close record {key}: {e}");
+            }
+        }
+        *self.closed.lock().unwrap() += closing.len() as u64;
+        debug!("closed {} idle records", closing.len());
+        Ok(())
+    }
+
     /// waits until all offline subkeys across all open records have been flushed to the network.
+    /// gives up after `PENDING_SYNC_TIMEOUT_MS`, e.g. while offline. veilid keeps what's left
+    /// and pushes it once the record is opened again
     pub(crate) async fn wait_for_all_pending(&self) {
-        let mut rx = self.pending_sync_tx.subscribe();
+        let mut rx = self.sync.pending_watch();
         // wait_for checks the current value first, so no race if already synced
-        let _ = rx.wait_for(|p| p.subkeys == 0).await;
+        select! {
+            _ = rx.wait_for(|p| p.subkeys == 0).fuse() => {}
+            _ = sleep(PENDING_SYNC_TIMEOUT_MS).fuse() => {
+                debug!("gave up waiting for local writes to sync");
+            }
+        }
     }
 
     /// waits until all pending subkeys on a record have been flushed to the network.
     pub(crate) async fn wait_for_pending(&self, reference: &Reference) -> Result<(), RecordError> {
         let record = self.get_or_open(reference).await?;
         loop {
-            let report = self
-                .connection
-                .routing_context()?
-                .inspect_dht_record(record.key(), None, DHTReportScope::Local)
-                .await
-                .map_err(|e| RecordError::ReadError(e.to_string()))?;
-            if report.offline_subkeys().is_empty() {
+            let offline = self.inspect_offline(&record.key()).await?;
+            if offline == 0 {
                 return Ok(());
             }
             debug!(
                 "waiting for record with key {} to sync, {} subkeys still offline",
                 record.key(),
-                report.offline_subkeys().len()
+                offline
             );
             sleep(PENDING_SYNC_POLL_INTERVAL_MS).await;
         }
@@ -297,32 +772,79 @@ impl RecordPool {
         // self.connection
         //     .routing_context()?
         //     .flush_dht_record(record.key(), None)
-        //     .await
-        //     .map_err(|e| RecordError::ReadError(e.to_string()))?;
+        //     .await?;
         // Ok(())
     }
 }
 
+// picks records to close out of idle ones (key, µs last used): the `over` least recently used,
+// plus any left untouched for longer than `RECORD_IDLE_TIMEOUT_MS`
+fn select_idle<K>(mut idle: Vec<(K, u64)>, over: usize, now: u64) -> Vec<(K, u64)> {
+    idle.sort_by_key(|(_, last_used)| *last_used);
+    idle.into_iter()
+        .enumerate()
+        .filter(|(i, (_, last_used))| {
+            *i < over || now.saturating_sub(*last_used) / 1000 > RECORD_IDLE_TIMEOUT_MS
+        })
+        .map(|(_, idle)| idle)
+        .collect()
+}
+
+// holds only a weak ref between sweeps, so the task exits once the pool is dropped
+async fn run_sweeper(weak: Weak<RecordPool>) {
+    loop {
+        sleep(SWEEP_INTERVAL_MS).await;
+        let Some(pool) = weak.upgrade() else {
+            break;
+        };
+        // no routing context means we're shutting down
+        if let Err(RecordError::ConnectionError(_)) = pool.sweep().await {
+            break;
+        }
+        if let Err(RecordError::ConnectionError(_)) = pool.purge_deleted(false).await {
+            break;
+        }
+    }
+}
+
 #[derive(Error, Debug, Clone)]
 #[non_exhaustive]
 pub enum RecordError {
-    #[error("failed to open record: {0}")]
-    OpenError(String),
+    #[error("record not found on the network")]
+    NotFound,
+
+    #[error("timed out waiting for the network")]
+    Timeout,
 
-    #[error("failed to create record: {0}")]
-    CreateError(String),
+    #[error("not connected to the network")]
+    Offline,
+
+    /// the network didn't come through for some other reason that might pass
+    #[error("temporarily unavailable: {0}")]
+    Unavailable(String),
+
+    #[error("not allowed to write to this record")]
+    NotWriter,
+
+    #[error("value is {size} bytes, but a subkey holds at most {max}")]
+    ValueTooLarge { size: usize, max: usize },
+
+    #[error("no node accepted the watch")]
+    WatchRejected,
 
     #[error("failed to build schema: {0}")]
     SchemaError(String),
 
-    #[error("failed to read record: {0}")]
-    ReadError(String),
+    /// veilid didn't accept the request itself, e.g. an invalid key
+    #[error("invalid request: {0}")]
+    InvalidRequest(String),
 
-    #[error("failed to write record: {0}")]
-    WriteError(String),
+    /// anything else veilid reports
+    #[error("network error: {0}")]
+    NetworkError(String),
 
-    #[error("failed to watch record: {0}")]
-    WatchError(String),
+    #[error("record has been deleted")]
+    Deleted,
 
     #[error("subkey {0} has no value")]
     SubkeyEmpty(u32),
@@ -336,3 +858,139 @@ pub enum RecordError {
     #[error("{0}")]
     ConnectionError(#[from] ConnectionError),
 }
+
+impl RecordError {
+    /// whether trying again later might work, e.g. to show "retrying..." rather than an error
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::Timeout | Self::Offline | Self::Unavailable(_))
+    }
+}
+
+impl From<VeilidAPIError> for RecordError {
+    fn from(e: VeilidAPIError) -> Self {
+        match e {
+            VeilidAPIError::Timeout => Self::Timeout,
+            VeilidAPIError::NoConnection { .. } => Self::Offline,
+            VeilidAPIError::TryAgain { message } => Self::Unavailable(message),
+            VeilidAPIError::KeyNotFound { .. } => Self::NotFound,
+            // the node is gone (or not up yet), same as not getting a routing context
+            VeilidAPIError::NotInitialized | VeilidAPIError::Shutdown => {
+                Self::ConnectionError(ConnectionError::NoRoutingContext)
+            }
+            VeilidAPIError::InvalidTarget { .. }
+            | VeilidAPIError::InvalidArgument { .. }
+            | VeilidAPIError::MissingArgument { .. }
+            | VeilidAPIError::ParseError { .. } => Self::InvalidRequest(e.to_string()),
+            e => Self::NetworkError(e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: u64 = 1000;
+
+    fn keys(closing: Vec<(&str, u64)>) -> Vec<&str> {
+        closing.into_iter().map(|(key, _)| key).collect()
+    }
+
+    #[test]
+    fn select_idle_timeout() {
+        let now = 1_000_000 * MS;
+        let idle = vec![
+            ("fresh", now - MS),
+            ("stale", now - (RECORD_IDLE_TIMEOUT_MS + 1) * MS),
+            ("borderline", now - RECORD_IDLE_TIMEOUT_MS * MS),
+        ];
+        assert_eq!(keys(select_idle(idle, 0, now)), vec!["stale"]);
+    }
+
+    #[test]
+    fn select_idle_least_recently_used() {
+        let now = 1_000_000 * MS;
+        let idle = vec![("c", now - 3 * MS), ("a", now - 1), ("b", now - 2 * MS)];
+        assert_eq!(keys(select_idle(idle.clone(), 2, now)), vec!["c", "b"]);
+        // asking for more than there are just closes everything
+        assert_eq!(keys(select_idle(idle, 10, now)).len(), 3);
+    }
+
+    #[test]
+    fn select_idle_both() {
+        let now = 1_000_000 * MS;
+        let idle = vec![
+            ("recent", now),
+            ("older", now - MS),
+            ("stale", now - (RECORD_IDLE_TIMEOUT_MS + 1) * MS),
+        ];
+        // the stale one takes the only lru slot, and would have gone anyway
+        assert_eq!(keys(select_idle(idle, 1, now)), vec!["stale"]);
+        assert!(select_idle::<&str>(Vec::new(), 5, now).is_empty());
+    }
+
+    #[test]
+    fn veilid_errors_map_to_record_errors() {
+        assert!(matches!(
+            RecordError::from(VeilidAPIError::timeout()),
+            RecordError::Timeout
+        ));
+        assert!(matches!(
+            RecordError::from(VeilidAPIError::no_connection("down")),
+            RecordError::Offline
+        ));
+        assert!(matches!(
+            RecordError::from(VeilidAPIError::try_again("busy")),
+            RecordError::Unavailable(message) if message == "busy"
+        ));
+        assert!(matches!(
+            RecordError::from(VeilidAPIError::key_not_found("key")),
+            RecordError::NotFound
+        ));
+        for e in [
+            VeilidAPIError::not_initialized(),
+            VeilidAPIError::shutdown(),
+        ] {
+            assert!(matches!(
+                RecordError::from(e),
+                RecordError::ConnectionError(ConnectionError::NoRoutingContext)
+            ));
+        }
+        for e in [
+            VeilidAPIError::invalid_target("target"),
+            VeilidAPIError::invalid_argument("context", "argument", "value"),
+            VeilidAPIError::missing_argument("context", "argument"),
+            VeilidAPIError::parse_error("message", "value"),
+        ] {
+            assert!(matches!(
+                RecordError::from(e),
+                RecordError::InvalidRequest(_)
+            ));
+        }
+        assert!(matches!(
+            RecordError::from(VeilidAPIError::generic("other")),
+            RecordError::NetworkError(_)
+        ));
+    }
+
+    #[test]
+    fn only_passing_failures_are_transient() {
+        let transient = [
+            VeilidAPIError::timeout(),
+            VeilidAPIError::no_connection("down"),
+            VeilidAPIError::try_again("busy"),
+        ];
+        for e in transient {
+            assert!(RecordError::from(e).is_transient());
+        }
+        let permanent = [
+            VeilidAPIError::key_not_found("key"),
+            VeilidAPIError::shutdown(),
+            VeilidAPIError::invalid_argument("context", "argument", "value"),
+            VeilidAPIError::generic("other"),
+        ];
+        for e in permanent {
+            assert!(!RecordError::from(e).is_transient());
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use std::future::Future;
+
+use futures::{FutureExt, select};
+use veilid_core::VeilidAPIError;
+use veilid_tools::sleep::sleep;
+
+use crate::{
+    debug,
+    veilid::{Connection, RecordError, is_attached},
+};
+
+/// how record operations (open, read, write, watch) deal with failures that might pass,
+/// like a peer not answering or the node not being attached yet
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct RetryPolicy {
+    /// tries per operation, including the first. 1 turns retrying off
+    pub attempts: u32,
+    /// wait before the first retry, doubled for every retry after it
+    pub initial_backoff_ms: u32,
+    /// the wait between retries never grows past this
+    pub max_backoff_ms: u32,
+    /// each try is given up on after this long. None leaves it to veilid's own timeouts
+    pub timeout_ms: Option<u32>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 4,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 8_000,
+            timeout_ms: Some(60_000),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// runs `op` until it succeeds, fails for good or runs out of attempts.
+    /// a try that runs into the timeout counts as a transient failure
+    pub(crate) async fn run<T, F, Fut>(
+        &self,
+        what: &str,
+        connection: &Connection,
+        op: F,
+    ) -> Result<T, RecordError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, VeilidAPIError>>,
+    {
+        self.run_with(what, op, |backoff_ms| self.backoff(backoff_ms, connection))
+            .await
+    }
+
+    // `run`, with the wait between tries passed in so it can be tested without a node
+    async fn run_with<T, F, Fut, W, WFut>(
+        &self,
+        what: &str,
+        mut op: F,
+        mut wait: W,
+    ) -> Result<T, RecordError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, VeilidAPIError>>,
+        W: FnMut(u32) -> WFut,
+        WFut: Future<Output = ()>,
+    {
+        let mut backoff_ms = self.initial_backoff_ms;
+        let mut attempt = 1;
+        loop {
+            let result = match self.timeout_ms {
+                Some(timeout_ms) => select! {
+                    result = op().fuse() => result,
+                    _ = sleep(timeout_ms).fuse() => Err(VeilidAPIError::Timeout),
+                },
+                None => op().await,
+            };
+            let e = match result.map_err(RecordError::from) {
+                Err(e) if e.is_transient() && attempt < self.attempts => e,
+                result => return result,
+            };
+            debug!(
+                "{what} failed ({e}), retrying in {backoff_ms}ms (attempt {attempt} of {})",
+                self.attempts
+            );
+            wait(backoff_ms).await;
+            backoff_ms = backoff_ms.saturating_mul(2).min(self.max_backoff_ms);
+            attempt += 1;
+        }
+    }
+
+    async fn backoff(&self, backoff_ms: u32, connection: &Connection) {
+        let mut attachment = connection.attachment_state();
+        let private = connection.is_private();
+        if is_attached(&attachment.borrow(), private) {
+            sleep(backoff_ms).await;
+            return;
+        }
+        // nothing gets through while detached, so hold off until we're back (or it's clearly not happening)
+        let wait_ms = self
+            .timeout_ms
+            .unwrap_or(self.max_backoff_ms)
+            .max(backoff_ms);
+        select! {
+            _ = attachment.wait_for(|a| is_attached(a, private)).fuse() => {},
+            _ = sleep(wait_ms).fuse() => {},
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::{Cell, RefCell},
+        future::{pending, ready},
+    };
+
+    use super::*;
+
+    fn policy(attempts: u32, timeout_ms: Option<u32>) -> RetryPolicy {
+        RetryPolicy {
+            attempts,
+            initial_backoff_ms: 100,
+            max_backoff_ms: 350,
+            timeout_ms,
+        }
+    }
+
+    // runs `policy` against an op failing with `errors` in turn (then succeeding),
+    // returning the result, how many times the op ran and the waits in between
+    fn run(
+        policy: &RetryPolicy,
+        errors: Vec<VeilidAPIError>,
+    ) -> (Result<u32, RecordError>, u32, Vec<u32>) {
+        let tries = Cell::new(0);
+        let waits = RefCell::new(vec![]);
+        let mut errors = errors.into_iter();
+        let result = tokio_test::block_on(policy.run_with(
+            "testing",
+            || {
+                tries.set(tries.get() + 1);
+                ready(errors.next().map_or(Ok(tries.get()), Err))
+            },
+            |ms| {
+                waits.borrow_mut().push(ms);
+                ready(())
+            },
+        ));
+        (result, tries.get(), waits.into_inner())
+    }
+
+    fn timeout() -> VeilidAPIError {
+        VeilidAPIError::Timeout
+    }
+
+    #[test]
+    fn succeeds_after_transient_failures() {
+        let (result, tries, _) = run(&policy(4, None), vec![timeout(), timeout()]);
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(tries, 3);
+    }
+
+    #[test]
+    fn gives_up_after_attempts() {
+        let (result, tries, _) = run(&policy(3, None), vec![timeout(); 5]);
+        assert!(matches!(result, Err(RecordError::Timeout)));
+        assert_eq!(tries, 3);
+
+        // a single attempt never retries
+        let (result, tries, waits) = run(&policy(1, None), vec![timeout()]);
+        assert!(result.is_err());
+        assert_eq!(tries, 1);
+        assert!(waits.is_empty());
+    }
+
+    #[test]
+    fn permanent_failures_arent_retried() {
+        let not_found = VeilidAPIError::key_not_found("key");
+        let (result, tries, waits) = run(&policy(4, None), vec![not_found]);
+        assert!(matches!(result, Err(RecordError::NotFound)));
+        assert_eq!(tries, 1);
+        assert!(waits.is_empty());
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        let (_, _, waits) = run(&policy(5, None), vec![timeout(); 5]);
+        assert_eq!(waits, vec![100, 200, 350, 350]);
+    }
+
+    #[tokio::test]
+    async fn timeout_counts_as_transient() {
+        let tries = Cell::new(0);
+        let result = policy(2, Some(10))
+            .run_with(
+                "testing",
+                || {
+                    tries.set(tries.get() + 1);
+                    pending::<Result<(), VeilidAPIError>>()
+                },
+                |_| ready(()),
+            )
+            .await;
+        assert!(matches!(result, Err(RecordError::Timeout)));
+        assert_eq!(tries.get(), 2);
+    }
+}
@@ -0,0 +1,497 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, Weak},
+};
+
+use futures::{FutureExt, select};
+use tokio::sync::{Notify, broadcast, watch};
+use veilid_core::{DHTReportScope, RecordKey};
+use veilid_tools::{get_timestamp, sleep::sleep, spawn::spawn_detached};
+
+use crate::{
+    debug,
+    veilid::{Connection, ConnectionError, PendingSync, SyncState},
+};
+
+// first re-check comes quickly since most writes are flushed straight away,
+// after that back off so a long offline stretch doesn't keep us busy
+const MIN_BACKOFF_MS: u32 = 250;
+const MAX_BACKOFF_MS: u32 = 8_000;
+// nothing dirty: just make sure the task notices a shutdown eventually
+const IDLE_MS: u32 = 60_000;
+// settled records a slow listener can fall behind by
+const SETTLED_BUFFER: usize = 64;
+
+struct Dirty {
+    // offline subkeys as of the last check
+    offline: usize,
+    backoff_ms: u32,
+    // µs timestamp
+    next_check: u64,
+    // bumped on every write, so a check that raced a write doesn't clear it
+    generation: u64,
+}
+
+// the bookkeeping half of the tracker, kept apart from the network so it can be tested.
+// times are µs timestamps
+#[derive(Default)]
+struct DirtySet(HashMap<RecordKey, Dirty>);
+
+impl DirtySet {
+    fn mark(&mut self, record: &RecordKey, now: u64) {
+        self.0
+            .entry(record.clone())
+            .and_modify(|d| {
+                d.backoff_ms = MIN_BACKOFF_MS;
+                d.next_check = now;
+                d.generation += 1;
+            })
+            .or_insert(Dirty {
+                offline: 0,
+                backoff_ms: MIN_BACKOFF_MS,
+                next_check: now,
+                generation: 0,
+            });
+    }
+
+    // records due for a check, along with their generation as of now
+    fn due(&self, now: u64) -> Vec<(RecordKey, u64)> {
+        self.0
+            .iter()
+            .filter(|(_, d)| d.next_check <= now)
+            .map(|(k, d)| (k.clone(), d.generation))
+            .collect()
+    }
+
+    // applies the outcome of a check started at `generation`. `offline` is None if it failed.
+    // returns whether a write raced the check, or None if the record wasn't dirty anymore
+    fn checked(
+        &mut self,
+        key: &RecordKey,
+        generation: u64,
+        offline: Option<usize>,
+        now: u64,
+    ) -> Option<bool> {
+        let entry = self.0.get_mut(key)?;
+        // a write that landed while we were inspecting needs another look before it's clean
+        let raced = entry.generation != generation;
+        if offline == Some(0) && !raced {
+            self.0.remove(key);
+        } else {
+            if let Some(offline) = offline {
+                entry.offline = offline;
+            }
+            if !raced {
+                entry.backoff_ms = (entry.backoff_ms * 2).min(MAX_BACKOFF_MS);
+                entry.next_check = now + entry.backoff_ms as u64 * 1000;
+            }
+        }
+        Some(raced)
+    }
+
+    // ms until the next record is due, None if nothing is dirty
+    fn next_delay_ms(&self, now: u64) -> Option<u32> {
+        self.0
+            .values()
+            .map(|d| d.next_check.saturating_sub(now) / 1000)
+            .min()
+            .map(|ms| ms.min(MAX_BACKOFF_MS as u64) as u32)
+    }
+
+    // makes everything due now, e.g. after reattaching
+    fn recheck_all(&mut self, now: u64) {
+        for dirty in self.0.values_mut() {
+            dirty.backoff_ms = MIN_BACKOFF_MS;
+            dirty.next_check = now;
+        }
+    }
+
+    fn offline(&self) -> HashMap<RecordKey, usize> {
+        self.0
+            .iter()
+            .filter(|(_, d)| d.offline > 0)
+            .map(|(k, d)| (k.clone(), d.offline))
+            .collect()
+    }
+
+    fn pending(&self) -> PendingSync {
+        self.0
+            .values()
+            .filter(|d| d.offline > 0)
+            .fold(PendingSync::default(), |mut p, d| {
+                p.records += 1;
+                p.subkeys += d.offline;
+                p
+            })
+    }
+}
+
+// `force` is for writes. checks never clear a failure, only a successful write does.
+// returns whether anything changed
+fn update_state(current: &mut SyncState, state: SyncState, force: bool) -> bool {
+    if *current == state || (!force && matches!(current, SyncState::Failed(_))) {
+        return false;
+    }
+    *current = state;
+    true
+}
+
+/// tracks which records have local writes that haven't reached the network yet.
+/// records are only inspected while they're dirty (written to, or just opened),
+/// with backoff, and dropped from the set once nothing is left offline.
+pub(crate) struct SyncTracker {
+    connection: Connection,
+    dirty: Mutex<DirtySet>,
+    states: Mutex<HashMap<RecordKey, watch::Sender<SyncState>>>,
+    pending_tx: watch::Sender<PendingSync>,
+    // records that went clean, or were forgotten
+    settled_tx: broadcast::Sender<RecordKey>,
+    wake: Arc<Notify>,
+}
+
+impl SyncTracker {
+    pub(crate) fn new(connection: Connection) -> Arc<Self> {
+        let (pending_tx, _) = watch::channel(PendingSync::default());
+        let tracker = Arc::new(Self {
+            connection,
+            dirty: Mutex::new(DirtySet::default()),
+            states: Mutex::new(HashMap::new()),
+            pending_tx,
+            settled_tx: broadcast::channel(SETTLED_BUFFER).0,
+            wake: Arc::new(Notify::new()),
+        });
+        spawn_detached("sync_tracker", run(Arc::downgrade(&tracker)));
+        tracker
+    }
+
+    /// schedules a record for an immediate check
+    pub(crate) fn mark_dirty(&self, record: &RecordKey) {
+        self.dirty.lock().unwrap().mark(record, get_timestamp());
+        self.wake.notify_one();
+    }
+
+    /// records the outcome of a write
+    pub(crate) fn wrote<E: std::fmt::Display>(&self, record: &RecordKey, result: &Result<(), E>) {
+        match result {
+            Ok(()) => {
+                // assume it's still on its way until the check says otherwise.
+                // this also clears a previous failure
+                let state = match self.is_attached() {
+                    true => SyncState::Syncing,
+                    false => SyncState::LocalOnly,
+                };
+                self.set_state(record, state, true);
+                self.mark_dirty(record);
+            }
+            Err(e) => self.set_state(record, SyncState::Failed(e.to_string()), true),
+        }
+    }
+
+    pub(crate) fn watch(&self, record: &RecordKey) -> watch::Receiver<SyncState> {
+        let mut states = self.states.lock().unwrap();
+        if !states.contains_key(record) {
+            // let go of records nobody watches anymore and that have nothing left to push,
+            // e.g. ones that were closed before their last check came back clean.
+            // failures stay until the next write, a later watch should still see them
+            let dirty = self.dirty.lock().unwrap();
+            states.retain(|key, tx| {
+                tx.receiver_count() > 0
+                    || dirty.0.contains_key(key)
+                    || matches!(*tx.borrow(), SyncState::Failed(_))
+            });
+        }
+        states
+            .entry(record.clone())
+            .or_insert_with(|| watch::channel(SyncState::default()).0)
+            .subscribe()
+    }
+
+    pub(crate) fn pending_watch(&self) -> watch::Receiver<PendingSync> {
+        self.pending_tx.subscribe()
+    }
+
+    /// every record whose writes have all reached the network from here on,
+    /// along with the ones that were deleted locally (see `forget`)
+    pub(crate) fn settled(&self) -> broadcast::Receiver<RecordKey> {
+        self.settled_tx.subscribe()
+    }
+
+    /// stops tracking a record that's gone from local storage
+    pub(crate) fn forget(&self, record: &RecordKey) {
+        self.dirty.lock().unwrap().0.remove(record);
+        self.states.lock().unwrap().remove(record);
+        let _ = self.settled_tx.send(record.clone());
+        self.publish_pending();
+    }
+
+    /// offline subkey counts for every record that had some as of its last check
+    pub(crate) fn offline(&self) -> HashMap<RecordKey, usize> {
+        self.dirty.lock().unwrap().offline()
+    }
+
+    /// whether the record still has writes that might not have reached the network
+    pub(crate) fn is_dirty(&self, record: &RecordKey) -> bool {
+        self.dirty.lock().unwrap().0.contains_key(record)
+    }
+
+    fn is_attached(&self) -> bool {
+        self.connection.is_attached()
+    }
+
+    // see `update_state` for what `force` does
+    fn set_state(&self, record: &RecordKey, state: SyncState, force: bool) {
+        let mut states = self.states.lock().unwrap();
+        // nobody's watching, no need to keep track
+        if !force && !states.contains_key(record) {
+            return;
+        }
+        let tx = states
+            .entry(record.clone())
+            .or_insert_with(|| watch::channel(SyncState::default()).0);
+        tx.send_if_modified(|current| update_state(current, state, force));
+    }
+
+    // a clean record's state is only worth keeping while somebody's watching it,
+    // or while it's a failure. a later `watch` starts over from the default, which is synced
+    fn drop_unwatched(&self, record: &RecordKey) {
+        let mut states = self.states.lock().unwrap();
+        if states.get(record).is_some_and(|tx| {
+            tx.receiver_count() == 0 && !matches!(*tx.borrow(), SyncState::Failed(_))
+        }) {
+            states.remove(record);
+        }
+    }
+
+    fn next_delay_ms(&self) -> Option<u32> {
+        self.dirty.lock().unwrap().next_delay_ms(get_timestamp())
+    }
+
+    fn recheck_all(&self) {
+        self.dirty.lock().unwrap().recheck_all(get_timestamp());
+    }
+
+    async fn check_due(&self) -> Result<(), ConnectionError> {
+        let due = self.dirty.lock().unwrap().due(get_timestamp());
+        if due.is_empty() {
+            return Ok(());
+        }
+
+        let ctx = self.connection.routing_context()?;
+        let attached = self.is_attached();
+        for (key, generation) in due {
+            let offline = match ctx
+                .inspect_dht_record(key.clone(), None, DHTReportScope::Local)
+                .await
+            {
+                Ok(report) => Some(report.offline_subkeys().len() as usize),
+                Err(e) => {
+                    debug!("failed to inspect record {key} for sync state: {e}");
+                    None
+                }
+            };
+
+            let now = get_timestamp();
+            let checked = self
+                .dirty
+                .lock()
+                .unwrap()
+                .checked(&key, generation, offline, now);
+            let Some(raced) = checked else {
+                continue;
+            };
+
+            let state = match offline {
+                _ if raced => continue,
+                Some(0) => SyncState::Synced,
+                Some(_) if attached => SyncState::Syncing,
+                Some(_) => SyncState::LocalOnly,
+                None => continue,
+            };
+            self.set_state(&key, state, false);
+            if offline == Some(0) {
+                self.drop_unwatched(&key);
+                let _ = self.settled_tx.send(key);
+            }
+        }
+        self.publish_pending();
+        Ok(())
+    }
+
+    fn publish_pending(&self) {
+        let pending = self.dirty.lock().unwrap().pending();
+        self.pending_tx.send_if_modified(|current| {
+            if *current == pending {
+                return false;
+            }
+            *current = pending;
+            true
+        });
+    }
+}
+
+impl Drop for SyncTracker {
+    fn drop(&mut self) {
+        // wake the task so it notices we're gone
+        self.wake.notify_one();
+    }
+}
+
+// holds only a weak ref between checks, so the task exits once the pool is dropped
+async fn run(weak: Weak<SyncTracker>) {
+    let (wake, mut attachment_rx) = match weak.upgrade() {
+        Some(tracker) => (
+            Arc::clone(&tracker.wake),
+            tracker.connection.attachment_state(),
+        ),
+        None => return,
+    };
+
+    loop {
+        let Some(delay) = weak.upgrade().map(|t| t.next_delay_ms().unwrap_or(IDLE_MS)) else {
+            break;
+        };
+        select! {
+            _ = wake.notified().fuse() => {}
+            _ = sleep(delay).fuse() => {}
+            result = attachment_rx.changed().fuse() => {
+                if result.is_err() { break; }
+                // offline writes get flushed once we're back, so look again straight away
+                if let Some(tracker) = weak.upgrade() {
+                    tracker.recheck_all();
+                }
+            }
+        }
+        let Some(tracker) = weak.upgrade() else {
+            break;
+        };
+        // no routing context means we're shutting down
+        if tracker.check_due().await.is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn key(c: char) -> RecordKey {
+        RecordKey::from_str(&format!(
+            "VLD0:{c}X9L_EV3JAy5ozyK875WErKAyFhBy4jZ-6DZajlDr9c:KpS0JtGg9OfJhpsIVCFY8FI9arViozN3kw3duglNkmY"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn clean_check_clears() {
+        let mut dirty = DirtySet::default();
+        let record = key('a');
+        dirty.mark(&record, 0);
+        let due = dirty.due(0);
+        assert_eq!(due, vec![(record.clone(), 0)]);
+
+        assert_eq!(dirty.checked(&record, 0, Some(0), 10), Some(false));
+        assert!(dirty.0.is_empty());
+        assert_eq!(dirty.next_delay_ms(10), None);
+        // already gone, e.g. cleared by an earlier check
+        assert_eq!(dirty.checked(&record, 0, Some(0), 10), None);
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        let mut dirty = DirtySet::default();
+        let record = key('a');
+        dirty.mark(&record, 0);
+
+        let mut now = 0;
+        let mut expected = MIN_BACKOFF_MS;
+        for _ in 0..10 {
+            assert_eq!(dirty.due(now).len(), 1);
+            dirty.checked(&record, 0, Some(2), now);
+            expected = (expected * 2).min(MAX_BACKOFF_MS);
+            assert_eq!(dirty.next_delay_ms(now), Some(expected));
+            // not due again until the backoff has passed
+            assert!(dirty.due(now + expected as u64 * 1000 - 1).is_empty());
+            now += expected as u64 * 1000;
+        }
+        assert_eq!(expected, MAX_BACKOFF_MS);
+
+        // a new write starts over
+        dirty.mark(&record, now);
+        assert_eq!(dirty.0[&record].backoff_ms, MIN_BACKOFF_MS);
+        assert_eq!(dirty.next_delay_ms(now), Some(0));
+    }
+
+    #[test]
+    fn raced_write_stays_dirty() {
+        let mut dirty = DirtySet::default();
+        let record = key('a');
+        dirty.mark(&record, 0);
+        let [(_, generation)] = dirty.due(0)[..] else {
+            panic!("expected one due record");
+        };
+
+        // written again while the check was in flight
+        dirty.mark(&record, 5);
+        assert_eq!(dirty.checked(&record, generation, Some(0), 10), Some(true));
+        let entry = &dirty.0[&record];
+        // still due straight away, with the backoff left alone
+        assert_eq!(entry.next_check, 5);
+        assert_eq!(entry.backoff_ms, MIN_BACKOFF_MS);
+
+        // the next check sees the newer generation and can clear it
+        let [(_, generation)] = dirty.due(10)[..] else {
+            panic!("expected one due record");
+        };
+        assert_eq!(dirty.checked(&record, generation, Some(0), 10), Some(false));
+        assert!(dirty.0.is_empty());
+    }
+
+    #[test]
+    fn failed_check_keeps_offline_count() {
+        let mut dirty = DirtySet::default();
+        let record = key('a');
+        dirty.mark(&record, 0);
+        dirty.checked(&record, 0, Some(3), 0);
+        dirty.checked(&record, 0, None, 0);
+        assert_eq!(dirty.offline().get(&record), Some(&3));
+    }
+
+    #[test]
+    fn pending_and_recheck() {
+        let mut dirty = DirtySet::default();
+        let (a, b, c) = (key('a'), key('b'), key('c'));
+        for record in [&a, &b, &c] {
+            dirty.mark(record, 0);
+        }
+        dirty.checked(&a, 0, Some(2), 0);
+        dirty.checked(&b, 0, Some(3), 0);
+        // c hasn't been checked yet, so it isn't counted
+        assert_eq!(
+            dirty.pending(),
+            PendingSync {
+                records: 2,
+                subkeys: 5
+            }
+        );
+        assert_eq!(dirty.offline().len(), 2);
+
+        dirty.recheck_all(100);
+        assert_eq!(dirty.due(100).len(), 3);
+        assert!(dirty.0.values().all(|d| d.backoff_ms == MIN_BACKOFF_MS));
+    }
+
+    #[test]
+    fn failures_stick_until_a_write() {
+        let mut state = SyncState::Failed("nope".to_string());
+        assert!(!update_state(&mut state, SyncState::Synced, false));
+        assert!(matches!(state, SyncState::Failed(_)));
+
+        assert!(update_state(&mut state, SyncState::Syncing, true));
+        assert_eq!(state, SyncState::Syncing);
+        assert!(!update_state(&mut state, SyncState::Syncing, false));
+        assert!(update_state(&mut state, SyncState::Synced, false));
+    }
+}
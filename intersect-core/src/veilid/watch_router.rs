@@ -1,24 +1,110 @@
 use std::{
     any::{Any, type_name},
     collections::HashMap,
-    sync::{Arc, Mutex},
+    sync::{Arc, Mutex, Weak},
 };
 
-use tokio::sync::watch;
+use futures::{FutureExt, future::pending, select};
+use tokio::sync::{Mutex as AsyncMutex, Notify, broadcast, watch};
 use veilid_core::{KeyPair, RecordKey, VeilidValueChange};
-use veilid_tools::spawn_detached;
+use veilid_tools::{get_timestamp, sleep::sleep, spawn_detached};
 
 use crate::{
-    api::{Document, DocumentError, TypedReference},
-    veilid::{RecordPool, updates::UpdateHandler},
+    api::{Document, DocumentChange, DocumentError, Reference, TypedReference, ViewCache},
+    debug,
+    veilid::{NetworkState, RecordPool, WatchMode, updates::UpdateHandler},
 };
 
 // ==== WatchRouter ====
-// dispatches veilid value_change events to per-record watch channels.
-// each record gets a watch::Sender<()>; subscribers get independent Receivers.
+// dispatches veilid value_change events to per-record watch channels,
+// and owns the lifecycle of the veilid watch behind them: setting it up, renewing it
+// before it runs out or after it lapsed, and cancelling it once nobody needs it.
+// records veilid won't watch are polled by their coordinators instead, until a watch can be set up.
+// reads that fail for reasons that might pass (see `DocumentError::is_transient`) are retried the same way.
+// each record gets a broadcast::Sender<RecordChange>; subscribers get independent Receivers.
+
+// how long each watch is asked for. veilid may cut it short, in which case it tells us
+const WATCH_DURATION_MS: u64 = 10 * 60 * 1000;
+// renew watches this long before they run out
+const RENEW_AHEAD_MS: u64 = 60_000;
+const RENEW_CHECK_MS: u32 = 15_000;
+// while polling, try to get a proper watch again this often
+const WATCH_RETRY_MS: u64 = 5 * 60 * 1000;
+// polling backs off from MIN to MAX while nothing changes
+const MIN_POLL_MS: u32 = 10_000;
+const MAX_POLL_MS: u32 = 120_000;
+// changes a slow subscriber can fall behind by before it has to catch up with a full read
+const CHANGE_BUFFER: usize = 64;
+
+/// a change to a watched record
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum RecordChange {
+    /// these subkeys changed. veilid stores the new values locally before telling us,
+    /// so they can be read without going to the network (which is why the value itself isn't passed along)
+    Subkeys(Vec<u32>),
+    /// changes might have been missed, so everything has to be read from the network again
+    CatchUp,
+}
+
+struct Route {
+    reference: Reference,
+    notify: broadcast::Sender<RecordChange>,
+    mode: watch::Sender<WatchMode>,
+    // live watch guards for this record
+    refs: usize,
+    // µs timestamp the veilid watch has to be renewed (or retried, while polling) by.
+    // None until the first attempt
+    renew_at: Option<u64>,
+    // the watch lapsed at some point, so changes might have been missed
+    lapsed: bool,
+    // held while the veilid watch is being set up, renewed or cancelled,
+    // so a cancel can never land after a concurrent re-open
+    lifecycle: Arc<AsyncMutex<()>>,
+}
+
+impl Route {
+    fn new(reference: Reference) -> Self {
+        Self {
+            reference,
+            notify: broadcast::channel(CHANGE_BUFFER).0,
+            // polled until the watch is set up
+            mode: watch::channel(WatchMode::Polling).0,
+            refs: 0,
+            renew_at: None,
+            // the first read might have happened before the watch was up
+            lapsed: true,
+            lifecycle: Arc::new(AsyncMutex::new(())),
+        }
+    }
+
+    fn watching(&mut self, expires: u64) {
+        self.renew_at = Some(expires - RENEW_AHEAD_MS * 1000);
+        self.mode.send_replace(WatchMode::Watching);
+        if self.lapsed {
+            self.lapsed = false;
+            let _ = self.notify.send(RecordChange::CatchUp);
+        }
+    }
+
+    fn polling(&mut self) {
+        self.renew_at = Some(get_timestamp() + WATCH_RETRY_MS * 1000);
+        self.mode.send_replace(WatchMode::Polling);
+        // whatever happened while the coordinators were waiting on the watch is only picked up by polling
+        self.lapsed = true;
+    }
+
+    fn lapse(&mut self) {
+        if self.mode.borrow().is_watching() {
+            self.renew_at = Some(0);
+            self.lapsed = true;
+        }
+    }
+}
 
 pub struct WatchRouter {
-    routes: Mutex<HashMap<RecordKey, watch::Sender<()>>>,
+    routes: Mutex<HashMap<RecordKey, Route>>,
+    // wakes the keeper when a watch lapses
+    wake: Arc<Notify>,
 }
 
 impl Default for WatchRouter {
@@ -31,55 +117,314 @@ impl WatchRouter {
     pub fn new() -> Self {
         Self {
             routes: Mutex::new(HashMap::new()),
+            wake: Arc::new(Notify::new()),
+        }
+    }
+
+    /// takes a reference on the record's watch, setting up the veilid watch in the background
+    /// if nobody else holds one. the record is polled until that's done, or for good if veilid won't watch it.
+    /// without `watch` nothing is set up until `resume` is called, e.g. while cache-only.
+    /// the watch is cancelled once the last guard is dropped.
+    pub(crate) fn acquire(
+        self: &Arc<Self>,
+        reference: &Reference,
+        pool: &Weak<RecordPool>,
+        watch: bool,
+    ) -> WatchGuard {
+        let first = {
+            let mut routes = self.routes.lock().unwrap();
+            let route = routes
+                .entry(reference.record().clone())
+                .or_insert_with(|| Route::new(reference.clone()));
+            route.refs += 1;
+            (watch && route.refs == 1 && route.renew_at.is_none())
+                .then(|| Arc::clone(&route.lifecycle))
+        };
+        if let Some(lifecycle) = first {
+            let router = Arc::clone(self);
+            let reference = reference.clone();
+            let pool = Weak::clone(pool);
+            spawn_detached("watch_acquire", async move {
+                let _lifecycle = lifecycle.lock().await;
+                // released again, or already set up by the keeper, while we were waiting
+                let still_first = router
+                    .routes
+                    .lock()
+                    .unwrap()
+                    .get(reference.record())
+                    .is_some_and(|r| r.refs > 0 && r.renew_at.is_none());
+                if let (true, Some(pool)) = (still_first, pool.upgrade()) {
+                    router.try_watch(&reference, &pool).await;
+                }
+            });
+        }
+        WatchGuard {
+            router: Arc::clone(self),
+            reference: reference.clone(),
+            pool: Weak::clone(pool),
         }
     }
 
     // subscribe to change notifications for a record.
-    // creates the entry if this is the first subscriber.
-    pub(crate) fn subscribe(&self, key: RecordKey) -> watch::Receiver<()> {
-        let mut routes = self.routes.lock().unwrap();
-        match routes.get(&key) {
-            Some(tx) => tx.subscribe(),
-            None => {
-                let (tx, rx) = watch::channel(());
-                routes.insert(key, tx);
-                rx
+    // only called while holding a guard, so the route is always there
+    pub(crate) fn subscribe(&self, key: &RecordKey) -> broadcast::Receiver<RecordChange> {
+        match self.routes.lock().unwrap().get(key) {
+            Some(route) => route.notify.subscribe(),
+            // closed straight away, the coordinator won't stick around
+            None => broadcast::channel(1).1,
+        }
+    }
+
+    // picks the record's channels up again after its route was dropped and set up anew,
+    // e.g. because the last guard was released just before the record was opened again.
+    // None if nobody holds a guard anymore
+    fn resubscribe(
+        &self,
+        key: &RecordKey,
+    ) -> Option<(
+        broadcast::Receiver<RecordChange>,
+        watch::Receiver<WatchMode>,
+    )> {
+        self.routes
+            .lock()
+            .unwrap()
+            .get(key)
+            .map(|route| (route.notify.subscribe(), route.mode.subscribe()))
+    }
+
+    /// has the record's coordinators read it again from the network,
+    /// e.g. because they started out from a cached view
+    pub(crate) fn catch_up(&self, key: &RecordKey) {
+        if let Some(route) = self.routes.lock().unwrap().get(key) {
+            let _ = route.notify.send(RecordChange::CatchUp);
+        }
+    }
+
+    /// sets up the watches `acquire` was told to hold off on, and has every record's coordinators
+    /// catch up on whatever they skipped in the meantime. for when cache-only is switched off
+    pub(crate) fn resume(&self) {
+        for route in self.routes.lock().unwrap().values_mut() {
+            if route.renew_at.is_none() {
+                route.renew_at = Some(0);
             }
+            let _ = route.notify.send(RecordChange::CatchUp);
         }
+        self.wake.notify_one();
+    }
+
+    /// whether the record is watched or polled. same caveat as `subscribe`
+    pub(crate) fn mode(&self, key: &RecordKey) -> watch::Receiver<WatchMode> {
+        match self.routes.lock().unwrap().get(key) {
+            Some(route) => route.mode.subscribe(),
+            None => watch::channel(WatchMode::default()).1,
+        }
+    }
+
+    /// keeps watches alive in the background: renews them ahead of time, and once they lapse
+    /// (veilid gave up on them, or we were detached) sets them up again and tells subscribers to catch up.
+    /// polled records get another shot at a watch every so often.
+    /// the task exits once the pool is dropped.
+    pub(crate) fn spawn_keeper(
+        self: &Arc<Self>,
+        pool: Weak<RecordPool>,
+        network: watch::Receiver<NetworkState>,
+    ) {
+        spawn_detached(
+            "watch_keeper",
+            run_keeper(Arc::downgrade(self), pool, network),
+        );
     }
 
-    // atomically removes the entry only if it has no remaining receivers.
-    // returns true if deregistered. combining the check and remove under one lock
-    // prevents a concurrent open() from inserting a new subscriber between the two.
-    // TODO: there is still a narrow race between this returning true and the caller invoking pool.cancel_watch
-    // a concurrent open() that called pool.watch() before this point will have its DHT watch silently cancelled
-    pub(crate) fn deregister_if_empty(&self, key: &RecordKey) -> bool {
+    // caller holds the route's lifecycle lock
+    async fn try_watch(&self, reference: &Reference, pool: &RecordPool) {
+        let expires = get_timestamp() + WATCH_DURATION_MS * 1000;
+        let result = pool.watch(reference, expires).await;
         let mut routes = self.routes.lock().unwrap();
-        match routes.get(key) {
-            Some(tx) if tx.receiver_count() == 0 => {
-                routes.remove(key);
-                true
+        let Some(route) = routes.get_mut(reference.record()) else {
+            return;
+        };
+        match result {
+            Ok(()) => route.watching(expires),
+            Err(e) => {
+                debug!(
+                    "can't watch {}, polling it instead: {e}",
+                    reference.record()
+                );
+                route.polling();
             }
-            _ => false,
         }
     }
+
+    // every watch is gone after a detach
+    fn lapse_all(&self) {
+        for route in self.routes.lock().unwrap().values_mut() {
+            route.lapse();
+        }
+    }
+
+    async fn renew_due(&self, pool: &RecordPool) {
+        let is_due = |r: &Route| r.refs > 0 && r.renew_at.is_some_and(|at| at <= get_timestamp());
+        let due: Vec<(Reference, Arc<AsyncMutex<()>>)> = self
+            .routes
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|r| is_due(r))
+            .map(|r| (r.reference.clone(), Arc::clone(&r.lifecycle)))
+            .collect();
+
+        for (reference, lifecycle) in due {
+            let _lifecycle = lifecycle.lock().await;
+            // might have been closed, or renewed by a re-open, while we were waiting
+            let still_due = self
+                .routes
+                .lock()
+                .unwrap()
+                .get(reference.record())
+                .is_some_and(is_due);
+            if still_due {
+                self.try_watch(&reference, pool).await;
+            }
+        }
+    }
+
+    fn release(self: &Arc<Self>, reference: Reference, pool: Weak<RecordPool>) {
+        let lifecycle = {
+            let mut routes = self.routes.lock().unwrap();
+            let Some(route) = routes.get_mut(reference.record()) else {
+                return;
+            };
+            route.refs -= 1;
+            if route.refs > 0 {
+                return;
+            }
+            Arc::clone(&route.lifecycle)
+        };
+
+        // cancelling is a network round trip, which can't happen in drop
+        let router = Arc::clone(self);
+        spawn_detached("watch_release", async move {
+            let _lifecycle = lifecycle.lock().await;
+            let record = reference.record();
+            let watching = match router.routes.lock().unwrap().get(record) {
+                Some(route) if route.refs == 0 => route.mode.borrow().is_watching(),
+                // opened again while we were waiting
+                _ => return,
+            };
+            if let (true, Some(pool)) = (watching, pool.upgrade()) {
+                let _ = pool.cancel_watch(&reference).await;
+            }
+
+            let mut routes = router.routes.lock().unwrap();
+            match routes.get_mut(record) {
+                // dropping the senders also stops the record's coordinators
+                Some(route) if route.refs == 0 => {
+                    routes.remove(record);
+                }
+                // opened again while we were cancelling, it'll have to watch again
+                Some(route) => {
+                    route.renew_at = Some(0);
+                    route.lapsed = true;
+                    router.wake.notify_one();
+                }
+                None => {}
+            }
+        });
+    }
+}
+
+// holds only weak refs between rounds, so the task exits once the pool is dropped
+async fn run_keeper(
+    router: Weak<WatchRouter>,
+    pool: Weak<RecordPool>,
+    mut network: watch::Receiver<NetworkState>,
+) {
+    let Some(wake) = router.upgrade().map(|r| Arc::clone(&r.wake)) else {
+        return;
+    };
+    let mut attached = network.borrow_and_update().attached;
+
+    loop {
+        select! {
+            _ = sleep(RENEW_CHECK_MS).fuse() => {}
+            _ = wake.notified().fuse() => {}
+            changed = network.changed().fuse() => {
+                if changed.is_err() {
+                    break;
+                }
+                let now_attached = network.borrow_and_update().attached;
+                if now_attached && !attached {
+                    if let Some(router) = router.upgrade() {
+                        router.lapse_all();
+                    }
+                }
+                attached = now_attached;
+            }
+        }
+        // nothing can be renewed while detached, it all gets redone once we're back
+        if !attached {
+            continue;
+        }
+        let (Some(router), Some(pool)) = (router.upgrade(), pool.upgrade()) else {
+            break;
+        };
+        router.renew_due(&pool).await;
+    }
+}
+
+/// keeps a record watched. every open document holds one,
+/// and the watch is cancelled as soon as the last one for the record is dropped.
+pub(crate) struct WatchGuard {
+    router: Arc<WatchRouter>,
+    reference: Reference,
+    pool: Weak<RecordPool>,
+}
+
+impl Drop for WatchGuard {
+    fn drop(&mut self) {
+        self.router
+            .release(self.reference.clone(), Weak::clone(&self.pool));
+    }
 }
 
 impl UpdateHandler for WatchRouter {
     fn value_change(&self, change: &VeilidValueChange) {
-        let routes = self.routes.lock().unwrap();
-        if let Some(tx) = routes.get(&change.key) {
-            // not sending the actual data here, just a notification which will trigger a re-read in the coordinator
-            let _ = tx.send(());
+        let mut routes = self.routes.lock().unwrap();
+        let Some(route) = routes.get_mut(&change.key) else {
+            return;
+        };
+        let subkeys: Vec<u32> = change.subkeys.iter().collect();
+        if !subkeys.is_empty() {
+            // the coordinators re-read just these
+            let _ = route.notify.send(RecordChange::Subkeys(subkeys));
+        }
+        // nothing left on the watch, veilid has given up on it
+        if change.count == 0 {
+            route.lapse();
+            self.wake.notify_one();
         }
     }
 }
 
 // ==== WatchCoordinators ====
-// one coordinator task per record. does a single D::read() per veilid notification
+// one coordinator task per record. does a single read per veilid notification, only of what changed where possible,
 // and fans the result out to all subscribers to avoid duplicate reads for multiple subscribers to the same record.
 
-type CoordinatorSender<D> = Arc<watch::Sender<Result<<D as Document>::View, DocumentError>>>;
+struct Coordinator<D: Document> {
+    views: watch::Sender<Result<D::View, DocumentError>>,
+    changes: broadcast::Sender<DocumentChange>,
+}
+
+impl<D: Document> Coordinator<D> {
+    fn subscribe(&self) -> Subscription<D> {
+        (self.views.subscribe(), self.changes.subscribe())
+    }
+}
+
+type Subscription<D> = (
+    watch::Receiver<Result<<D as Document>::View, DocumentError>>,
+    broadcast::Receiver<DocumentChange>,
+);
 // keying on record key + document type here to avoid coordinator tasks being overwritten by different document types.
 // in practice this should never happen since each record only has one valid type
 // but we want to make sure things don't break even if we somehow erroneaously try to open the same record with different types
@@ -89,105 +434,229 @@ type CoordinatorMap = Arc<Mutex<HashMap<(RecordKey, &'static str), Box<dyn Any +
 #[derive(Clone)]
 pub struct WatchCoordinators {
     inner: CoordinatorMap,
-}
-
-impl Default for WatchCoordinators {
-    fn default() -> Self {
-        Self::new()
-    }
+    // the identity documents are read with. changing it re-reads every open document,
+    // so private sections appear on login and disappear on logout
+    identity: Arc<watch::Sender<KeyPair>>,
+    // every new view goes in here, so it's there for the next session (or an offline one)
+    cache: ViewCache,
 }
 
 impl WatchCoordinators {
-    pub fn new() -> Self {
+    pub(crate) fn new(identity: KeyPair, cache: ViewCache) -> Self {
         Self {
             inner: Arc::new(Mutex::new(HashMap::new())),
+            identity: Arc::new(watch::channel(identity).0),
+            cache,
         }
     }
 
+    pub(crate) fn set_identity(&self, identity: KeyPair) {
+        self.identity.send_replace(identity);
+    }
+
     // if a coordinator is already running for this record + document type,
-    // returns a new subscriber receiver (immediately holds the latest cached view).
-    pub fn try_subscribe<D: Document>(
-        &self,
-        key: &RecordKey,
-    ) -> Option<watch::Receiver<Result<D::View, DocumentError>>> {
+    // returns a new subscription (the view receiver immediately holds the latest cached view).
+    pub(crate) fn try_subscribe<D: Document>(&self, key: &RecordKey) -> Option<Subscription<D>> {
         self.inner
             .lock()
             .unwrap()
             .get(&(key.clone(), type_name::<D>()))?
-            .downcast_ref::<CoordinatorSender<D>>()
-            .map(|s| s.subscribe())
+            .downcast_ref::<Arc<Coordinator<D>>>()
+            .map(|c| c.subscribe())
     }
 
-    // creates a coordinator for this record and returns a receiver.
+    // creates a coordinator for this record and returns a subscription.
     // if another open() raced us and already created one, subscribes to that instead.
-    pub fn create<D: Document>(
+    pub(crate) fn create<D: Document>(
         &self,
         typed_ref: TypedReference<D>,
         initial: D::View,
         pool: Arc<RecordPool>,
-        identity: Option<KeyPair>,
-        notify_rx: watch::Receiver<()>,
-        router: Arc<WatchRouter>,
-    ) -> watch::Receiver<Result<D::View, DocumentError>> {
+        router: &Arc<WatchRouter>,
+    ) -> Subscription<D> {
         let mut map = self.inner.lock().unwrap();
 
         // double-check: another open() may have raced us during the async initial read
-        if let Some(s) = map
+        if let Some(c) = map
             .get(&(typed_ref.reference().record().clone(), type_name::<D>()))
-            .and_then(|b| b.downcast_ref::<CoordinatorSender<D>>())
+            .and_then(|b| b.downcast_ref::<Arc<Coordinator<D>>>())
         {
-            return s.subscribe();
+            return c.subscribe();
         }
 
-        let (tx, rx) = watch::channel(Ok(initial));
-        let sender: CoordinatorSender<D> = Arc::new(tx);
+        let coordinator = Arc::new(Coordinator::<D> {
+            views: watch::channel(Ok(initial)).0,
+            changes: broadcast::channel(CHANGE_BUFFER).0,
+        });
+        let subscription = coordinator.subscribe();
         map.insert(
             (typed_ref.reference().record().clone(), type_name::<D>()),
-            Box::new(Arc::clone(&sender)),
+            Box::new(Arc::clone(&coordinator)),
         );
         drop(map); // drop the lock
 
-        let inner = Arc::clone(&self.inner);
+        let coordinators = self.clone();
+        let notify_rx = router.subscribe(typed_ref.reference().record());
+        let mode_rx = router.mode(typed_ref.reference().record());
+        let identity_rx = self.identity.subscribe();
+        let router = Arc::downgrade(router);
         spawn_detached("intersect-coordinator", async move {
-            coordinator_task::<D>(typed_ref, pool, identity, notify_rx, sender, router, inner)
-                .await;
+            coordinator_task::<D>(
+                typed_ref,
+                pool,
+                router,
+                notify_rx,
+                mode_rx,
+                identity_rx,
+                coordinator,
+                coordinators,
+            )
+            .await;
         });
 
-        rx
+        subscription
     }
 }
 
 async fn coordinator_task<D: Document>(
     typed_ref: TypedReference<D>,
     pool: Arc<RecordPool>,
-    identity: Option<KeyPair>,
-    mut notify_rx: watch::Receiver<()>,
-    sender: CoordinatorSender<D>,
-    router: Arc<WatchRouter>,
-    coordinators: CoordinatorMap,
+    router: Weak<WatchRouter>,
+    mut notify_rx: broadcast::Receiver<RecordChange>,
+    mut mode_rx: watch::Receiver<WatchMode>,
+    mut identity_rx: watch::Receiver<KeyPair>,
+    coordinator: Arc<Coordinator<D>>,
+    coordinators: WatchCoordinators,
 ) {
+    let key = (typed_ref.reference().record().clone(), type_name::<D>());
     // seed last_view from the initial value already in the channel
-    let mut last_view: Option<D::View> = sender.borrow().as_ref().ok().cloned();
+    let mut last_view: Option<D::View> = coordinator.views.borrow().as_ref().ok().cloned();
+    let mut retrying = coordinator
+        .views
+        .borrow()
+        .as_ref()
+        .is_err_and(DocumentError::is_transient);
+    let mut poll_ms = MIN_POLL_MS;
 
     loop {
-        if notify_rx.changed().await.is_err() {
-            break; // WatchRouter entry dropped
-        }
+        // only ticks while the record can't be watched, or the last read might work if tried again
+        let polling = !mode_rx.borrow_and_update().is_watching() || retrying;
+        let poll = async move {
+            match polling {
+                true => sleep(poll_ms).await,
+                false => pending::<()>().await,
+            }
+        };
+        let mut polled = false;
 
-        // notification triggers re-read of the document
-        match D::read(&typed_ref, identity.as_ref(), false, &pool).await {
+        // None once the router entry is gone
+        let change = select! {
+            change = notify_rx.recv().fuse() => match change {
+                Ok(RecordChange::Subkeys(subkeys)) => Some(DocumentChange::Subkeys(subkeys)),
+                Ok(RecordChange::CatchUp) => Some(DocumentChange::Full),
+                // fell behind, so some changes are gone. start over
+                Err(broadcast::error::RecvError::Lagged(_)) => Some(DocumentChange::Full),
+                // last watch guard dropped
+                Err(broadcast::error::RecvError::Closed) => None,
+            },
+            changed = identity_rx.changed().fuse() => {
+                if changed.is_err() {
+                    break; // intersect dropped
+                }
+                Some(DocumentChange::Identity)
+            },
+            _ = coordinator.views.closed().fuse() => {
+                // checked under the map lock, so nobody can subscribe between this and the removal
+                let mut map = coordinators.inner.lock().unwrap();
+                if coordinator.views.receiver_count() == 0 {
+                    remove_if_ours(&mut map, &key, &coordinator);
+                    return;
+                }
+                continue;
+            }
+            changed = mode_rx.changed().fuse() => match changed {
+                Ok(()) => {
+                    poll_ms = MIN_POLL_MS;
+                    continue;
+                }
+                Err(_) => None,
+            },
+            _ = poll.fuse() => {
+                polled = true;
+                Some(DocumentChange::Full)
+            },
+        };
+        let change = match change {
+            Some(change) => change,
+            // the record might have been opened again right after the last guard went,
+            // and subscribed to this coordinator before it could leave. if so it carries on
+            // with the new router entry, reading everything again since changes might have been missed
+            None => {
+                let mut map = coordinators.inner.lock().unwrap();
+                let route = match coordinator.views.receiver_count() {
+                    0 => None,
+                    _ => router
+                        .upgrade()
+                        .and_then(|router| router.resubscribe(&key.0)),
+                };
+                let Some((notify, mode)) = route else {
+                    // under the map lock, so nobody can subscribe between the check and the removal
+                    remove_if_ours(&mut map, &key, &coordinator);
+                    return;
+                };
+                drop(map);
+                notify_rx = notify;
+                mode_rx = mode;
+                poll_ms = MIN_POLL_MS;
+                DocumentChange::Full
+            }
+        };
+
+        // the cached view is all there is while cache-only, nothing gets read until it's switched off
+        if coordinators.cache.cache_only() {
+            continue;
+        }
+        let identity = identity_rx.borrow_and_update().clone();
+        let read = match (&change, &last_view) {
+            // the rest of the document is still what we have
+            (DocumentChange::Subkeys(subkeys), Some(current)) => {
+                D::read_changed(&typed_ref, Some(&identity), current, subkeys, &pool).await
+            }
+            // the last read failed, so there's nothing to patch. the change is local already though
+            (DocumentChange::Subkeys(_), None) => {
+                D::read(&typed_ref, Some(&identity), false, &pool).await
+            }
+            // nothing changed on the network, only what we can decrypt
+            (DocumentChange::Identity, _) => {
+                D::read(&typed_ref, Some(&identity), false, &pool).await
+            }
+            // catching up has to ask the network
+            (DocumentChange::Full, _) => D::read(&typed_ref, Some(&identity), true, &pool).await,
+        };
+        // poll quickly while things are changing, and back off while they aren't
+        if polled {
+            poll_ms = match &read {
+                Ok(view) if Some(view) != last_view.as_ref() => MIN_POLL_MS,
+                _ => (poll_ms * 2).min(MAX_POLL_MS),
+            };
+        }
+        retrying = read.as_ref().is_err_and(DocumentError::is_transient);
+        match read {
             Ok(new_view) => {
                 if Some(&new_view) != last_view.as_ref() {
+                    coordinators.cache.put(&typed_ref, &new_view);
                     last_view = Some(new_view.clone());
-                    if sender.send(Ok(new_view)).is_err() {
+                    if coordinator.views.send(Ok(new_view)).is_err() {
                         break; // all receivers dropped
                     }
+                    // nobody listening for changes is fine, the view is what counts
+                    let _ = coordinator.changes.send(change);
                 }
             }
             Err(e) => {
                 // send the error but don't update last_view
                 // next successful read will still be compared against the last good view
-                if sender.send(Err(e)).is_err() {
+                if coordinator.views.send(Err(e)).is_err() {
                     break;
                 }
             }
@@ -195,16 +664,88 @@ async fn coordinator_task<D: Document>(
     }
 
     // remove from coordinator map, this drops the map's Arc clone.
-    // when this function returns, the local sender Arc drops too.
-    // once all Arc clones are gone, the watch::Sender drops and any remaining
-    // receivers' changed() will return Err.
-    coordinators
-        .lock()
-        .unwrap()
-        .remove(&(typed_ref.reference().record().clone(), type_name::<D>()));
-
-    drop(notify_rx); // make sure to decrement listeners before we check if empty
-    if router.deregister_if_empty(typed_ref.reference().record()) {
-        let _ = pool.cancel_watch(typed_ref.reference()).await;
+    // when this function returns, the local Arc drops too.
+    // once all Arc clones are gone, the senders drop and any remaining
+    // receivers will see the channels closed.
+    remove_if_ours(&mut coordinators.inner.lock().unwrap(), &key, &coordinator);
+}
+
+// a newer coordinator for the same record may have taken the entry already, that one stays
+fn remove_if_ours<C: 'static>(
+    map: &mut HashMap<(RecordKey, &'static str), Box<dyn Any + Send + Sync>>,
+    key: &(RecordKey, &'static str),
+    ours: &Arc<C>,
+) {
+    let is_ours = map
+        .get(key)
+        .and_then(|b| b.downcast_ref::<Arc<C>>())
+        .is_some_and(|c| Arc::ptr_eq(c, ours));
+    if is_ours {
+        map.remove(key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use veilid_core::SharedSecret;
+
+    use super::*;
+
+    fn reference() -> Reference {
+        Reference::new(
+            RecordKey::from_str(
+                "VLD0:sX9L_EV3JAy5ozyK875WErKAyFhBy4jZ-6DZajlDr9c:KpS0JtGg9OfJhpsIVCFY8FI9arViozN3kw3duglNkmY",
+            )
+            .unwrap(),
+            SharedSecret::from_str("VLD0:KpS0JtGg9OfJhpsIVCFY8FI9arViozN3kw3duglNkmY").unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn reopen_right_after_release_gets_the_update() {
+        let router = Arc::new(WatchRouter::new());
+        let reference = reference();
+        let record = reference.record();
+
+        let guard = router.acquire(&reference, &Weak::new(), false);
+        let mut notify_rx = router.subscribe(record);
+        // release the last guard
+        drop(guard);
+        assert_eq!(
+            notify_rx.recv().await,
+            Err(broadcast::error::RecvError::Closed)
+        );
+        // reopened immediately, before the old coordinator got to leave
+        let _guard = router.acquire(&reference, &Weak::new(), false);
+        let (mut notify_rx, _mode_rx) = router.resubscribe(record).unwrap();
+        // then the update arrives
+        router.catch_up(record);
+        assert_eq!(notify_rx.recv().await, Ok(RecordChange::CatchUp));
+    }
+
+    #[tokio::test]
+    async fn nothing_to_resubscribe_to_once_released() {
+        let router = Arc::new(WatchRouter::new());
+        let reference = reference();
+        let guard = router.acquire(&reference, &Weak::new(), false);
+        let mut notify_rx = router.subscribe(reference.record());
+        drop(guard);
+        let _ = notify_rx.recv().await;
+        assert!(router.resubscribe(reference.record()).is_none());
+    }
+
+    #[test]
+    fn newer_coordinator_stays() {
+        let key = (reference().record().clone(), "test");
+        let old = Arc::new(1);
+        let new = Arc::new(2);
+        let mut map: HashMap<_, Box<dyn Any + Send + Sync>> = HashMap::new();
+        map.insert(key.clone(), Box::new(Arc::clone(&new)));
+        remove_if_ours(&mut map, &key, &old);
+        assert!(map.contains_key(&key));
+        remove_if_ours(&mut map, &key, &new);
+        assert!(!map.contains_key(&key));
     }
 }
@@ -3,7 +3,10 @@ use std::str::FromStr;
 use intersect_core::{Document, TypedTrace, TypedReference, models::TraceSecret};
 use leptos::prelude::*;
 
-use crate::components::base::{Form, Modal, TextInput};
+use crate::{
+    components::base::{Form, Modal, TextInput},
+    shell::use_intersect,
+};
 
 type UnlockResult<D> = Result<TypedReference<D>, String>;
 
@@ -37,6 +40,7 @@ pub fn AccessPrompt<D: Document + 'static>(
     let show = RwSignal::new(true);
     let input = RwSignal::new(String::new());
     let trace = StoredValue::new(trace);
+    let intersect = use_intersect();
 
     let validate = Callback::new(move |()| -> Result<TypedReference<D>, anyhow::Error> {
         let raw = input.get_untracked();
@@ -48,7 +52,7 @@ pub fn AccessPrompt<D: Document + 'static>(
                 let secret = TraceSecret::from_str(&raw)?;
                 Ok(locked.unlock(secret)?)
             }
-            TypedTrace::Protected(protected) => Ok(protected.unlock(&raw)?),
+            TypedTrace::Protected(protected) => Ok(protected.unlock(&intersect, &raw)?),
         })
     });
 
@@ -1,11 +1,24 @@
-use intersect_core::{TypedReference, documents::AccountDocument};
+use anyhow::anyhow;
+use intersect_core::{
+    TypedReference,
+    documents::{AccountDocument, AccountView},
+    models::{ContactStatus, TrustLevel},
+};
 use leptos::prelude::*;
+use leptos::task::spawn_local;
 
-use crate::components::{NetworkSuspend, use_open};
+use crate::{
+    components::{
+        NetworkSuspend,
+        base::{Form, TextInput},
+        use_loading, use_open,
+    },
+    shell::{use_account, use_contacts, use_intersect},
+};
 
 #[component]
 pub fn AccountDisplay(account_ref: TypedReference<AccountDocument>) -> impl IntoView {
-    let signal = use_open(account_ref);
+    let signal = use_open(account_ref.clone());
 
     view! {
         <NetworkSuspend signal let:account>
@@ -20,10 +33,165 @@ pub fn AccountDisplay(account_ref: TypedReference<AccountDocument>) -> impl Into
                             {name}
                             <span class="account-fingerprint">"#" {fingerprint}</span>
                         </p>
+                        <ContactBadge account=account.clone() />
                         {bio.map(|b| view! { <p class="account-bio">{b}</p> })}
+                        <FollowButton account_ref=account_ref.clone() />
                     </div>
                 }
             }
         </NetworkSuspend>
     }
 }
+
+/// shows what the local contact list knows about an account,
+/// warning loudly if the name belongs to a contact with a different fingerprint.
+#[component]
+fn ContactBadge(account: AccountView) -> impl IntoView {
+    let contacts = use_contacts();
+    let account = StoredValue::new(account);
+
+    move || match account.with_value(|a| a.contact_status(&contacts.get())) {
+        ContactStatus::Known(contact) => {
+            let verified = contact.trust() == TrustLevel::Verified;
+            view! {
+                <p class="account-contact" class:account-verified=verified>
+                    {if verified { "verified contact: " } else { "contact: " }}
+                    {contact.petname().as_ref().to_owned()}
+                </p>
+            }
+            .into_any()
+        }
+        ContactStatus::NameConflict {
+            contact,
+            fingerprint,
+        } => view! {
+            <p class="account-warning">
+                "warning: this name matches your contact \""
+                {contact.petname().as_ref().to_owned()}
+                "\" (#" {fingerprint} "), but the fingerprint is different"
+            </p>
+            <SaveContact account=account.get_value() />
+        }
+        .into_any(),
+        ContactStatus::Unknown => view! { <SaveContact account=account.get_value() /> }.into_any(),
+    }
+}
+
+#[component]
+fn SaveContact(account: AccountView) -> impl IntoView {
+    let petname_input = RwSignal::new(
+        account
+            .name()
+            .map(|n| n.as_ref().to_owned())
+            .unwrap_or_default(),
+    );
+    let fingerprint = account.public_key().fingerprint();
+
+    let validate = Callback::new(move |()| -> Result<String, anyhow::Error> {
+        let petname = petname_input.get_untracked();
+        if petname.trim().is_empty() {
+            return Err(anyhow!("name can't be empty"));
+        }
+        Ok(petname)
+    });
+
+    let intersect = use_intersect();
+    let loading = use_loading();
+
+    let on_submit = Callback::new(move |petname: String| {
+        let intersect = intersect.clone();
+        let fingerprint = fingerprint.clone();
+        spawn_local(async move {
+            // errors are surfaced as an overlay by loading.run
+            let _ = loading
+                .run(
+                    || async move {
+                        intersect
+                            .set_contact(&fingerprint, petname, TrustLevel::Known)
+                            .await
+                            .map_err(|e| anyhow!(e))
+                    },
+                    "saving contact...",
+                )
+                .await;
+        });
+    });
+
+    view! {
+        <Form validate on_submit>
+            <TextInput value=petname_input id="contact-petname" label="save as contact" />
+            <button type="submit">"save"</button>
+        </Form>
+    }
+}
+
+/// follow / unfollow toggle. hidden for anonymous sessions and for your own account.
+#[component]
+fn FollowButton(account_ref: TypedReference<AccountDocument>) -> impl IntoView {
+    let own = use_account();
+    let intersect = use_intersect();
+    let loading = use_loading();
+    // None until the follow list has loaded
+    let following: RwSignal<Option<bool>> = RwSignal::new(None);
+    let account_ref = StoredValue::new(account_ref);
+
+    Effect::new({
+        let intersect = intersect.clone();
+        move |_| {
+            // refetch whenever the session changes
+            if own.get().is_none() {
+                return;
+            }
+            let intersect = intersect.clone();
+            let account_ref = account_ref.get_value();
+            spawn_local(async move {
+                if let Ok(is_following) = intersect.is_following(&account_ref).await {
+                    following.set(Some(is_following));
+                }
+            });
+        }
+    });
+
+    let toggle = move |_| {
+        let Some(is_following) = following.get_untracked() else {
+            return;
+        };
+        let intersect = intersect.clone();
+        let account_ref = account_ref.get_value();
+        spawn_local(async move {
+            let result = loading
+                .run(
+                    || async move {
+                        if is_following {
+                            intersect.unfollow(&account_ref).await.map(|_| ())
+                        } else {
+                            intersect.follow(&account_ref).await
+                        }
+                        .map_err(|e| anyhow!(e))
+                    },
+                    if is_following {
+                        "unfollowing..."
+                    } else {
+                        "following..."
+                    },
+                )
+                .await;
+            if result.is_ok() {
+                following.set(Some(!is_following));
+            }
+        });
+    };
+
+    move || {
+        let is_own = own.with(|o| o.as_ref() == Some(&account_ref.get_value()));
+        match (own.with(Option::is_some) && !is_own, following.get()) {
+            (true, Some(is_following)) => view! {
+                <button type="button" class="account-follow" on:click=toggle.clone()>
+                    {if is_following { "unfollow" } else { "follow" }}
+                </button>
+            }
+            .into_any(),
+            _ => ().into_any(),
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use intersect_core::{TypedReference, documents::FragmentDocument};
+use intersect_core::{TypedReference, documents::FragmentDocument, models::Timestamp};
 use leptos::prelude::*;
 
 use crate::components::{NetworkSuspend, Note, use_fetch};
@@ -11,8 +11,11 @@ pub fn FragmentDisplay(fragment_ref: TypedReference<FragmentDocument>) -> impl I
         <NetworkSuspend signal let:fragment>
             {
                 let mime = fragment.mime().as_ref().to_owned();
+                let posted = fragment
+                    .created()
+                    .map(|t| view! { <p class="fragment-posted">"posted " {t.ago(Timestamp::now())}</p> });
                 if mime.starts_with("text/") {
-                    view! { <Note fragment /> }.into_any()
+                    view! { {posted} <Note fragment /> }.into_any()
                 } else {
                     let len = fragment.data().len();
                     view! {
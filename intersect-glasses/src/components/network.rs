@@ -1,4 +1,4 @@
-use intersect_core::{Document, MutableDocument, TypedReference};
+use intersect_core::{Document, MutableDocument, RecordError, TypedReference};
 use leptos::prelude::*;
 use leptos::task::spawn_local;
 
@@ -7,13 +7,16 @@ use crate::{use_intersect, util::watch_to_signal};
 /// the standard async data shape for data from the network: None while loading, Some(Err) on failure, Some(Ok) when ready.
 pub type NetworkSignal<T> = ReadSignal<Option<Result<T, String>>>;
 
-/// one-shot document fetch
+/// one-shot document fetch, showing the last known version straight away if there is one
 pub fn use_fetch<D: Document + 'static>(typed_ref: TypedReference<D>) -> NetworkSignal<D::View> {
     let intersect = use_intersect();
     let signal = RwSignal::new(None);
     spawn_local(async move {
         signal.set(Some(
-            intersect.fetch(&typed_ref).await.map_err(|e| e.to_string()),
+            intersect
+                .fetch_cached(&typed_ref)
+                .await
+                .map_err(|e| describe(e.record_error(), &e)),
         ));
     });
     signal.read_only()
@@ -28,10 +31,10 @@ pub fn use_open<D: MutableDocument + 'static>(
     let signal = RwSignal::new(None);
     spawn_local(async move {
         match intersect.open(&typed_ref).await {
-            Err(e) => signal.set(Some(Err(e.to_string()))),
+            Err(e) => signal.set(Some(Err(describe(e.record_error(), &e)))),
             Ok(doc) => {
                 watch_to_signal(Some(signal), doc.updates, |v| {
-                    Some(v.map_err(|e| e.to_string()))
+                    Some(v.map_err(|e| describe(e.record_error(), &e)))
                 });
             }
         }
@@ -39,6 +42,20 @@ pub fn use_open<D: MutableDocument + 'static>(
     signal.read_only()
 }
 
+/// plainer wording for the network failures people can do something about, or just wait out
+fn describe(record_error: Option<&RecordError>, e: &dyn std::fmt::Display) -> String {
+    match record_error {
+        Some(RecordError::NotFound) => {
+            "not found on the network. it might have expired, or the trace is wrong".to_string()
+        }
+        Some(RecordError::Offline) => "you're offline, try again once connected".to_string(),
+        Some(RecordError::Timeout) => {
+            "the network took too long to answer, try again in a bit".to_string()
+        }
+        _ => e.to_string(),
+    }
+}
+
 /// suspends child rendering behind a NetworkSignal,
 /// showing a loading state or error message until the data is ready.
 /// use `let:name` to bind the resolved value in the child view.
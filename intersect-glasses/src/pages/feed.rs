@@ -0,0 +1,106 @@
+use intersect_core::{
+    FeedChange, FeedItem,
+    models::{Contacts, Timestamp},
+};
+use leptos::prelude::*;
+use leptos::task::spawn_local;
+
+use crate::{
+    components::{Login, base::PageLink},
+    router::AppRoute,
+    shell::{use_account, use_contacts, use_intersect},
+    util::watch_to_signal,
+};
+
+#[component]
+pub fn FeedPage() -> impl IntoView {
+    let account = use_account();
+
+    move || {
+        if account.get().is_some() {
+            view! { <FeedList /> }.into_any()
+        } else {
+            view! {
+                <p>"log in to see updates from accounts you follow"</p>
+                <Login />
+            }
+            .into_any()
+        }
+    }
+}
+
+#[component]
+fn FeedList() -> impl IntoView {
+    let intersect = use_intersect();
+    let contacts = use_contacts();
+    // None while the feed is starting up
+    let items: RwSignal<Option<Result<Vec<FeedItem>, String>>> = RwSignal::new(None);
+
+    spawn_local(async move {
+        match intersect.feed().await {
+            Err(e) => items.set(Some(Err(e.to_string()))),
+            // the feed stops once this receiver (owned by the signal bridge) is dropped
+            Ok(feed) => {
+                watch_to_signal(Some(items), feed.items, |v| Some(Ok(v)));
+            }
+        }
+    });
+
+    move || {
+        match items.get() {
+        None => view! { <p class="network-loading">"loading..."</p> }.into_any(),
+        Some(Err(e)) => view! { <p class="network-error">"error: " {e}</p> }.into_any(),
+        Some(Ok(list)) if list.is_empty() => view! {
+            <p class="feed-empty">"nothing yet. follow accounts from their page to see their updates here."</p>
+        }
+        .into_any(),
+        Some(Ok(list)) => {
+            let contacts = contacts.get();
+            view! {
+                <ul class="feed">
+                    {list
+                        .iter()
+                        .map(|item| view! { <li class="feed-item">{render_item(item, &list, &contacts)}</li> })
+                        .collect_view()}
+                </ul>
+            }
+            .into_any()
+        }
+    }
+    }
+}
+
+fn render_item(item: &FeedItem, items: &[FeedItem], contacts: &Contacts) -> impl IntoView {
+    // home changes don't carry the account itself, so borrow the latest profile we've seen for it
+    let profile = match &item.change {
+        FeedChange::Account(view) => Some(view),
+        FeedChange::Home(_) => items.iter().find_map(|i| match &i.change {
+            FeedChange::Account(view) if i.account == item.account => Some(view),
+            _ => None,
+        }),
+    };
+    // prefer our own petname over whatever the account calls itself
+    let name = profile
+        .and_then(|view| {
+            contacts
+                .get(&view.public_key().fingerprint())
+                .map(|c| c.petname().as_ref().to_owned())
+                .or_else(|| view.name().map(|n| n.as_ref().to_owned()))
+        })
+        .unwrap_or("anonymous".to_string());
+    let what = match &item.change {
+        FeedChange::Account(_) => "updated their profile".to_string(),
+        FeedChange::Home(index) => format!("updated their home: {}", index.name().as_ref()),
+    };
+    // prefer the author's own edit time for home changes, otherwise when we noticed it
+    let when = match &item.change {
+        FeedChange::Home(index) => index.modified().unwrap_or(item.timestamp),
+        FeedChange::Account(_) => item.timestamp,
+    };
+
+    view! {
+        <PageLink route={AppRoute::Trace { trace: item.account.to_string() }} text=name class="feed-account" />
+        " " {what}
+        <span class="feed-when">{when.ago(Timestamp::now())}</span>
+    }
+}
@@ -4,3 +4,7 @@ mod trace;
 pub use trace::*;
 mod account;
 pub use account::*;
+mod feed;
+pub use feed::*;
+mod search;
+pub use search::*;
@@ -0,0 +1,51 @@
+use leptos::prelude::*;
+
+use crate::{
+    components::base::{PageLink, TextInput},
+    router::AppRoute,
+    shell::use_intersect,
+};
+
+const MAX_RESULTS: usize = 50;
+
+#[component]
+pub fn SearchPage() -> impl IntoView {
+    let intersect = use_intersect();
+    let query = RwSignal::new(String::new());
+    let enabled = intersect.search_enabled();
+
+    // the index is local and in memory, so just search on every keystroke
+    let results = Memo::new(move |_| intersect.search(&query.get(), MAX_RESULTS));
+
+    view! {
+        <TextInput value=query id="search" label="search: " />
+        <p class="search-hint">
+            {if enabled {
+                "searches everything you've opened on this device. nothing is sent over the network."
+            } else {
+                "local search is turned off on this device."
+            }}
+        </p>
+        <ul class="search-results">
+            {move || {
+                results
+                    .get()
+                    .into_iter()
+                    .map(|result| {
+                        let title = if result.title.is_empty() {
+                            "(untitled)".to_string()
+                        } else {
+                            result.title
+                        };
+                        view! {
+                            <li class="search-result">
+                                <PageLink route={AppRoute::Trace { trace: result.trace.to_string() }} text=title />
+                                {result.snippet.map(|s| view! { <p class="search-snippet">{s}</p> })}
+                            </li>
+                        }
+                    })
+                    .collect_view()
+            }}
+        </ul>
+    }
+}
@@ -2,12 +2,12 @@ use std::str::FromStr;
 
 use intersect_core::{
     TypedTrace,
-    documents::FragmentDocument,
+    documents::{AccountDocument, FragmentDocument},
     models::{DocumentType, Trace},
 };
 use leptos::prelude::*;
 
-use crate::components::{FragmentDisplay, use_access};
+use crate::components::{AccountDisplay, FragmentDisplay, use_access};
 
 #[component]
 pub fn TracePage(trace: String) -> impl IntoView {
@@ -22,6 +22,10 @@ pub fn TracePage(trace: String) -> impl IntoView {
             Ok(opened) => fragment_page(opened).into_any(),
             Err(_) => unreachable!("unexpected document type"),
         },
+        DocumentType::Account => match trace.into_typed::<AccountDocument>() {
+            Ok(opened) => account_page(opened).into_any(),
+            Err(_) => unreachable!("unexpected document type"),
+        },
         other => view! { <p>"unsupported document type: " {format!("{other:?}")}</p> }.into_any(),
     }
 }
@@ -38,3 +42,16 @@ fn fragment_page(opened: TypedTrace<FragmentDocument>) -> impl IntoView {
         }}
     }
 }
+
+fn account_page(opened: TypedTrace<AccountDocument>) -> impl IntoView {
+    let (resolved, access_view) = use_access(opened);
+
+    view! {
+        {access_view}
+        {move || match resolved.get() {
+            None => ().into_any(),
+            Some(Ok(account_ref)) => view! { <AccountDisplay account_ref /> }.into_any(),
+            Some(Err(e)) => view! { <p>"error: " {e}</p> }.into_any(),
+        }}
+    }
+}
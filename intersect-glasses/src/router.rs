@@ -9,7 +9,7 @@ use leptos_router::{
 use web_sys::UrlSearchParams;
 use web_sys::wasm_bindgen::JsValue;
 
-use crate::pages::{AccountPage, HomePage, TracePage};
+use crate::pages::{AccountPage, FeedPage, HomePage, SearchPage, TracePage};
 
 // matches #/<path> or #/<path>?<args>
 pub static ROUTE_REGEX: Lazy<Regex> = lazy_regex!(r"#/(?<path>[^?]*)(\?(?<args>.*))?$");
@@ -21,6 +21,8 @@ pub enum AppRoute {
     Trace { trace: String },
     NewPost,
     Account,
+    Feed,
+    Search,
     NotFound,
 }
 
@@ -63,6 +65,8 @@ impl AppRoute {
             },
             "new" => AppRoute::NewPost,
             "account" => AppRoute::Account,
+            "feed" => AppRoute::Feed,
+            "search" => AppRoute::Search,
             _ => AppRoute::NotFound,
         }
     }
@@ -78,6 +82,8 @@ impl AppRoute {
             ),
             AppRoute::NewPost => NavTarget::new("#/new", vec![]),
             AppRoute::Account => NavTarget::new("#/account", vec![]),
+            AppRoute::Feed => NavTarget::new("#/feed", vec![]),
+            AppRoute::Search => NavTarget::new("#/search", vec![]),
             AppRoute::NotFound => NavTarget::new("#/nothing", vec![]),
         }
     }
@@ -162,6 +168,8 @@ pub fn HashRouter() -> impl IntoView {
         AppRoute::Home => view! { <HomePage /> }.into_any(),
         AppRoute::Trace { trace } => view! { <TracePage trace /> }.into_any(),
         AppRoute::Account => view! { <AccountPage /> }.into_any(),
+        AppRoute::Feed => view! { <FeedPage /> }.into_any(),
+        AppRoute::Search => view! { <SearchPage /> }.into_any(),
         // TODO: replace these stubs
         AppRoute::NewPost => view! { "new post" }.into_any(),
         AppRoute::NotFound => view! { "not found" }.into_any(),
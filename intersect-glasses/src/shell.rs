@@ -1,5 +1,6 @@
 use intersect_core::{
     ConnectionParams, Intersect, NetworkState, TypedReference, documents::AccountDocument,
+    models::Contacts,
 };
 use leptos::prelude::*;
 
@@ -29,6 +30,12 @@ pub fn use_account() -> ReadSignal<Option<TypedReference<AccountDocument>>> {
         .expect("use_account called outside of Shell")
 }
 
+/// returns the reactive local contact list from context.
+/// panics if called outside of a Shell component.
+pub fn use_contacts() -> ReadSignal<Contacts> {
+    use_context::<ReadSignal<Contacts>>().expect("use_contacts called outside of Shell")
+}
+
 #[component]
 /// wrapper that sets up intersect initialisation, context, and basic page layout
 pub fn Shell(children: ChildrenFn) -> impl IntoView {
@@ -53,6 +60,8 @@ pub fn Shell(children: ChildrenFn) -> impl IntoView {
             let network = watch_to_signal(None, node.network_watch(), |v| v);
             provide_context::<ReadSignal<NetworkState>>(network.read_only());
             watch_to_signal(Some(account), node.account_watch(), |v| v);
+            let contacts = watch_to_signal(None, node.contacts_watch(), |v| v);
+            provide_context::<ReadSignal<Contacts>>(contacts.read_only());
         });
 
         init.set(true);
@@ -72,6 +81,10 @@ pub fn Shell(children: ChildrenFn) -> impl IntoView {
             <h1><PageLink route=AppRoute::Home text="./intersect/"/></h1>
             <Nav>
                 <li><PageLink route=AppRoute::NewPost text="new post"/></li>
+                <li><PageLink route=AppRoute::Search text="search"/></li>
+                <Show when=move || account.get().is_some()>
+                    <li><PageLink route=AppRoute::Feed text="feed"/></li>
+                </Show>
                 <li><PageLink route=AppRoute::Account text={ move || if account.get().is_some() { "account" } else { "log in" } }/></li>
             </Nav>
         </header>